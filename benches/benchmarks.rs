@@ -13,11 +13,13 @@
 //! 9. **logger_init**          – logger construction with various configs
 //! 10. **high_volume**         – tight loops measuring throughput
 //! 11. **concurrent_logging**  – multi-threaded shared logger
-//! 12. **visualization**       – Mermaid diagram generation from tracked components
+//! 12. **file_scaling**        – 1 to 32 threads writing to one file
+//! 13. **visualization**       – Mermaid diagram generation from tracked components
 
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use std::sync::Arc;
 use std::thread;
+use telelog::output::FileOutput;
 use telelog::{
     ChartConfig, ChartType, ComponentTracker, Config, LogLevel, Logger, MermaidGenerator,
 };
@@ -585,7 +587,53 @@ fn bench_concurrent_logging(c: &mut Criterion) {
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
-// 12. Visualization – Mermaid diagram generation
+// 12. File scaling – many threads writing to one file output
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn bench_file_scaling(c: &mut Criterion) {
+    const RECORDS_PER_THREAD: usize = 1000;
+    let dir = tempdir().expect("tempdir");
+
+    let mut group = c.benchmark_group("file_scaling");
+    group.sample_size(20);
+
+    for thread_count in [1usize, 2, 4, 8, 16, 32] {
+        let logger = Arc::new(silent_logger("bench_file_scaling"));
+        let path = dir.path().join(format!("scaling_{}.log", thread_count));
+        logger.add_output(Box::new(FileOutput::new(&path, true).expect("file output")));
+
+        group.throughput(Throughput::Elements(
+            (thread_count * RECORDS_PER_THREAD) as u64,
+        ));
+        group.bench_with_input(
+            BenchmarkId::new("threads", thread_count),
+            &thread_count,
+            |b, &n| {
+                b.iter(|| {
+                    let handles: Vec<_> = (0..n)
+                        .map(|i| {
+                            let l = Arc::clone(&logger);
+                            thread::spawn(move || {
+                                let thread = i.to_string();
+                                for _ in 0..RECORDS_PER_THREAD {
+                                    l.info_with("file event", &[("thread", &thread)]);
+                                }
+                            })
+                        })
+                        .collect();
+                    for h in handles {
+                        h.join().unwrap();
+                    }
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 13. Visualization – Mermaid diagram generation
 // ─────────────────────────────────────────────────────────────────────────────
 
 fn bench_visualization(c: &mut Criterion) {
@@ -685,6 +733,7 @@ criterion_group!(
     bench_logger_init,
     bench_high_volume,
     bench_concurrent_logging,
+    bench_file_scaling,
     bench_visualization,
 );
 criterion_main!(benches);
@@ -22,7 +22,7 @@ use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 #[cfg(feature = "system-monitor")]
 use crate::monitor::SystemMonitor;
@@ -178,6 +178,7 @@ pub struct ComponentTracker {
     components: RwLock<HashMap<String, Component>>,
     current_stack: RwLock<Vec<String>>,
     next_id: RwLock<u64>,
+    epoch: (Instant, SystemTime),
 }
 
 impl ComponentTracker {
@@ -187,6 +188,27 @@ impl ComponentTracker {
             components: RwLock::new(HashMap::new()),
             current_stack: RwLock::new(Vec::new()),
             next_id: RwLock::new(0),
+            epoch: (Instant::now(), SystemTime::now()),
+        }
+    }
+
+    /// Returns the number of components currently running, i.e. the nesting
+    /// depth of a component started now.
+    pub fn depth(&self) -> usize {
+        self.current_stack.read().len()
+    }
+
+    /// Converts an [`Instant`] recorded by this tracker, such as a component's
+    /// start or end time, to wall-clock time.
+    ///
+    /// Conversions use a fixed reference point taken when the tracker was
+    /// created, so the same instant always maps to the same time.
+    pub fn system_time(&self, instant: Instant) -> SystemTime {
+        let (epoch_instant, epoch_time) = self.epoch;
+        if instant >= epoch_instant {
+            epoch_time + instant.duration_since(epoch_instant)
+        } else {
+            epoch_time - epoch_instant.duration_since(instant)
         }
     }
 
@@ -471,6 +493,25 @@ impl Drop for ComponentGuard {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_system_time_is_stable() {
+        let tracker = ComponentTracker::new();
+        let id = tracker.start_component("timed");
+        tracker
+            .end_component(&id, ComponentStatus::Success)
+            .unwrap();
+
+        let component = &tracker.get_components()[&id];
+        let start = tracker.system_time(component.start_time);
+        let end = tracker.system_time(component.end_time.unwrap());
+        assert_eq!(start, tracker.system_time(component.start_time));
+        assert_eq!(
+            end.duration_since(start).unwrap(),
+            component.duration().unwrap()
+        );
+        assert!(start <= SystemTime::now());
+    }
+
     #[test]
     fn test_component_creation() {
         let tracker = ComponentTracker::new();
@@ -20,9 +20,16 @@
 //! ```
 
 use crate::level::LogLevel;
+use crate::output::background::{self, OverflowPolicy};
+use crate::output::console::{ColorMode, ColorTheme, ConsoleFormat, ConsoleTarget};
+use crate::output::durability::SyncPolicy;
+use crate::output::pretty::PrettyStyle;
+use crate::output::rotation::{Compression, RotationSchedule, RotationTimeZone};
+use crate::output::ErrorHandler;
 use crate::visualization::{ChartConfig, ChartType};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// Output-related configuration options.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +41,31 @@ pub struct OutputConfig {
     pub json_format: bool,
     pub max_file_size: u64,
     pub max_files: u32,
+    #[serde(default)]
+    pub rotation_schedule: RotationSchedule,
+    #[serde(default)]
+    pub rotation_time_zone: RotationTimeZone,
+    #[serde(default)]
+    pub compression: Compression,
+    #[serde(default)]
+    pub max_file_age: Option<Duration>,
+    #[serde(default)]
+    pub max_total_size: Option<u64>,
+    #[serde(default)]
+    pub reopen_check_interval: Option<Duration>,
+    #[serde(default)]
+    pub multi_process: bool,
+    #[serde(default)]
+    pub sync_policy: SyncPolicy,
+    #[serde(default)]
+    pub console_target: ConsoleTarget,
+    /// Ignored when `colored_output` is `false`.
+    #[serde(default)]
+    pub color_mode: ColorMode,
+    #[serde(default)]
+    pub color_theme: ColorTheme,
+    #[serde(default)]
+    pub console_format: ConsoleFormat,
 }
 
 impl Default for OutputConfig {
@@ -46,6 +78,18 @@ impl Default for OutputConfig {
             json_format: false,
             max_file_size: 10 * 1024 * 1024,
             max_files: 5,
+            rotation_schedule: RotationSchedule::Never,
+            rotation_time_zone: RotationTimeZone::Utc,
+            compression: Compression::None,
+            max_file_age: None,
+            max_total_size: None,
+            reopen_check_interval: None,
+            multi_process: false,
+            sync_policy: SyncPolicy::Never,
+            console_target: ConsoleTarget::Stdout,
+            color_mode: ColorMode::Auto,
+            color_theme: ColorTheme::default(),
+            console_format: ConsoleFormat::Plain,
         }
     }
 }
@@ -58,8 +102,42 @@ pub struct PerformanceConfig {
     pub component_tracking_enabled: bool,
     pub buffer_size: usize,
     pub buffering_enabled: bool,
-    #[cfg(feature = "async")]
+    /// Flushes the buffer once its records reach this many bytes.
+    #[serde(default)]
+    pub buffer_max_bytes: Option<usize>,
+    /// Flushes the buffer once its oldest record has waited this long.
+    #[serde(default)]
+    pub buffer_max_age: Option<Duration>,
+    /// Flushes the buffer as soon as a record at or above this level arrives.
+    #[serde(default)]
+    pub buffer_flush_level: Option<LogLevel>,
+    /// Writes records on a background thread; see [`BackgroundOutput`](crate::output::BackgroundOutput).
+    #[serde(default)]
     pub async_enabled: bool,
+    /// Records the background writer queues before the overflow policy applies.
+    #[serde(default = "default_async_capacity")]
+    pub async_capacity: usize,
+    /// Queued records that wake the background writer early.
+    #[serde(default = "default_async_batch_size")]
+    pub async_batch_size: usize,
+    /// Longest time records wait in the background writer's queue.
+    #[serde(default = "default_async_flush_interval")]
+    pub async_flush_interval: Duration,
+    /// What the background writer does with records when its queue is full.
+    #[serde(default)]
+    pub overflow_policy: OverflowPolicy,
+}
+
+fn default_async_capacity() -> usize {
+    background::DEFAULT_CAPACITY
+}
+
+fn default_async_batch_size() -> usize {
+    background::DEFAULT_BATCH_SIZE
+}
+
+fn default_async_flush_interval() -> Duration {
+    background::DEFAULT_INTERVAL
 }
 
 impl Default for PerformanceConfig {
@@ -70,8 +148,14 @@ impl Default for PerformanceConfig {
             component_tracking_enabled: false,
             buffer_size: 1024,
             buffering_enabled: false,
-            #[cfg(feature = "async")]
+            buffer_max_bytes: None,
+            buffer_max_age: None,
+            buffer_flush_level: None,
             async_enabled: false,
+            async_capacity: default_async_capacity(),
+            async_batch_size: default_async_batch_size(),
+            async_flush_interval: default_async_flush_interval(),
+            overflow_policy: OverflowPolicy::default(),
         }
     }
 }
@@ -84,6 +168,75 @@ pub struct VisualizationConfig {
     pub output_directory: Option<PathBuf>,
 }
 
+/// Flight recorder settings: records below the logger's minimum level are kept
+/// in memory and written ahead of the next error from the same thread or request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlightRecorderConfig {
+    /// Lowest level that is captured; records below it are discarded as usual.
+    pub capture_level: LogLevel,
+    /// Records at or above this level flush the captured records.
+    pub trigger_level: LogLevel,
+    /// Maximum records kept per thread or request; the oldest are dropped first.
+    pub capacity: usize,
+    /// Context or data key identifying a request, e.g. `request_id`. Records
+    /// without it, or all records when unset, are grouped by thread.
+    pub key: Option<String>,
+    /// Maximum number of requests with captured records; the least recently
+    /// used buffer is discarded when exceeded. Each thread keeps one buffer of
+    /// its own, which is not counted.
+    pub max_buffers: usize,
+}
+
+impl Default for FlightRecorderConfig {
+    fn default() -> Self {
+        Self {
+            capture_level: LogLevel::Debug,
+            trigger_level: LogLevel::Error,
+            capacity: 100,
+            key: None,
+            max_buffers: 1024,
+        }
+    }
+}
+
+impl FlightRecorderConfig {
+    /// Creates a flight recorder configuration with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the lowest level that is captured (`Debug` by default).
+    pub fn with_capture_level(mut self, level: LogLevel) -> Self {
+        self.capture_level = level;
+        self
+    }
+
+    /// Sets the level that flushes captured records (`Error` by default).
+    pub fn with_trigger_level(mut self, level: LogLevel) -> Self {
+        self.trigger_level = level;
+        self
+    }
+
+    /// Sets how many records are kept per thread or request (100 by default).
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Groups captured records by the value of a context or data key instead
+    /// of by thread.
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    /// Sets how many requests are tracked at once (1024 by default).
+    pub fn with_max_buffers(mut self, max_buffers: usize) -> Self {
+        self.max_buffers = max_buffers;
+        self
+    }
+}
+
 /// Main configuration for the logger.
 ///
 /// Combines output, performance, and visualization settings with a builder pattern
@@ -94,6 +247,12 @@ pub struct Config {
     pub output: OutputConfig,
     pub performance: PerformanceConfig,
     pub visualization: VisualizationConfig,
+    /// Keeps filtered-out records for replay ahead of errors; disabled when `None`.
+    #[serde(default)]
+    pub flight_recorder: Option<FlightRecorderConfig>,
+    /// Receives output errors instead of stderr; not serialized.
+    #[serde(skip)]
+    pub error_handler: Option<ErrorHandler>,
 }
 
 impl Default for Config {
@@ -103,6 +262,8 @@ impl Default for Config {
             output: OutputConfig::default(),
             performance: PerformanceConfig::default(),
             visualization: VisualizationConfig::default(),
+            flight_recorder: None,
+            error_handler: None,
         }
     }
 }
@@ -139,11 +300,39 @@ impl Config {
     }
 
     /// Enables or disables colored console output.
+    ///
+    /// When enabled, colors are still only used where the [`ColorMode`]
+    /// allows them.
     pub fn with_colored_output(mut self, enabled: bool) -> Self {
         self.output.colored_output = enabled;
         self
     }
 
+    /// Sets whether console output goes to stdout, stderr, or is split by level.
+    pub fn with_console_target(mut self, target: ConsoleTarget) -> Self {
+        self.output.console_target = target;
+        self
+    }
+
+    /// Sets when console output is colored (automatic by default).
+    pub fn with_color_mode(mut self, mode: ColorMode) -> Self {
+        self.output.color_mode = mode;
+        self
+    }
+
+    /// Sets how console output is rendered, e.g. [`ConsoleFormat::Pretty`]
+    /// for local development.
+    pub fn with_console_format(mut self, format: ConsoleFormat) -> Self {
+        self.output.console_format = format;
+        self
+    }
+
+    /// Sets the colors used for each level in console output.
+    pub fn with_color_theme(mut self, theme: ColorTheme) -> Self {
+        self.output.color_theme = theme;
+        self
+    }
+
     /// Enables or disables performance profiling.
     pub fn with_profiling(mut self, enabled: bool) -> Self {
         self.performance.profiling_enabled = enabled;
@@ -162,6 +351,26 @@ impl Config {
         self
     }
 
+    /// Flushes buffered output once the buffered records reach `max_bytes`.
+    pub fn with_buffer_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.performance.buffer_max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Flushes buffered output once the oldest buffered record has waited
+    /// for `max_age`, even if no further records arrive.
+    pub fn with_buffer_max_age(mut self, max_age: Duration) -> Self {
+        self.performance.buffer_max_age = Some(max_age);
+        self
+    }
+
+    /// Flushes buffered output immediately when a record at or above `level`
+    /// is logged.
+    pub fn with_buffer_flush_level(mut self, level: LogLevel) -> Self {
+        self.performance.buffer_flush_level = Some(level);
+        self
+    }
+
     /// Configures file rotation with maximum file size and file count.
     pub fn with_file_rotation(mut self, max_size: u64, max_files: u32) -> Self {
         self.output.max_file_size = max_size;
@@ -169,13 +378,94 @@ impl Config {
         self
     }
 
-    /// Enables or disables async output (requires `async` feature).
-    #[cfg(feature = "async")]
+    /// Rotates the log file on time boundaries in addition to size.
+    ///
+    /// Rotated files are named after the period they cover, e.g. `app.2026-10-16.log`.
+    pub fn with_rotation_schedule(mut self, schedule: RotationSchedule) -> Self {
+        self.output.rotation_schedule = schedule;
+        self
+    }
+
+    /// Sets the time zone in which rotation boundaries are computed.
+    pub fn with_rotation_time_zone(mut self, time_zone: RotationTimeZone) -> Self {
+        self.output.rotation_time_zone = time_zone;
+        self
+    }
+
+    /// Compresses rotated log files in the background (requires `gzip` or `zstd` feature).
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.output.compression = compression;
+        self
+    }
+
+    /// Deletes rotated log files older than `max_age`.
+    pub fn with_max_file_age(mut self, max_age: Duration) -> Self {
+        self.output.max_file_age = Some(max_age);
+        self
+    }
+
+    /// Caps the combined size of the active and rotated log files.
+    pub fn with_max_total_size(mut self, max_total_size: u64) -> Self {
+        self.output.max_total_size = Some(max_total_size);
+        self
+    }
+
+    /// Periodically checks whether the log file was moved or deleted externally
+    /// and reopens it at the configured path.
+    pub fn with_reopen_check(mut self, interval: Duration) -> Self {
+        self.output.reopen_check_interval = Some(interval);
+        self
+    }
+
+    /// Enables safe appends and coordinated rotation when several processes
+    /// write to the same log file.
+    pub fn with_multi_process(mut self, enabled: bool) -> Self {
+        self.output.multi_process = enabled;
+        self
+    }
+
+    /// Sets when file output is flushed and synced to disk.
+    pub fn with_sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.output.sync_policy = policy;
+        self
+    }
+
+    /// Enables or disables writing records on a background thread.
+    ///
+    /// Works without an async runtime; records that do not fit in the queue
+    /// are dropped and counted.
     pub fn with_async(mut self, enabled: bool) -> Self {
         self.performance.async_enabled = enabled;
         self
     }
 
+    /// Sets how many records the background writer queues (1000 by default).
+    pub fn with_async_capacity(mut self, capacity: usize) -> Self {
+        self.performance.async_capacity = capacity;
+        self
+    }
+
+    /// Sets how many queued records wake the background writer early (100 by
+    /// default).
+    pub fn with_async_batch_size(mut self, batch_size: usize) -> Self {
+        self.performance.async_batch_size = batch_size;
+        self
+    }
+
+    /// Sets how long records may wait before the background writer writes
+    /// them (100ms by default).
+    pub fn with_async_flush_interval(mut self, interval: Duration) -> Self {
+        self.performance.async_flush_interval = interval;
+        self
+    }
+
+    /// Sets what the background writer does when its queue is full (drop
+    /// newest by default).
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.performance.overflow_policy = policy;
+        self
+    }
+
     /// Enables or disables buffered output.
     pub fn with_buffering(mut self, enabled: bool) -> Self {
         self.performance.buffering_enabled = enabled;
@@ -206,14 +496,43 @@ impl Config {
         self
     }
 
+    /// Enables the flight recorder.
+    ///
+    /// Records below the minimum level but at or above
+    /// [`capture_level`](FlightRecorderConfig::capture_level) are kept in
+    /// memory instead of being discarded, and written, marked with
+    /// `backfilled=true`, ahead of the next record at
+    /// [`trigger_level`](FlightRecorderConfig::trigger_level) from the same
+    /// thread or request.
+    pub fn with_flight_recorder(mut self, recorder: FlightRecorderConfig) -> Self {
+        self.flight_recorder = Some(recorder);
+        self
+    }
+
+    /// Calls `handler` with the destination name and error whenever an output
+    /// fails, instead of printing to stderr.
+    ///
+    /// Use it to raise an alert when logging itself breaks. The handler may be
+    /// called from background writer threads and must not log through the
+    /// failing logger.
+    pub fn with_error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&str, &std::io::Error) + Send + Sync + 'static,
+    {
+        self.error_handler = Some(ErrorHandler::new(handler));
+        self
+    }
+
     /// Creates a preset development configuration.
     ///
-    /// Includes debug logging, colored console output, profiling, and component tracking.
+    /// Includes debug logging, colored pretty console output, profiling, and
+    /// component tracking.
     pub fn development() -> Self {
         Self::new()
             .with_min_level(LogLevel::Debug)
             .with_console_output(true)
             .with_colored_output(true)
+            .with_console_format(ConsoleFormat::Pretty(PrettyStyle::new()))
             .with_profiling(true)
             .with_component_tracking(true)
     }
@@ -257,8 +576,10 @@ impl Config {
     ///
     /// Returns an error if:
     /// - File output is enabled but no path is specified
-    /// - Buffer size is zero
+    /// - Buffer size or buffer max bytes is zero
+    /// - The async capacity or batch size is zero
     /// - Max file size is zero
+    /// - The flight recorder capacity or max buffers is zero
     /// - Auto-generate charts is enabled without chart configuration
     pub fn validate(&self) -> Result<(), String> {
         if self.output.file_enabled && self.output.file_path.is_none() {
@@ -269,10 +590,26 @@ impl Config {
             return Err("Buffer size must be greater than 0".to_string());
         }
 
+        if self.performance.buffer_max_bytes == Some(0) {
+            return Err("Buffer max bytes must be greater than 0".to_string());
+        }
+
+        if self.performance.async_capacity == 0 || self.performance.async_batch_size == 0 {
+            return Err("Async capacity and batch size must be greater than 0".to_string());
+        }
+
         if self.output.max_file_size == 0 {
             return Err("Max file size must be greater than 0".to_string());
         }
 
+        if let Some(recorder) = &self.flight_recorder {
+            if recorder.capacity == 0 || recorder.max_buffers == 0 {
+                return Err(
+                    "Flight recorder capacity and max buffers must be greater than 0".to_string(),
+                );
+            }
+        }
+
         if self.visualization.auto_generate_charts && self.visualization.chart_config.is_none() {
             return Err(
                 "Auto-generate charts enabled but no chart configuration provided".to_string(),
@@ -313,6 +650,10 @@ mod tests {
         assert_eq!(config.min_level, LogLevel::Debug);
         assert!(config.output.console_enabled);
         assert!(config.output.colored_output);
+        assert!(matches!(
+            config.output.console_format,
+            ConsoleFormat::Pretty(_)
+        ));
         assert!(config.performance.profiling_enabled);
         assert!(config.performance.component_tracking_enabled);
     }
@@ -356,4 +697,112 @@ mod tests {
         assert_eq!(config.output.max_files, 10);
         assert!(config.visualization.auto_generate_charts);
     }
+
+    #[test]
+    fn test_rotation_schedule_config() {
+        let config = Config::new()
+            .with_rotation_schedule(RotationSchedule::Daily)
+            .with_rotation_time_zone(RotationTimeZone::Local);
+
+        assert_eq!(config.output.rotation_schedule, RotationSchedule::Daily);
+        assert_eq!(config.output.rotation_time_zone, RotationTimeZone::Local);
+        assert_eq!(
+            Config::default().output.rotation_schedule,
+            RotationSchedule::Never
+        );
+    }
+
+    #[test]
+    fn test_retention_config() {
+        let config = Config::new()
+            .with_max_file_age(Duration::from_secs(7 * 86400))
+            .with_max_total_size(100 * 1024 * 1024);
+
+        assert_eq!(config.output.compression, Compression::None);
+        assert_eq!(
+            config.output.max_file_age,
+            Some(Duration::from_secs(7 * 86400))
+        );
+        assert_eq!(config.output.max_total_size, Some(100 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_reopen_check_config() {
+        let config = Config::new().with_reopen_check(Duration::from_secs(5));
+        assert_eq!(
+            config.output.reopen_check_interval,
+            Some(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_multi_process_config() {
+        assert!(!Config::default().output.multi_process);
+        assert!(Config::new().with_multi_process(true).output.multi_process);
+    }
+
+    #[test]
+    fn test_sync_policy_config() {
+        assert_eq!(Config::default().output.sync_policy, SyncPolicy::Never);
+        let config = Config::new().with_sync_policy(SyncPolicy::AtLevel(LogLevel::Error));
+        assert_eq!(
+            config.output.sync_policy,
+            SyncPolicy::AtLevel(LogLevel::Error)
+        );
+    }
+
+    #[test]
+    fn test_buffer_flush_config() {
+        let config = Config::new()
+            .with_buffering(true)
+            .with_buffer_max_bytes(64 * 1024)
+            .with_buffer_max_age(Duration::from_secs(2))
+            .with_buffer_flush_level(LogLevel::Error);
+
+        assert_eq!(config.performance.buffer_max_bytes, Some(64 * 1024));
+        assert_eq!(
+            config.performance.buffer_max_age,
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(config.performance.buffer_flush_level, Some(LogLevel::Error));
+        assert!(config.validate().is_ok());
+        assert!(Config::new().with_buffer_max_bytes(0).validate().is_err());
+    }
+
+    #[test]
+    fn test_async_config() {
+        let policy = OverflowPolicy::Block {
+            timeout: Duration::from_millis(20),
+        };
+        let config = Config::new()
+            .with_async(true)
+            .with_async_capacity(10_000)
+            .with_async_batch_size(256)
+            .with_async_flush_interval(Duration::from_millis(50))
+            .with_overflow_policy(policy);
+
+        assert_eq!(config.performance.async_capacity, 10_000);
+        assert_eq!(config.performance.async_batch_size, 256);
+        assert_eq!(
+            config.performance.async_flush_interval,
+            Duration::from_millis(50)
+        );
+        assert_eq!(config.performance.overflow_policy, policy);
+        assert!(config.validate().is_ok());
+        assert!(Config::new().with_async_capacity(0).validate().is_err());
+
+        // Configurations saved before these settings existed still load.
+        let mut value = serde_json::to_value(PerformanceConfig::default()).unwrap();
+        for key in [
+            "async_capacity",
+            "async_batch_size",
+            "async_flush_interval",
+            "overflow_policy",
+        ] {
+            value.as_object_mut().unwrap().remove(key);
+        }
+        let loaded: PerformanceConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(loaded.async_capacity, 1000);
+        assert_eq!(loaded.overflow_policy, OverflowPolicy::DropNewest);
+    }
 }
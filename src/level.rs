@@ -46,6 +46,9 @@ impl LogLevel {
     }
 
     /// Returns the ANSI color code for terminal output (requires `console` feature).
+    ///
+    /// These are the colors of the default
+    /// [`ColorTheme`](crate::output::ColorTheme), which console output uses.
     #[cfg(feature = "console")]
     pub fn color(&self) -> &'static str {
         match self {
@@ -57,6 +60,33 @@ impl LogLevel {
         }
     }
 
+    /// Returns the syslog severity (RFC 5424 §6.2.1) for this level.
+    ///
+    /// The same numbering is used by journald's `PRIORITY` field and GELF's `level`.
+    pub fn syslog_severity(&self) -> u8 {
+        match self {
+            LogLevel::Debug => 7,
+            LogLevel::Info => 6,
+            LogLevel::Warning => 4,
+            LogLevel::Error => 3,
+            LogLevel::Critical => 2,
+        }
+    }
+
+    /// Returns the OpenTelemetry `SeverityNumber` for this level.
+    ///
+    /// Each level maps to the first number of its OpenTelemetry range, with
+    /// `Critical` mapped to `FATAL`.
+    pub fn otel_severity_number(&self) -> i32 {
+        match self {
+            LogLevel::Debug => 5,
+            LogLevel::Info => 9,
+            LogLevel::Warning => 13,
+            LogLevel::Error => 17,
+            LogLevel::Critical => 21,
+        }
+    }
+
     /// Determines if this log level should be logged given the minimum level.
     ///
     /// Returns `true` if this level is equal to or higher than the minimum level.
@@ -114,6 +144,20 @@ mod tests {
         assert!(LogLevel::Info.should_log(LogLevel::Info));
     }
 
+    #[test]
+    fn test_syslog_severity() {
+        assert_eq!(LogLevel::Debug.syslog_severity(), 7);
+        assert_eq!(LogLevel::Warning.syslog_severity(), 4);
+        assert_eq!(LogLevel::Critical.syslog_severity(), 2);
+    }
+
+    #[test]
+    fn test_otel_severity_number() {
+        assert_eq!(LogLevel::Debug.otel_severity_number(), 5);
+        assert_eq!(LogLevel::Info.otel_severity_number(), 9);
+        assert_eq!(LogLevel::Critical.otel_severity_number(), 21);
+    }
+
     #[test]
     fn test_from_str() {
         assert_eq!("INFO".parse::<LogLevel>().unwrap(), LogLevel::Info);
@@ -27,33 +27,39 @@
 //! // Your expensive operation here
 //! ```
 //!
-//! ## Async Support
+//! ## Background Writing
 //!
-//! Enable the `async` feature for bounded async output with backpressure:
+//! `Config::with_async(true)` moves writing to a dedicated thread with a
+//! bounded queue; no async runtime is needed. Wrap a single destination in
+//! [`output::BackgroundOutput`] to choose the queue size and what happens when
+//! it is full:
 //!
-//! ```rust,ignore
-//! use telelog::{Logger, AsyncOutput};
+//! ```no_run
+//! use telelog::output::{BackgroundOutput, FileOutput, OverflowPolicy};
+//! use telelog::Logger;
+//! use std::sync::Arc;
 //!
-//! #[tokio::main]
-//! async fn main() {
-//!     let logger = Logger::new("app");
-//!     let mut async_output = AsyncOutput::new();
-//!     logger.add_output(Box::new(async_output));
-//!     logger.info("Async logging!");
-//! }
+//! let logger = Logger::new("app");
+//! let file = Arc::new(FileOutput::new("app.log", true).unwrap());
+//! logger.add_output(Box::new(
+//!     BackgroundOutput::new(file).with_overflow_policy(OverflowPolicy::DropOldest),
+//! ));
+//! logger.info("Written in the background");
 //! ```
 //!
+//! The `async` feature adds `AsyncOutput`, which does the same on a Tokio task.
+//!
 //! ## Performance
 //!
 //! - **~788ns** per log with thread-local buffer pooling
 //! - **~11ns** level check overhead (nearly free when filtered)
-//! - **Bounded async** channels with backpressure (capacity: 1000)
+//! - **Bounded background queues** with configurable overflow policies
 //!
 //! ## Features
 //!
 //! - **Thread-safe** logging with parking_lot
 //! - **Optimized allocations** with thread-local buffer pooling
-//! - **Optional features**: async, system-monitor, console, python
+//! - **Optional features**: async, system-monitor, console, python, gzip, zstd, signal, journald, tls, http, otlp, fluentd
 
 pub mod component;
 pub mod config;
@@ -62,6 +68,7 @@ pub mod level;
 pub mod logger;
 pub mod output;
 pub mod profile;
+pub mod recorder;
 pub mod visualization;
 
 #[cfg(feature = "system-monitor")]
@@ -70,11 +77,14 @@ pub mod monitor;
 pub use component::{
     Component, ComponentGuard, ComponentMetadata, ComponentStatus, ComponentTracker,
 };
-pub use config::Config;
+pub use config::{Config, FlightRecorderConfig};
 pub use context::{Context, ContextGuard};
 pub use level::LogLevel;
 pub use logger::Logger;
-pub use output::{BufferedOutput, ConsoleOutput, FileOutput, MultiOutput};
+pub use output::{
+    BufferedOutput, Compression, ConsoleOutput, FileOutput, MultiOutput, RotationSchedule,
+    RotationTimeZone,
+};
 pub use profile::ProfileGuard;
 pub use visualization::{ChartConfig, ChartType, Direction, MermaidGenerator};
 
@@ -133,6 +143,23 @@ mod tests {
         assert_eq!(logger.name(), "test");
     }
 
+    #[test]
+    fn test_add_output_survives_set_config() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let logger = Logger::with_config("test", Config::new().with_console_output(false));
+        let output = std::sync::Arc::new(FileOutput::new(file.path(), false).unwrap());
+        logger.add_output(Box::new(std::sync::Arc::clone(&output)));
+
+        logger.info("before");
+        logger.set_config(Config::new().with_console_output(false));
+        logger.info("after");
+        output::OutputDestination::flush(&*output).unwrap();
+
+        let contents = std::fs::read_to_string(file.path()).unwrap();
+        assert!(contents.contains("test: before"));
+        assert!(contents.contains("test: after"));
+    }
+
     #[test]
     fn test_version() {
         assert!(!VERSION.is_empty());
@@ -1,12 +1,17 @@
 use crate::component::{ComponentGuard, ComponentTracker};
-use crate::output::{LogRecord, OutputDestination};
+use crate::output::{report_error, LogRecord, MultiOutput, OutputDestination, Route};
 
-pub struct OutputPipeline(pub Arc<dyn OutputDestination>);
+/// The destination records are written to, and the router inside it that
+/// [`Logger::add_output`] appends to.
+pub struct OutputPipeline(pub Arc<dyn OutputDestination>, Arc<MultiOutput>);
 use crate::{config::Config, context::Context, level::LogLevel};
 
-use arc_swap::ArcSwap;
+use crate::recorder::FlightRecorder;
+use arc_swap::{ArcSwap, ArcSwapOption};
 use std::cell::RefCell;
 use std::fmt::Write;
+#[cfg(all(unix, feature = "signal"))]
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
 
@@ -22,10 +27,14 @@ pub struct Logger {
     min_level: AtomicU8,
     config: ArcSwap<Config>,
     output: ArcSwap<OutputPipeline>,
+    extra_outputs: Arc<parking_lot::Mutex<Vec<Arc<dyn OutputDestination>>>>,
+    recorder: ArcSwapOption<FlightRecorder>,
     context: Arc<Context>,
     component_tracker: Arc<ComponentTracker>,
     #[cfg(feature = "system-monitor")]
     system_monitor: Arc<parking_lot::RwLock<SystemMonitor>>,
+    #[cfg(all(unix, feature = "signal"))]
+    reopen_requested: Arc<AtomicBool>,
 }
 
 impl Logger {
@@ -35,53 +44,69 @@ impl Logger {
 
     pub fn with_config(name: &str, config: Config) -> Self {
         config.validate().expect("Invalid Logger Configuration");
-        let output = build_output_pipeline(&config);
-        let output = Arc::new(OutputPipeline(output));
+        let component_tracker = Arc::new(ComponentTracker::new());
+        let output = Arc::new(build_output_pipeline(&config, &[], &component_tracker));
+        let recorder = build_recorder(&config);
 
         Self {
             name: Arc::from(name),
             min_level: AtomicU8::new(config.min_level as u8),
             config: ArcSwap::from_pointee(config),
             output: ArcSwap::from(output),
+            extra_outputs: Arc::default(),
+            recorder: ArcSwapOption::new(recorder),
             context: Arc::new(Context::new()),
-            component_tracker: Arc::new(ComponentTracker::new()),
+            component_tracker,
             #[cfg(feature = "system-monitor")]
             system_monitor: Arc::new(parking_lot::RwLock::new(SystemMonitor::new())),
+            #[cfg(all(unix, feature = "signal"))]
+            reopen_requested: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    #[track_caller]
     pub fn debug(&self, m: &str) {
         self.log(LogLevel::Debug, m, None);
     }
+    #[track_caller]
     pub fn info(&self, m: &str) {
         self.log(LogLevel::Info, m, None);
     }
+    #[track_caller]
     pub fn warning(&self, m: &str) {
         self.log(LogLevel::Warning, m, None);
     }
+    #[track_caller]
     pub fn error(&self, m: &str) {
         self.log(LogLevel::Error, m, None);
     }
+    #[track_caller]
     pub fn critical(&self, m: &str) {
         self.log(LogLevel::Critical, m, None);
     }
 
+    #[track_caller]
     pub fn debug_with(&self, m: &str, d: &[(&str, &str)]) {
         self.log(LogLevel::Debug, m, Some(d));
     }
+    #[track_caller]
     pub fn info_with(&self, m: &str, d: &[(&str, &str)]) {
         self.log(LogLevel::Info, m, Some(d));
     }
+    #[track_caller]
     pub fn warning_with(&self, m: &str, d: &[(&str, &str)]) {
         self.log(LogLevel::Warning, m, Some(d));
     }
+    #[track_caller]
     pub fn error_with(&self, m: &str, d: &[(&str, &str)]) {
         self.log(LogLevel::Error, m, Some(d));
     }
+    #[track_caller]
     pub fn critical_with(&self, m: &str, d: &[(&str, &str)]) {
         self.log(LogLevel::Critical, m, Some(d));
     }
 
+    #[track_caller]
     pub fn log_with(&self, level: LogLevel, message: &str, data: &[(&str, &str)]) {
         self.log(level, message, Some(data));
     }
@@ -102,10 +127,29 @@ impl Logger {
     }
 
     #[inline]
+    #[track_caller]
     fn log(&self, level: LogLevel, message: &str, data: Option<&[(&str, &str)]>) {
         if (level as u8) < self.min_level.load(Ordering::Relaxed) {
+            if let Some(recorder) = &*self.recorder.load() {
+                if recorder.captures(level) {
+                    let location = std::panic::Location::caller();
+                    let context = self.context.data.read();
+                    recorder.capture(&self.name, level, message, &context, data, location);
+                }
+            }
             return;
         }
+        let location = std::panic::Location::caller();
+
+        #[cfg(all(unix, feature = "signal"))]
+        if self.reopen_requested.load(Ordering::Relaxed)
+            && self.reopen_requested.swap(false, Ordering::AcqRel)
+        {
+            if let Err(e) = self.reopen_files() {
+                let error = std::io::Error::new(e.kind(), format!("reopen failed: {}", e));
+                self.report_error(&self.output.load().0, &error);
+            }
+        }
 
         TIMESTAMP_BUF.with(|buf| {
             let mut b = buf.borrow_mut();
@@ -119,20 +163,79 @@ impl Logger {
                 message,
                 context: &self.context.data.read(),
                 data,
+                location: Some(location),
             };
 
-            if let Err(e) = self.output.load().0.write(&record) {
-                eprintln!("[Telelog] Write error: {}", e);
+            let output = self.output.load();
+            if let Some(recorder) = &*self.recorder.load() {
+                if recorder.triggers(level) {
+                    for backfilled in recorder.take(record.context, data) {
+                        if let Err(e) = backfilled.with_record(|r| output.0.write(r)) {
+                            self.report_error(&output.0, &e);
+                        }
+                    }
+                }
+            }
+
+            if let Err(e) = output.0.write(&record) {
+                self.report_error(&output.0, &e);
             }
         });
     }
 
+    #[cold]
+    fn report_error(&self, output: &Arc<dyn OutputDestination>, error: &std::io::Error) {
+        let config = self.config.load();
+        report_error(config.error_handler.as_ref(), output.name(), error);
+    }
+
+    /// Closes and reopens all log files, e.g. after logrotate moved them away.
+    pub fn reopen_files(&self) -> std::io::Result<()> {
+        self.output.load().0.reopen()
+    }
+
+    /// Reopens log files when the process receives `SIGHUP` (requires `signal` feature).
+    ///
+    /// The signal handler only sets a flag; files are reopened by the next
+    /// logging call on this logger or any of its clones.
+    #[cfg(all(unix, feature = "signal"))]
+    pub fn reopen_on_sighup(&self) -> std::io::Result<()> {
+        signal_hook::flag::register(
+            signal_hook::consts::SIGHUP,
+            Arc::clone(&self.reopen_requested),
+        )?;
+        Ok(())
+    }
+
+    /// Adds a destination such as a [`NetworkOutput`](crate::output::NetworkOutput)
+    /// alongside the console and file outputs built from the configuration.
+    ///
+    /// The destination is appended to the running pipeline; existing outputs
+    /// and background threads are left untouched. Added outputs are shared
+    /// with clones of this logger and kept when the configuration is changed
+    /// with [`set_config`](Self::set_config).
+    pub fn add_output(&self, output: Box<dyn OutputDestination>) {
+        let output: Arc<dyn OutputDestination> = Arc::from(output);
+        let mut extra_outputs = self.extra_outputs.lock();
+        extra_outputs.push(Arc::clone(&output));
+        self.output
+            .load()
+            .1
+            .push_output(Box::new(output), Route::All);
+    }
+
     pub fn set_config(&self, config: Config) {
         if config.validate().is_ok() {
             self.min_level
                 .store(config.min_level as u8, Ordering::Release);
-            let new_pipeline = Arc::new(OutputPipeline(build_output_pipeline(&config)));
+            let extra_outputs = self.extra_outputs.lock();
+            let new_pipeline = Arc::new(build_output_pipeline(
+                &config,
+                &extra_outputs,
+                &self.component_tracker,
+            ));
             self.output.store(new_pipeline);
+            self.recorder.store(build_recorder(&config));
             self.config.store(Arc::new(config));
         }
     }
@@ -194,69 +297,173 @@ impl Clone for Logger {
             min_level: AtomicU8::new(self.min_level.load(Ordering::Relaxed)),
             config: ArcSwap::from(self.config.load_full()),
             output: ArcSwap::from(self.output.load_full()),
+            extra_outputs: Arc::clone(&self.extra_outputs),
+            recorder: ArcSwapOption::new(self.recorder.load_full()),
             context: Arc::clone(&self.context),
             component_tracker: Arc::clone(&self.component_tracker),
             #[cfg(feature = "system-monitor")]
             system_monitor: Arc::clone(&self.system_monitor),
+            #[cfg(all(unix, feature = "signal"))]
+            reopen_requested: Arc::clone(&self.reopen_requested),
         }
     }
 }
 
-pub(crate) fn build_output_pipeline(config: &Config) -> Arc<dyn OutputDestination> {
+fn build_recorder(config: &Config) -> Option<Arc<FlightRecorder>> {
+    config
+        .flight_recorder
+        .clone()
+        .map(|recorder| Arc::new(FlightRecorder::new(recorder)))
+}
+
+pub(crate) fn build_output_pipeline(
+    config: &Config,
+    extra_outputs: &[Arc<dyn OutputDestination>],
+    component_tracker: &Arc<ComponentTracker>,
+) -> OutputPipeline {
     use crate::output::{
-        BufferedOutput, ConsoleOutput, FileOutput, MultiOutput, RotatingFileOutput,
+        BackgroundOutput, BufferedOutput, ColorMode, ConsoleOutput, FileOutput, RotatingFileOutput,
     };
     let mut multi_output = MultiOutput::new();
 
     if config.output.console_enabled {
-        let console = Box::new(ConsoleOutput::new(config.output.colored_output));
-        multi_output = multi_output.add_output(console);
+        let color_mode = if config.output.colored_output {
+            config.output.color_mode
+        } else {
+            ColorMode::Never
+        };
+        let console = ConsoleOutput::new(config.output.colored_output)
+            .with_target(config.output.console_target)
+            .with_color_mode(color_mode)
+            .with_theme(config.output.color_theme.clone())
+            .with_format(config.output.console_format)
+            .with_component_tracker(Arc::clone(component_tracker));
+        multi_output = multi_output.add_output(Box::new(console));
     }
 
     if config.output.file_enabled {
         if let Some(file_path) = &config.output.file_path {
-            if config.output.max_file_size > 0 && config.output.max_files > 1 {
+            let timed = config.output.rotation_schedule.is_timed();
+            if timed || (config.output.max_file_size > 0 && config.output.max_files > 1) {
                 match RotatingFileOutput::new(
                     file_path,
                     config.output.max_file_size,
                     config.output.max_files,
                     config.output.json_format,
                 ) {
-                    Ok(rotating) => {
+                    Ok(mut rotating) => {
+                        if let Some(handler) = &config.error_handler {
+                            rotating = rotating.with_error_handler(handler.clone());
+                        }
+                        rotating = rotating
+                            .with_multi_process(config.output.multi_process)
+                            .with_sync_policy(config.output.sync_policy)
+                            .with_schedule(config.output.rotation_schedule)
+                            .with_time_zone(config.output.rotation_time_zone)
+                            .with_compression(config.output.compression);
+                        if let Some(max_age) = config.output.max_file_age {
+                            rotating = rotating.with_max_age(max_age);
+                        }
+                        if let Some(max_total_size) = config.output.max_total_size {
+                            rotating = rotating.with_max_total_size(max_total_size);
+                        }
+                        if let Some(interval) = config.output.reopen_check_interval {
+                            rotating = rotating.with_reopen_check(interval);
+                        }
                         multi_output = multi_output.add_output(Box::new(rotating));
                     }
                     Err(e) => {
-                        eprintln!("Failed to create rotating file output: {}", e);
-                        if let Ok(file) = FileOutput::new(file_path, config.output.json_format) {
+                        report_error(config.error_handler.as_ref(), "RotatingFileOutput", &e);
+                        if let Ok(mut file) = FileOutput::new(file_path, config.output.json_format)
+                        {
+                            if let Some(handler) = &config.error_handler {
+                                file = file.with_error_handler(handler.clone());
+                            }
                             multi_output = multi_output.add_output(Box::new(file));
                         }
                     }
                 }
-            } else if let Ok(file) = FileOutput::new(file_path, config.output.json_format) {
+            } else if let Ok(mut file) = FileOutput::new(file_path, config.output.json_format) {
+                if let Some(handler) = &config.error_handler {
+                    file = file.with_error_handler(handler.clone());
+                }
+                let file = file
+                    .with_multi_process(config.output.multi_process)
+                    .with_sync_policy(config.output.sync_policy);
+                let file = match config.output.reopen_check_interval {
+                    Some(interval) => file.with_reopen_check(interval),
+                    None => file,
+                };
                 multi_output = multi_output.add_output(Box::new(file));
             }
         }
     }
-    let output: Arc<dyn OutputDestination> = Arc::new(multi_output);
+    for output in extra_outputs {
+        multi_output = multi_output.add_output(Box::new(Arc::clone(output)));
+    }
+    if let Some(handler) = &config.error_handler {
+        multi_output = multi_output.with_error_handler(handler.clone());
+    }
+    let router = Arc::new(multi_output);
+    let output: Arc<dyn OutputDestination> = router.clone();
 
     let output = if config.performance.buffering_enabled {
-        Arc::new(BufferedOutput::new(output, config.performance.buffer_size))
+        let performance = &config.performance;
+        let mut buffered = BufferedOutput::new(output, performance.buffer_size);
+        if let Some(max_bytes) = performance.buffer_max_bytes {
+            buffered = buffered.with_max_bytes(max_bytes);
+        }
+        if let Some(max_age) = performance.buffer_max_age {
+            buffered = buffered.with_max_age(max_age);
+        }
+        if let Some(level) = performance.buffer_flush_level {
+            buffered = buffered.with_flush_level(level);
+        }
+        if let Some(handler) = &config.error_handler {
+            buffered = buffered.with_error_handler(handler.clone());
+        }
+        Arc::new(buffered)
     } else {
         output
     };
 
-    #[cfg(feature = "async")]
-    let output = if config.performance.async_enabled {
-        match crate::async_output::AsyncOutput::new(output.clone()) {
-            Ok(async_output) => Arc::new(async_output) as Arc<dyn OutputDestination>,
-            Err(e) => {
-                eprintln!("Failed to create async output: {}", e);
-                output
-            }
+    let output: Arc<dyn OutputDestination> = if config.performance.async_enabled {
+        let performance = &config.performance;
+        let background = BackgroundOutput::new(output)
+            .with_capacity(performance.async_capacity)
+            .with_batch_size(performance.async_batch_size)
+            .with_interval(performance.async_flush_interval)
+            .with_overflow_policy(performance.overflow_policy);
+        match &config.error_handler {
+            Some(handler) => Arc::new(background.with_error_handler(handler.clone())),
+            None => Arc::new(background),
         }
     } else {
         output
     };
+    OutputPipeline(output, router)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::MemoryOutput;
 
-    output
+    #[test]
+    fn test_add_output_keeps_pipeline() {
+        let logger = Logger::with_config(
+            "app",
+            Config::new().with_console_output(false).with_async(true),
+        );
+        let pipeline = Arc::as_ptr(&logger.output.load().0);
+
+        let memory = Arc::new(MemoryOutput::new(10));
+        logger.add_output(Box::new(Arc::clone(&memory)));
+        assert!(std::ptr::eq(Arc::as_ptr(&logger.output.load().0), pipeline));
+
+        let clone = logger.clone();
+        clone.info("from clone");
+        logger.output.load().0.flush().unwrap();
+        assert_eq!(memory.len(), 1);
+    }
 }
@@ -86,7 +86,7 @@ mod tests {
         monitor.refresh();
 
         let memory = monitor.memory_usage();
-        assert!(memory >= 0.0 && memory <= 100.0);
+        assert!((0.0..=100.0).contains(&memory));
 
         let cpu = monitor.cpu_usage();
         assert!(cpu >= 0.0);
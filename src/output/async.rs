@@ -2,7 +2,7 @@
 //!
 //! [`AsyncOutput`] wraps any [`OutputDestination`] and offloads writes to a
 //! background Tokio task. The caller's [`write`](OutputDestination::write) call
-//! serializes the record to bytes and enqueues it via a non-blocking `try_send`,
+//! encodes the record as a JSON line and enqueues it via a non-blocking `try_send`,
 //! returning immediately. The background task drains the channel in batches of
 //! up to 100 records every 100 ms, minimising I/O syscalls.
 //!
@@ -29,40 +29,57 @@ use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio::time::{timeout, Duration};
 
-use crate::output::{LogRecord, OutputDestination};
+use crate::output::{report_error, EncodedRecord, ErrorHandler, LogRecord, OutputDestination};
+
+type SharedHandler = Arc<parking_lot::Mutex<Option<ErrorHandler>>>;
 
 /// Asynchronous output destination with bounded channel and backpressure.
 pub struct AsyncOutput {
     sender: mpsc::Sender<Vec<u8>>,
     _handle: tokio::task::JoinHandle<()>,
     shutdown: Arc<AtomicBool>,
+    error_handler: SharedHandler,
 }
 
 impl AsyncOutput {
     /// Creates a new async output wrapping the given destination.
     ///
     /// Spawns a Tokio background task that drains the channel in batches.
+    /// Returns an error when called outside a Tokio runtime; use
+    /// [`BackgroundOutput`](crate::output::BackgroundOutput) there instead.
     pub fn new(destination: Arc<dyn OutputDestination>) -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Unsupported, e))?;
         let (sender, receiver) = mpsc::channel(1000);
         let shutdown = Arc::new(AtomicBool::new(false));
         let shutdown_clone = Arc::clone(&shutdown);
+        let error_handler = SharedHandler::default();
+        let handler_clone = Arc::clone(&error_handler);
 
-        let handle = tokio::spawn(async move {
-            Self::background_task(receiver, destination, shutdown_clone).await;
+        let handle = runtime.spawn(async move {
+            Self::background_task(receiver, destination, shutdown_clone, handler_clone).await;
         });
 
         Ok(Self {
             sender,
             _handle: handle,
             shutdown,
+            error_handler,
         })
     }
 
+    /// Reports errors of the background task to `handler` instead of stderr.
+    pub fn with_error_handler(self, handler: ErrorHandler) -> Self {
+        *self.error_handler.lock() = Some(handler);
+        self
+    }
+
     /// Background task: collects records into batches, flushes every 100 ms.
     async fn background_task(
         mut receiver: mpsc::Receiver<Vec<u8>>,
         destination: Arc<dyn OutputDestination>,
         shutdown: Arc<AtomicBool>,
+        error_handler: SharedHandler,
     ) {
         let mut batch: Vec<Vec<u8>> = Vec::new();
         let batch_size = 100;
@@ -78,18 +95,18 @@ impl AsyncOutput {
                             Err(_) => break,
                         }
                     }
-                    Self::flush_batch(&batch, &destination).await;
+                    Self::flush_batch(&batch, &destination, &error_handler).await;
                     batch.clear();
                 }
                 Ok(None) => {
                     if !batch.is_empty() {
-                        Self::flush_batch(&batch, &destination).await;
+                        Self::flush_batch(&batch, &destination, &error_handler).await;
                     }
                     break;
                 }
                 Err(_timeout) => {
                     if !batch.is_empty() {
-                        Self::flush_batch(&batch, &destination).await;
+                        Self::flush_batch(&batch, &destination, &error_handler).await;
                         batch.clear();
                     }
                 }
@@ -100,7 +117,7 @@ impl AsyncOutput {
                     batch.push(msg);
                 }
                 if !batch.is_empty() {
-                    Self::flush_batch(&batch, &destination).await;
+                    Self::flush_batch(&batch, &destination, &error_handler).await;
                 }
                 break;
             }
@@ -108,14 +125,21 @@ impl AsyncOutput {
     }
 
     /// Writes a batch of pre-serialized records and flushes the destination.
-    async fn flush_batch(batch: &[Vec<u8>], destination: &Arc<dyn OutputDestination>) {
+    async fn flush_batch(
+        batch: &[Vec<u8>],
+        destination: &Arc<dyn OutputDestination>,
+        error_handler: &SharedHandler,
+    ) {
+        let report = |e: std::io::Error| {
+            report_error(error_handler.lock().as_ref(), destination.name(), &e);
+        };
         for bytes in batch {
             if let Err(e) = destination.write_bytes(bytes) {
-                eprintln!("AsyncOutput write error: {}", e);
+                report(e);
             }
         }
         if let Err(e) = destination.flush() {
-            eprintln!("AsyncOutput flush error: {}", e);
+            report(e);
         }
     }
 
@@ -123,9 +147,7 @@ impl AsyncOutput {
     pub async fn shutdown(self) -> std::io::Result<()> {
         self.shutdown.store(true, Ordering::Relaxed);
         drop(self.sender);
-        self._handle
-            .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+        self._handle.await.map_err(std::io::Error::other)
     }
 }
 
@@ -134,12 +156,12 @@ impl OutputDestination for AsyncOutput {
     ///
     /// Returns `WouldBlock` if the channel is at capacity (1000 records).
     fn write(&self, record: &LogRecord<'_>) -> std::io::Result<()> {
-        let mut buf = Vec::with_capacity(256);
-        serde_json::to_writer(&mut buf, record)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-        buf.push(b'\n');
+        EncodedRecord::scope(record, |encoded| self.write_encoded(encoded))
+    }
 
-        self.sender.try_send(buf).map_err(|e| {
+    /// Enqueues a copy of the record's shared JSON encoding.
+    fn write_encoded(&self, encoded: &EncodedRecord<'_, '_>) -> std::io::Result<()> {
+        self.sender.try_send(encoded.json().to_vec()).map_err(|e| {
             std::io::Error::new(
                 std::io::ErrorKind::WouldBlock,
                 format!("Log channel full (backpressure): {}", e),
@@ -177,6 +199,7 @@ mod tests {
                     message: "Test async message",
                     context: &ctx,
                     data: Some(&[("count", &i_str)]),
+                    location: None,
                 })
                 .unwrap();
         }
@@ -184,4 +207,11 @@ mod tests {
         tokio::time::sleep(Duration::from_millis(200)).await;
         async_output.shutdown().await.unwrap();
     }
+
+    #[test]
+    fn test_requires_runtime() {
+        let console = Arc::new(ConsoleOutput::new(false));
+        let err = AsyncOutput::new(console).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
 }
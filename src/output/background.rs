@@ -0,0 +1,608 @@
+//! Background writer running a destination on a dedicated thread.
+//!
+//! [`BackgroundOutput`] wraps any [`OutputDestination`] and moves the actual
+//! writing to a `telelog-writer` thread, so it works with or without an async
+//! runtime. `write` copies the record into an [`OwnedRecord`] and queues it;
+//! the thread writes queued records in batches through the destination's own
+//! `write`, preserving its format, and flushes the destination after each batch.
+//!
+//! The queue is bounded. What happens when it is full is chosen with an
+//! [`OverflowPolicy`]; records discarded by the policy are counted and reported
+//! by [`dropped`](BackgroundOutput::dropped).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use telelog::output::{BackgroundOutput, FileOutput, OverflowPolicy};
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! let file = Arc::new(FileOutput::new("app.log", true).unwrap());
+//! let background = BackgroundOutput::new(file)
+//!     .with_capacity(10_000)
+//!     .with_batch_size(256)
+//!     .with_overflow_policy(OverflowPolicy::BlockOnError {
+//!         timeout: Duration::from_millis(50),
+//!     });
+//! ```
+
+use crate::level::LogLevel;
+use crate::output::{report_error, ErrorHandler, LogRecord, OutputDestination, OwnedRecord};
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+pub(crate) const DEFAULT_CAPACITY: usize = 1000;
+pub(crate) const DEFAULT_BATCH_SIZE: usize = 100;
+pub(crate) const DEFAULT_INTERVAL: Duration = Duration::from_millis(100);
+/// How often a waiting flush checks that the writer thread is still running.
+const WORKER_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What [`BackgroundOutput`] does with a record when its queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OverflowPolicy {
+    /// Discard the new record.
+    #[default]
+    DropNewest,
+    /// Discard the oldest queued record to make room for the new one.
+    DropOldest,
+    /// Wait up to `timeout` for room, then discard the new record.
+    Block { timeout: Duration },
+    /// Wait up to `timeout` for records at `Error` or above; discard other new
+    /// records immediately.
+    BlockOnError { timeout: Duration },
+}
+
+enum Item {
+    Record(OwnedRecord),
+    Bytes(Vec<u8>),
+}
+
+struct Queue {
+    items: VecDeque<Item>,
+    /// Number of flushes requested and completed; a flush waits until its
+    /// request number has been completed.
+    flush_requested: u64,
+    flush_completed: u64,
+    /// Number of writers waiting for room under a blocking policy.
+    blocked: usize,
+    shutdown: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    /// Signalled to wake the writer thread.
+    work: Condvar,
+    /// Signalled when the writer has made room or completed a flush.
+    progress: Condvar,
+    dropped: AtomicU64,
+}
+
+struct Settings {
+    capacity: usize,
+    batch_size: usize,
+    interval: Duration,
+    error_handler: Option<ErrorHandler>,
+}
+
+/// Writes to a destination on a dedicated background thread.
+///
+/// The thread is started by the first record and stopped, after writing all
+/// queued records, when the output is dropped.
+pub struct BackgroundOutput {
+    destination: Arc<dyn OutputDestination>,
+    shared: Arc<Shared>,
+    settings: Settings,
+    policy: OverflowPolicy,
+    worker: Mutex<Option<JoinHandle<()>>>,
+    /// Set once the writer thread runs, so writes skip the `worker` lock.
+    started: AtomicBool,
+}
+
+impl BackgroundOutput {
+    /// Creates a background writer for `destination`.
+    pub fn new(destination: Arc<dyn OutputDestination>) -> Self {
+        Self {
+            destination,
+            shared: Arc::new(Shared {
+                queue: Mutex::new(Queue {
+                    items: VecDeque::new(),
+                    flush_requested: 0,
+                    flush_completed: 0,
+                    blocked: 0,
+                    shutdown: false,
+                }),
+                work: Condvar::new(),
+                progress: Condvar::new(),
+                dropped: AtomicU64::new(0),
+            }),
+            settings: Settings {
+                capacity: DEFAULT_CAPACITY,
+                batch_size: DEFAULT_BATCH_SIZE,
+                interval: DEFAULT_INTERVAL,
+                error_handler: None,
+            },
+            policy: OverflowPolicy::default(),
+            worker: Mutex::new(None),
+            started: AtomicBool::new(false),
+        }
+    }
+
+    /// Sets how many records may be queued (1000 by default).
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.settings.capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets how many queued records wake the writer early (100 by default).
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.settings.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets how long records may wait in the queue before they are written
+    /// (100ms by default).
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.settings.interval = interval;
+        self
+    }
+
+    /// Sets what happens when the queue is full (drop newest by default).
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Reports errors of the writer thread and dropped records to `handler`
+    /// instead of stderr.
+    pub fn with_error_handler(mut self, handler: ErrorHandler) -> Self {
+        self.settings.error_handler = Some(handler);
+        self
+    }
+
+    /// Returns how many records were discarded because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of records waiting to be written.
+    pub fn queued(&self) -> usize {
+        self.shared.queue.lock().items.len()
+    }
+
+    fn worker_stopped(&self) -> bool {
+        self.worker
+            .lock()
+            .as_ref()
+            .is_some_and(|worker| worker.is_finished())
+    }
+
+    fn start(&self) -> io::Result<()> {
+        if self.started.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let mut worker = self.worker.lock();
+        if worker.is_none() {
+            let shared = Arc::clone(&self.shared);
+            let destination = Arc::clone(&self.destination);
+            let batch_size = self.settings.batch_size;
+            let interval = self.settings.interval;
+            let handler = self.settings.error_handler.clone();
+            let handle = std::thread::Builder::new()
+                .name("telelog-writer".to_string())
+                .spawn(move || {
+                    run(
+                        &shared,
+                        &*destination,
+                        batch_size,
+                        interval,
+                        handler.as_ref(),
+                    )
+                })?;
+            *worker = Some(handle);
+            self.started.store(true, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    /// Queues `item`; `level` is only consulted when the queue is full.
+    fn enqueue(&self, item: Item, level: impl FnOnce() -> LogLevel) -> io::Result<()> {
+        self.start()?;
+        let capacity = self.settings.capacity;
+        let mut queue = self.shared.queue.lock();
+        if queue.items.len() >= capacity {
+            let timeout = match self.policy {
+                OverflowPolicy::DropNewest => None,
+                OverflowPolicy::DropOldest => {
+                    queue.items.pop_front();
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                    None
+                }
+                OverflowPolicy::Block { timeout } => Some(timeout),
+                OverflowPolicy::BlockOnError { timeout } => {
+                    (level() >= LogLevel::Error).then_some(timeout)
+                }
+            };
+            if let Some(timeout) = timeout {
+                let deadline = Instant::now() + timeout;
+                queue.blocked += 1;
+                self.shared.work.notify_one();
+                while queue.items.len() >= capacity {
+                    if self
+                        .shared
+                        .progress
+                        .wait_until(&mut queue, deadline)
+                        .timed_out()
+                    {
+                        break;
+                    }
+                }
+                queue.blocked -= 1;
+            }
+            if queue.items.len() >= capacity {
+                self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "background writer queue is full",
+                ));
+            }
+        }
+        queue.items.push_back(item);
+        if queue.items.len() >= self.settings.batch_size {
+            self.shared.work.notify_one();
+        }
+        Ok(())
+    }
+}
+
+/// Returns the level of a JSON-encoded record, or `Info` if it has none.
+fn level_of(bytes: &[u8]) -> LogLevel {
+    #[derive(Deserialize)]
+    struct Fields {
+        level: Option<String>,
+    }
+    serde_json::from_slice::<Fields>(bytes.trim_ascii_end())
+        .ok()
+        .and_then(|fields| fields.level?.parse().ok())
+        .unwrap_or(LogLevel::Info)
+}
+
+/// Writer thread: waits for a full batch, a flush request, a blocked writer,
+/// shutdown or the interval to pass, then writes everything queued.
+fn run(
+    shared: &Shared,
+    destination: &dyn OutputDestination,
+    batch_size: usize,
+    interval: Duration,
+    handler: Option<&ErrorHandler>,
+) {
+    let mut reported_drops = 0;
+    loop {
+        let (batch, flush, shutdown) = {
+            let mut queue = shared.queue.lock();
+            let deadline = Instant::now() + interval;
+            while !queue.shutdown
+                && queue.flush_requested == queue.flush_completed
+                && queue.blocked == 0
+                && queue.items.len() < batch_size
+            {
+                if shared.work.wait_until(&mut queue, deadline).timed_out() {
+                    break;
+                }
+            }
+            let batch: Vec<Item> = queue.items.drain(..).collect();
+            (batch, queue.flush_requested, queue.shutdown)
+        };
+        shared.progress.notify_all();
+
+        for item in &batch {
+            let result = match item {
+                Item::Record(record) => record.with_record(|r| destination.write(r)),
+                Item::Bytes(bytes) => destination.write_bytes(bytes),
+            };
+            if let Err(e) = result {
+                report_error(handler, destination.name(), &e);
+            }
+        }
+        if !batch.is_empty() || flush > shared.queue.lock().flush_completed {
+            if let Err(e) = destination.flush() {
+                report_error(handler, destination.name(), &e);
+            }
+        }
+
+        let dropped = shared.dropped.load(Ordering::Relaxed);
+        if dropped > reported_drops {
+            let notice = io::Error::other(format!(
+                "dropped {} records (queue full)",
+                dropped - reported_drops
+            ));
+            report_error(handler, "BackgroundOutput", &notice);
+            reported_drops = dropped;
+        }
+
+        let mut queue = shared.queue.lock();
+        queue.flush_completed = queue.flush_completed.max(flush);
+        drop(queue);
+        shared.progress.notify_all();
+        if shutdown {
+            break;
+        }
+    }
+}
+
+impl OutputDestination for BackgroundOutput {
+    /// Queues a copy of the record, applying the overflow policy if the queue
+    /// is full. Returns `WouldBlock` if the record was discarded.
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        self.enqueue(Item::Record(OwnedRecord::from_record(record)), || {
+            record.level
+        })
+    }
+
+    /// Waits until every record queued before the call has been written and
+    /// the destination flushed.
+    ///
+    /// Returns an error instead of waiting forever if the writer thread has
+    /// stopped, e.g. because the destination panicked.
+    fn flush(&self) -> io::Result<()> {
+        if !self.started.load(Ordering::Acquire) {
+            return self.destination.flush();
+        }
+        let mut queue = self.shared.queue.lock();
+        queue.flush_requested += 1;
+        let target = queue.flush_requested;
+        self.shared.work.notify_one();
+        while queue.flush_completed < target {
+            let timed_out = self
+                .shared
+                .progress
+                .wait_for(&mut queue, WORKER_CHECK_INTERVAL)
+                .timed_out();
+            if timed_out && self.worker_stopped() {
+                return Err(io::Error::other("background writer thread has stopped"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Queues the bytes; under [`OverflowPolicy::BlockOnError`] the level is
+    /// read from the record's `level` field when the queue is full.
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        self.enqueue(Item::Bytes(bytes.to_vec()), || level_of(bytes))
+    }
+
+    fn reopen(&self) -> io::Result<()> {
+        self.flush()?;
+        self.destination.reopen()
+    }
+}
+
+impl Drop for BackgroundOutput {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.get_mut().take() {
+            self.shared.queue.lock().shutdown = true;
+            self.shared.work.notify_one();
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::MemoryOutput;
+    use std::collections::HashMap;
+
+    /// A destination that blocks every write until released.
+    struct Gate {
+        open: Mutex<bool>,
+        opened: Condvar,
+        inner: MemoryOutput,
+    }
+
+    impl Gate {
+        fn new() -> Arc<Self> {
+            Arc::new(Self {
+                open: Mutex::new(false),
+                opened: Condvar::new(),
+                inner: MemoryOutput::new(100),
+            })
+        }
+
+        fn release(&self) {
+            *self.open.lock() = true;
+            self.opened.notify_all();
+        }
+    }
+
+    impl OutputDestination for Gate {
+        fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+            let mut open = self.open.lock();
+            while !*open {
+                self.opened.wait(&mut open);
+            }
+            self.inner.write(record)
+        }
+
+        fn flush(&self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn write(output: &BackgroundOutput, level: LogLevel, message: &str) -> io::Result<()> {
+        let ctx = HashMap::new();
+        output.write(&LogRecord {
+            timestamp: "2025-09-07T10:30:00Z",
+            level,
+            logger: "bg",
+            message,
+            context: &ctx,
+            data: None,
+            location: None,
+        })
+    }
+
+    fn messages(memory: &MemoryOutput) -> Vec<String> {
+        memory
+            .snapshot()
+            .iter()
+            .map(|r| r.message.clone())
+            .collect()
+    }
+
+    /// Queues `first`, waits until the writer is blocked on it, then fills the
+    /// queue with `fill`.
+    fn stall(output: &BackgroundOutput, fill: &[&str]) {
+        write(output, LogLevel::Info, "first").unwrap();
+        output.shared.work.notify_one();
+        while output.queued() > 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        for message in fill {
+            write(output, LogLevel::Info, message).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_writes_in_background_and_flushes() {
+        let memory = Arc::new(MemoryOutput::new(100));
+        let output = BackgroundOutput::new(memory.clone()).with_interval(Duration::from_secs(60));
+
+        for i in 0..5 {
+            write(&output, LogLevel::Info, &i.to_string()).unwrap();
+        }
+        output.flush().unwrap();
+        assert_eq!(messages(&memory), ["0", "1", "2", "3", "4"]);
+
+        write(&output, LogLevel::Info, "on drop").unwrap();
+        drop(output);
+        assert_eq!(memory.len(), 6);
+    }
+
+    #[test]
+    fn test_drop_newest_and_oldest() {
+        let gate = Gate::new();
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&errors);
+        let output = BackgroundOutput::new(gate.clone())
+            .with_capacity(2)
+            .with_error_handler(ErrorHandler::new(move |name, e| {
+                seen.lock().push(format!("{}: {}", name, e));
+            }));
+        stall(&output, &["a", "b"]);
+        assert_eq!(
+            write(&output, LogLevel::Info, "c").unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+        assert_eq!(output.dropped(), 1);
+        gate.release();
+        output.flush().unwrap();
+        assert_eq!(messages(&gate.inner), ["first", "a", "b"]);
+        assert_eq!(
+            *errors.lock(),
+            ["BackgroundOutput: dropped 1 records (queue full)"]
+        );
+
+        let gate = Gate::new();
+        let output = BackgroundOutput::new(gate.clone())
+            .with_capacity(2)
+            .with_overflow_policy(OverflowPolicy::DropOldest);
+        stall(&output, &["a", "b"]);
+        write(&output, LogLevel::Info, "c").unwrap();
+        assert_eq!(output.dropped(), 1);
+        gate.release();
+        output.flush().unwrap();
+        assert_eq!(messages(&gate.inner), ["first", "b", "c"]);
+    }
+
+    #[test]
+    fn test_block_on_error() {
+        let gate = Gate::new();
+        let output = BackgroundOutput::new(gate.clone())
+            .with_capacity(1)
+            .with_overflow_policy(OverflowPolicy::BlockOnError {
+                timeout: Duration::from_secs(10),
+            });
+        stall(&output, &["a"]);
+
+        assert!(write(&output, LogLevel::Warning, "dropped").is_err());
+        let releaser = {
+            let gate = gate.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                gate.release();
+            })
+        };
+        write(&output, LogLevel::Error, "kept").unwrap();
+        releaser.join().unwrap();
+        output.flush().unwrap();
+        assert_eq!(messages(&gate.inner), ["first", "a", "kept"]);
+        assert_eq!(output.dropped(), 1);
+    }
+
+    #[test]
+    fn test_block_on_error_reads_level_of_bytes() {
+        let gate = Gate::new();
+        let output = BackgroundOutput::new(gate.clone())
+            .with_capacity(1)
+            .with_overflow_policy(OverflowPolicy::BlockOnError {
+                timeout: Duration::from_secs(10),
+            });
+        stall(&output, &["a"]);
+
+        assert!(output
+            .write_bytes(b"{\"level\":\"WARNING\",\"message\":\"dropped\"}\n")
+            .is_err());
+        let releaser = {
+            let gate = gate.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                gate.release();
+            })
+        };
+        output
+            .write_bytes(b"{\"level\":\"ERROR\",\"message\":\"kept\"}\n")
+            .unwrap();
+        releaser.join().unwrap();
+        assert_eq!(output.dropped(), 1);
+    }
+
+    #[test]
+    fn test_flush_fails_when_writer_stopped() {
+        struct Panics;
+
+        impl OutputDestination for Panics {
+            fn write(&self, _record: &LogRecord<'_>) -> io::Result<()> {
+                panic!("destination failed");
+            }
+
+            fn flush(&self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let output = BackgroundOutput::new(Arc::new(Panics));
+        write(&output, LogLevel::Info, "boom").unwrap();
+        assert!(output.flush().is_err());
+    }
+
+    #[test]
+    fn test_config_async_uses_background_thread() {
+        use crate::{Config, Logger};
+
+        let memory = Arc::new(MemoryOutput::new(10));
+        let config = Config::new().with_console_output(false).with_async(true);
+        let logger = Logger::with_config("app", config);
+        logger.add_output(Box::new(Arc::clone(&memory)));
+
+        logger.info("queued");
+        // Dropping the logger stops the writer after the queue is written.
+        drop(logger);
+        assert_eq!(messages(&memory), ["queued"]);
+    }
+}
@@ -4,39 +4,72 @@
 //! replays them through the underlying destination's `write` method on flush.
 //! This preserves the destination's configured format (plain-text or JSON)
 //! regardless of how the buffer is used.
+//!
+//! The buffer is flushed when it holds `buffer_size` records, and optionally
+//! when its records reach a total size ([`with_max_bytes`](BufferedOutput::with_max_bytes)),
+//! when a record at or above a level arrives ([`with_flush_level`](BufferedOutput::with_flush_level)),
+//! or when the oldest record has waited for a maximum age
+//! ([`with_max_age`](BufferedOutput::with_max_age)). The maximum age is
+//! enforced by a background thread, so records are written even when no
+//! further records arrive.
+//!
+//! # Example
+//!
+//! ```
+//! use telelog::output::{BufferedOutput, ConsoleOutput};
+//! use telelog::LogLevel;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! let buffered = BufferedOutput::new(Arc::new(ConsoleOutput::new(false)), 512)
+//!     .with_max_bytes(64 * 1024)
+//!     .with_flush_level(LogLevel::Error)
+//!     .with_max_age(Duration::from_secs(5));
+//! ```
 
 use crate::level::LogLevel;
-use crate::output::{LogRecord, OutputDestination};
-use parking_lot::Mutex;
-use std::collections::HashMap;
+use crate::output::{report_error, ErrorHandler, LogRecord, OutputDestination, OwnedRecord};
+use parking_lot::{Condvar, Mutex};
 use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
-/// Owned copy of a [`LogRecord`] held inside the buffer.
-struct OwnedRecord {
-    timestamp: String,
-    level: LogLevel,
-    logger: String,
-    message: String,
-    context: HashMap<String, String>,
-    data: Vec<(String, String)>,
+struct Buffer {
+    records: Vec<OwnedRecord>,
+    /// Combined [`OwnedRecord::size`] of the buffered records.
+    bytes: usize,
+    /// When the oldest buffered record arrived.
+    oldest: Option<Instant>,
 }
 
-impl OwnedRecord {
-    fn from_record(r: &LogRecord<'_>) -> Self {
-        Self {
-            timestamp: r.timestamp.to_owned(),
-            level: r.level,
-            logger: r.logger.to_owned(),
-            message: r.message.to_owned(),
-            context: r.context.clone(),
-            data: r
-                .data
-                .unwrap_or_default()
-                .iter()
-                .map(|(k, v)| (k.to_string(), v.to_string()))
-                .collect(),
+struct Shared {
+    destination: Arc<dyn OutputDestination>,
+    buffer: Mutex<Buffer>,
+    /// Set when the output is dropped to stop the ticker.
+    shutdown: Mutex<bool>,
+    /// Signalled to wake the ticker on shutdown.
+    wake: Condvar,
+}
+
+impl Shared {
+    fn flush_buffer(&self) -> io::Result<()> {
+        let mut buffer = self.buffer.lock();
+        buffer.bytes = 0;
+        buffer.oldest = None;
+        for owned in buffer.records.drain(..) {
+            owned.with_record(|record| self.destination.write(record))?;
         }
+        self.destination.flush()?;
+        Ok(())
+    }
+
+    /// Returns how long until the oldest record reaches `max_age`, or `None`
+    /// if the buffer is empty.
+    fn time_left(&self, max_age: Duration) -> Option<Duration> {
+        let oldest = self.buffer.lock().oldest?;
+        Some(max_age.saturating_sub(oldest.elapsed()))
     }
 }
 
@@ -45,9 +78,15 @@ impl OwnedRecord {
 /// Respects the underlying destination's format (plain-text or JSON).
 /// Automatically flushes when the buffer reaches its capacity.
 pub struct BufferedOutput {
-    destination: Arc<dyn OutputDestination>,
-    buffer: Arc<Mutex<Vec<OwnedRecord>>>,
+    shared: Arc<Shared>,
     buffer_size: usize,
+    max_bytes: Option<usize>,
+    flush_level: Option<LogLevel>,
+    max_age: Option<Duration>,
+    error_handler: Option<ErrorHandler>,
+    ticker: Mutex<Option<JoinHandle<()>>>,
+    /// Set once the ticker runs, so writes skip the `ticker` lock.
+    ticker_started: AtomicBool,
 }
 
 impl BufferedOutput {
@@ -59,52 +98,120 @@ impl BufferedOutput {
     /// * `buffer_size` - Number of records to buffer before auto-flush
     pub fn new(destination: Arc<dyn OutputDestination>, buffer_size: usize) -> Self {
         Self {
-            destination,
-            buffer: Arc::new(Mutex::new(Vec::with_capacity(buffer_size))),
+            shared: Arc::new(Shared {
+                destination,
+                buffer: Mutex::new(Buffer {
+                    records: Vec::with_capacity(buffer_size),
+                    bytes: 0,
+                    oldest: None,
+                }),
+                shutdown: Mutex::new(false),
+                wake: Condvar::new(),
+            }),
             buffer_size,
+            max_bytes: None,
+            flush_level: None,
+            max_age: None,
+            error_handler: None,
+            ticker: Mutex::new(None),
+            ticker_started: AtomicBool::new(false),
         }
     }
 
+    /// Also flushes once the buffered records reach `max_bytes` in total, as
+    /// measured by [`OwnedRecord::size`].
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Flushes immediately, including the new record, when a record at or
+    /// above `level` arrives.
+    pub fn with_flush_level(mut self, level: LogLevel) -> Self {
+        self.flush_level = Some(level);
+        self
+    }
+
+    /// Flushes from a background thread once the oldest buffered record has
+    /// waited for `max_age`.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Reports errors of age-triggered flushes to `handler` instead of stderr.
+    pub fn with_error_handler(mut self, handler: ErrorHandler) -> Self {
+        self.error_handler = Some(handler);
+        self
+    }
+
     /// Flushes all buffered records to the underlying destination.
     pub fn flush_buffer(&self) -> io::Result<()> {
-        let mut buffer = self.buffer.lock();
-        for owned in buffer.drain(..) {
-            let data_refs: Vec<(&str, &str)> = owned
-                .data
-                .iter()
-                .map(|(k, v)| (k.as_str(), v.as_str()))
-                .collect();
-            let record = LogRecord {
-                timestamp: &owned.timestamp,
-                level: owned.level,
-                logger: &owned.logger,
-                message: &owned.message,
-                context: &owned.context,
-                data: if data_refs.is_empty() {
-                    None
-                } else {
-                    Some(&data_refs)
-                },
-            };
-            self.destination.write(&record)?;
-        }
-        self.destination.flush()?;
-        Ok(())
+        self.shared.flush_buffer()
     }
 
     /// Returns the number of records currently in the buffer.
     pub fn buffer_len(&self) -> usize {
-        self.buffer.lock().len()
+        self.shared.buffer.lock().records.len()
+    }
+
+    /// Starts the thread enforcing the maximum age, if one is set.
+    fn start_ticker(&self) -> io::Result<()> {
+        let Some(max_age) = self.max_age else {
+            return Ok(());
+        };
+        if self.ticker_started.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let mut ticker = self.ticker.lock();
+        if ticker.is_none() {
+            let shared = Arc::clone(&self.shared);
+            let handler = self.error_handler.clone();
+            let handle = std::thread::Builder::new()
+                .name("telelog-buffer".to_string())
+                .spawn(move || tick(&shared, max_age, handler.as_ref()))?;
+            *ticker = Some(handle);
+            self.ticker_started.store(true, Ordering::Release);
+        }
+        Ok(())
+    }
+}
+
+/// Flushes the buffer whenever its oldest record reaches `max_age`, until
+/// shutdown.
+fn tick(shared: &Shared, max_age: Duration, handler: Option<&ErrorHandler>) {
+    loop {
+        let wait = shared.time_left(max_age).unwrap_or(max_age);
+        let mut shutdown = shared.shutdown.lock();
+        if !*shutdown && !wait.is_zero() {
+            shared.wake.wait_for(&mut shutdown, wait);
+        }
+        if *shutdown {
+            return;
+        }
+        drop(shutdown);
+
+        if shared.time_left(max_age).is_some_and(|left| left.is_zero()) {
+            if let Err(e) = shared.flush_buffer() {
+                report_error(handler, shared.destination.name(), &e);
+            }
+        }
     }
 }
 
 impl OutputDestination for BufferedOutput {
     fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        self.start_ticker()?;
         let owned = OwnedRecord::from_record(record);
-        let mut buffer = self.buffer.lock();
-        buffer.push(owned);
+        let mut buffer = self.shared.buffer.lock();
+        buffer.bytes += owned.size();
+        buffer.oldest.get_or_insert_with(Instant::now);
+        buffer.records.push(owned);
 
-        if buffer.len() >= self.buffer_size {
+        let full = buffer.records.len() >= self.buffer_size
+            || self.max_bytes.is_some_and(|max| buffer.bytes >= max)
+            || self.flush_level.is_some_and(|level| record.level >= level);
+        if full {
             drop(buffer);
             self.flush_buffer()?;
         }
@@ -115,12 +222,26 @@ impl OutputDestination for BufferedOutput {
     fn flush(&self) -> io::Result<()> {
         self.flush_buffer()
     }
+
+    fn reopen(&self) -> io::Result<()> {
+        self.flush_buffer()?;
+        self.shared.destination.reopen()
+    }
 }
 
 impl Drop for BufferedOutput {
     fn drop(&mut self) {
+        if let Some(ticker) = self.ticker.get_mut().take() {
+            *self.shared.shutdown.lock() = true;
+            self.shared.wake.notify_one();
+            let _ = ticker.join();
+        }
         if let Err(e) = self.flush_buffer() {
-            eprintln!("Error flushing BufferedOutput on drop: {}", e);
+            report_error(
+                self.error_handler.as_ref(),
+                self.shared.destination.name(),
+                &e,
+            );
         }
     }
 }
@@ -128,11 +249,25 @@ impl Drop for BufferedOutput {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::level::LogLevel;
     use crate::output::file::FileOutput;
+    use crate::output::MemoryOutput;
     use std::collections::HashMap;
     use tempfile::NamedTempFile;
 
+    fn write(output: &BufferedOutput, level: LogLevel, message: &str) {
+        output
+            .write(&LogRecord {
+                timestamp: "2025-09-07T10:30:00Z",
+                level,
+                logger: "test",
+                message,
+                context: &HashMap::new(),
+                data: None,
+                location: None,
+            })
+            .unwrap();
+    }
+
     #[test]
     fn test_buffered_output() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -148,6 +283,7 @@ mod tests {
                 message: "Buffered message",
                 context: &ctx,
                 data: None,
+                location: None,
             })
             .unwrap();
         assert_eq!(buffered.buffer_len(), 1);
@@ -160,6 +296,7 @@ mod tests {
                 message: "Buffered message",
                 context: &ctx,
                 data: None,
+                location: None,
             })
             .unwrap();
         assert_eq!(buffered.buffer_len(), 2);
@@ -173,6 +310,7 @@ mod tests {
                 message: "Buffered message",
                 context: &ctx,
                 data: None,
+                location: None,
             })
             .unwrap();
         assert_eq!(buffered.buffer_len(), 0);
@@ -181,4 +319,73 @@ mod tests {
         let content = std::fs::read_to_string(temp_file.path()).unwrap();
         assert!(content.contains("Buffered message"));
     }
+
+    #[test]
+    fn test_flush_level_and_max_bytes() {
+        let memory = Arc::new(MemoryOutput::new(10));
+        let buffered = BufferedOutput::new(Arc::clone(&memory) as _, 100)
+            .with_flush_level(LogLevel::Error)
+            .with_max_bytes(100);
+
+        write(&buffered, LogLevel::Info, "a");
+        write(&buffered, LogLevel::Warning, "b");
+        assert_eq!(buffered.buffer_len(), 2);
+        write(&buffered, LogLevel::Error, "c");
+        assert_eq!(buffered.buffer_len(), 0);
+        assert_eq!(memory.len(), 3);
+
+        // Each record is 20 bytes of timestamp plus logger and message.
+        write(&buffered, LogLevel::Info, &"x".repeat(40));
+        assert_eq!(buffered.buffer_len(), 1);
+        write(&buffered, LogLevel::Info, &"y".repeat(40));
+        assert_eq!(buffered.buffer_len(), 0);
+        assert_eq!(memory.len(), 5);
+    }
+
+    #[test]
+    fn test_max_age() {
+        let memory = Arc::new(MemoryOutput::new(10));
+        let buffered = BufferedOutput::new(Arc::clone(&memory) as _, 100)
+            .with_max_age(Duration::from_millis(50));
+
+        write(&buffered, LogLevel::Info, "quiet");
+        assert_eq!(memory.len(), 0);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while memory.is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(memory.len(), 1);
+        assert_eq!(buffered.buffer_len(), 0);
+    }
+
+    #[test]
+    fn test_max_age_reports_to_handler() {
+        struct Failing;
+
+        impl OutputDestination for Failing {
+            fn write(&self, _record: &LogRecord<'_>) -> io::Result<()> {
+                Err(io::Error::other("down"))
+            }
+
+            fn flush(&self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&errors);
+        let buffered = BufferedOutput::new(Arc::new(Failing), 100)
+            .with_max_age(Duration::from_millis(20))
+            .with_error_handler(ErrorHandler::new(move |name, e| {
+                seen.lock().push(format!("{}: {}", name, e));
+            }));
+
+        write(&buffered, LogLevel::Info, "lost");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while errors.lock().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(*errors.lock(), ["Failing: down"]);
+    }
 }
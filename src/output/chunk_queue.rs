@@ -0,0 +1,301 @@
+//! Lock-free multi-producer queue of serialized records.
+//!
+//! Producers push finished lines with a single compare-and-swap; the consumer
+//! takes the whole queue at once with a swap and replays it oldest first.
+//! Because nodes are never popped individually, the usual ABA problem of
+//! lock-free stacks cannot occur.
+//!
+//! The queue holds a bounded number of records; [`try_push`](ChunkQueue::try_push)
+//! refuses new ones once it is full, so producers can fall back to waiting
+//! instead of growing memory without limit.
+//!
+//! Drained nodes are kept for reuse, so queuing a line copies it into an
+//! existing buffer instead of allocating. A line that fails to be written is
+//! reported to the thread that queued it rather than to the consumer.
+
+use crate::level::LogLevel;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::io;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::thread::ThreadId;
+
+/// Most drained nodes kept for reuse.
+const MAX_SPARE_NODES: usize = 64;
+/// Most undelivered errors kept for threads that have not written since.
+const MAX_FAILURES: usize = 64;
+
+thread_local! {
+    static THREAD_ID: ThreadId = std::thread::current().id();
+}
+
+fn current_thread() -> ThreadId {
+    THREAD_ID.with(|id| *id)
+}
+
+struct Node {
+    bytes: Vec<u8>,
+    level: Option<LogLevel>,
+    owner: ThreadId,
+    next: *mut Node,
+}
+
+// SAFETY: `next` is only followed by the thread that owns the node, either
+// before it is pushed or after the list was taken with a swap.
+unsafe impl Send for Node {}
+
+pub(crate) struct ChunkQueue {
+    head: AtomicPtr<Node>,
+    /// Records pushed and not yet drained.
+    len: AtomicUsize,
+    capacity: usize,
+    /// Drained nodes whose buffers are reused by `push`. Boxed so that the
+    /// node allocation is reused as well.
+    #[allow(clippy::vec_box)]
+    spare: Mutex<Vec<Box<Node>>>,
+    /// Errors writing lines queued by other threads, awaiting pickup by
+    /// [`take_error`](Self::take_error).
+    failures: Mutex<VecDeque<(ThreadId, io::Error)>>,
+    has_failures: AtomicBool,
+}
+
+impl ChunkQueue {
+    /// Creates a queue holding at most `capacity` records.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+            len: AtomicUsize::new(0),
+            capacity: capacity.max(1),
+            spare: Mutex::new(Vec::new()),
+            failures: Mutex::new(VecDeque::new()),
+            has_failures: AtomicBool::new(false),
+        }
+    }
+
+    /// Adds a copy of a serialized record, or returns `false` without
+    /// blocking if the queue is full.
+    pub(crate) fn try_push(&self, bytes: &[u8], level: Option<LogLevel>) -> bool {
+        if self.len.fetch_add(1, Ordering::Relaxed) >= self.capacity {
+            self.len.fetch_sub(1, Ordering::Relaxed);
+            return false;
+        }
+        let spare = self.spare.try_lock().and_then(|mut spare| spare.pop());
+        let mut node = spare.unwrap_or_else(|| {
+            Box::new(Node {
+                bytes: Vec::new(),
+                level: None,
+                owner: current_thread(),
+                next: ptr::null_mut(),
+            })
+        });
+        node.bytes.clear();
+        node.bytes.extend_from_slice(bytes);
+        node.level = level;
+        node.owner = current_thread();
+
+        let node = Box::into_raw(node);
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            // SAFETY: `node` is not shared until the exchange below succeeds.
+            unsafe { (*node).next = head };
+            match self
+                .head
+                .compare_exchange_weak(head, node, Ordering::SeqCst, Ordering::Relaxed)
+            {
+                Ok(_) => return true,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.head.load(Ordering::SeqCst).is_null()
+    }
+
+    /// Removes every queued record and passes each to `f`, oldest first.
+    ///
+    /// All records are removed even if `f` fails. The first error for a record
+    /// queued by the calling thread is returned; errors for records of other
+    /// threads are kept for them to [`take`](Self::take_error).
+    pub(crate) fn drain(
+        &self,
+        mut f: impl FnMut(&[u8], Option<LogLevel>) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut node = self.head.swap(ptr::null_mut(), Ordering::SeqCst);
+        if node.is_null() {
+            return Ok(());
+        }
+
+        // Reverse the list, which holds the newest record first.
+        let mut oldest = ptr::null_mut();
+        while !node.is_null() {
+            // SAFETY: the swap above made this thread the only owner of the list.
+            let next = unsafe { (*node).next };
+            unsafe { (*node).next = oldest };
+            oldest = node;
+            node = next;
+        }
+
+        let current = current_thread();
+        let mut result = Ok(());
+        let mut drained = 0;
+        let mut node = oldest;
+        while !node.is_null() {
+            drained += 1;
+            // SAFETY: the list is owned by this thread; nodes are freed below.
+            let (bytes, level, owner) = unsafe { (&(*node).bytes, (*node).level, (*node).owner) };
+            if let Err(e) = f(bytes, level) {
+                if owner != current {
+                    self.fail(owner, e);
+                } else if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+            node = unsafe { (*node).next };
+        }
+
+        self.len.fetch_sub(drained, Ordering::Relaxed);
+
+        let mut spare = self.spare.lock();
+        while !oldest.is_null() {
+            // SAFETY: every node was created by `Box::into_raw` in `push`.
+            let node = unsafe { Box::from_raw(oldest) };
+            oldest = node.next;
+            if spare.len() < MAX_SPARE_NODES {
+                spare.push(node);
+            }
+        }
+        result
+    }
+
+    fn fail(&self, owner: ThreadId, error: io::Error) {
+        let mut failures = self.failures.lock();
+        if failures.len() >= MAX_FAILURES {
+            failures.pop_front();
+        }
+        failures.push_back((owner, error));
+        self.has_failures.store(true, Ordering::Release);
+    }
+
+    /// Returns the oldest undelivered error for a record queued by the
+    /// calling thread and written by another.
+    pub(crate) fn take_error(&self) -> io::Result<()> {
+        if !self.has_failures.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let mut failures = self.failures.lock();
+        let current = current_thread();
+        let error = failures
+            .iter()
+            .position(|(owner, _)| *owner == current)
+            .and_then(|index| failures.remove(index));
+        self.has_failures
+            .store(!failures.is_empty(), Ordering::Release);
+        match error {
+            Some((_, e)) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ChunkQueue {
+    /// Frees records that were never drained; owners that must not lose them
+    /// drain the queue first.
+    fn drop(&mut self) {
+        let _ = self.drain(|_, _| Ok(()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_drains_in_push_order() {
+        let queue = ChunkQueue::new(16);
+        for line in ["a", "b", "c"] {
+            assert!(queue.try_push(line.as_bytes(), Some(LogLevel::Info)));
+        }
+        let mut seen = Vec::new();
+        queue
+            .drain(|bytes, _| {
+                seen.push(String::from_utf8(bytes.to_vec()).unwrap());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(seen, ["a", "b", "c"]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_refuses_records_when_full() {
+        let queue = ChunkQueue::new(2);
+        assert!(queue.try_push(b"a", None));
+        assert!(queue.try_push(b"b", None));
+        assert!(!queue.try_push(b"c", None));
+        queue.drain(|_, _| Ok(())).unwrap();
+        assert!(queue.try_push(b"d", None));
+    }
+
+    #[test]
+    fn test_concurrent_producers() {
+        let queue = Arc::new(ChunkQueue::new(8000));
+        let handles: Vec<_> = (0..8)
+            .map(|t| {
+                let queue = Arc::clone(&queue);
+                std::thread::spawn(move || {
+                    for i in 0..1000u32 {
+                        assert!(queue.try_push(format!("{} {}", t, i).as_bytes(), None));
+                    }
+                })
+            })
+            .collect();
+
+        let mut last = [None::<u32>; 8];
+        let mut count = 0;
+        let mut consume = |bytes: &[u8], _| {
+            let text = std::str::from_utf8(bytes).unwrap();
+            let (t, i) = text.split_once(' ').unwrap();
+            let (t, i): (usize, u32) = (t.parse().unwrap(), i.parse().unwrap());
+            assert!(last[t].is_none_or(|prev| prev < i), "out of order");
+            last[t] = Some(i);
+            count += 1;
+            Ok(())
+        };
+        for handle in handles {
+            queue.drain(&mut consume).unwrap();
+            handle.join().unwrap();
+        }
+        queue.drain(&mut consume).unwrap();
+        assert_eq!(count, 8000);
+    }
+
+    #[test]
+    fn test_errors_go_to_queuing_thread() {
+        let queue = ChunkQueue::new(16);
+        let barrier = std::sync::Barrier::new(2);
+        assert!(queue.try_push(b"own", None));
+        std::thread::scope(|scope| {
+            let other = scope.spawn(|| {
+                assert!(queue.try_push(b"other", None));
+                barrier.wait();
+                barrier.wait();
+                let error = queue.take_error().unwrap_err();
+                assert_eq!(error.to_string(), "disk full");
+                assert!(queue.take_error().is_ok());
+            });
+
+            barrier.wait();
+            let fail = |bytes: &[u8], _| match bytes {
+                b"other" => Err(io::Error::other("disk full")),
+                _ => Ok(()),
+            };
+            queue.drain(fail).unwrap();
+            assert!(queue.take_error().is_ok());
+            barrier.wait();
+            other.join().unwrap();
+        });
+    }
+}
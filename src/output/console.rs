@@ -1,68 +1,287 @@
 //! Console output destination with optional ANSI color support.
+//!
+//! [`ConsoleOutput`] writes to stdout, stderr, or splits records between them
+//! by level (see [`ConsoleTarget`]). Whether colors are used is decided once
+//! per stream when the output is created (see [`ColorMode`]): in
+//! [`Auto`](ColorMode::Auto) mode, colors are used only if the stream is a
+//! terminal, `NO_COLOR` is unset or empty and `TERM` is not `dumb`; setting
+//! `CLICOLOR_FORCE` to anything but `0` enables them regardless of the stream.
+//! The escape sequence for each level comes from a [`ColorTheme`].
+//!
+//! Records are rendered as [`TextRecord`] lines by default, or in the pretty
+//! development format selected with [`ConsoleFormat::Pretty`].
+//!
+//! # Example
+//!
+//! ```
+//! use telelog::output::{ColorMode, ColorTheme, ConsoleOutput, ConsoleTarget};
+//! use telelog::LogLevel;
+//!
+//! let console = ConsoleOutput::new(true)
+//!     .with_target(ConsoleTarget::Split { stderr_level: LogLevel::Warning })
+//!     .with_color_mode(ColorMode::Auto)
+//!     .with_theme(ColorTheme::default().with_color(LogLevel::Info, "\x1b[34m"));
+//! ```
 
-use crate::output::{LogRecord, OutputDestination};
-use std::io::{self, Write};
+use crate::component::ComponentTracker;
+use crate::level::LogLevel;
+use crate::output::pretty::{PrettyRecord, PrettyStyle};
+use crate::output::{LogRecord, OutputDestination, OwnedRecord, TextRecord};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{self, IsTerminal, Write};
+use std::sync::Arc;
 
-/// Writes log records to stdout, optionally with ANSI color codes per log level.
+/// Which standard stream a [`ConsoleOutput`] writes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ConsoleTarget {
+    /// Every record goes to stdout.
+    #[default]
+    Stdout,
+    /// Every record goes to stderr.
+    Stderr,
+    /// Records at `stderr_level` or above go to stderr, the rest to stdout.
+    Split { stderr_level: LogLevel },
+}
+
+impl ConsoleTarget {
+    fn uses_stderr(&self, level: LogLevel) -> bool {
+        match self {
+            ConsoleTarget::Stdout => false,
+            ConsoleTarget::Stderr => true,
+            ConsoleTarget::Split { stderr_level } => level >= *stderr_level,
+        }
+    }
+}
+
+/// How a [`ConsoleOutput`] renders records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ConsoleFormat {
+    /// One [`TextRecord`] line per record, colored as a whole.
+    #[default]
+    Plain,
+    /// The human-friendly development format; see [`PrettyStyle`].
+    Pretty(PrettyStyle),
+}
+
+/// When a [`ConsoleOutput`] uses ANSI colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorMode {
+    /// Color terminals unless the environment disables it.
+    #[default]
+    Auto,
+    /// Always emit colors.
+    Always,
+    /// Never emit colors.
+    Never,
+}
+
+impl ColorMode {
+    /// Decides whether to color a stream, given whether it is a terminal and a
+    /// lookup of environment variables.
+    fn enabled(self, is_terminal: bool, env: impl Fn(&str) -> Option<String>) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                if env("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+                    return false;
+                }
+                if env("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+                    return true;
+                }
+                is_terminal && env("TERM").as_deref() != Some("dumb")
+            }
+        }
+    }
+}
+
+/// ANSI escape sequences used to color each log level.
+///
+/// The default theme uses cyan, green, yellow, red and bright red.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColorTheme {
+    pub debug: String,
+    pub info: String,
+    pub warning: String,
+    pub error: String,
+    pub critical: String,
+}
+
+impl Default for ColorTheme {
+    fn default() -> Self {
+        Self {
+            debug: "\x1b[36m".to_string(),
+            info: "\x1b[32m".to_string(),
+            warning: "\x1b[33m".to_string(),
+            error: "\x1b[31m".to_string(),
+            critical: "\x1b[91m".to_string(),
+        }
+    }
+}
+
+impl ColorTheme {
+    /// Sets the escape sequence for `level`, e.g. `"\x1b[1;35m"`.
+    pub fn with_color(mut self, level: LogLevel, escape: &str) -> Self {
+        let slot = match level {
+            LogLevel::Debug => &mut self.debug,
+            LogLevel::Info => &mut self.info,
+            LogLevel::Warning => &mut self.warning,
+            LogLevel::Error => &mut self.error,
+            LogLevel::Critical => &mut self.critical,
+        };
+        *slot = escape.to_string();
+        self
+    }
+
+    /// Returns the escape sequence for `level`.
+    pub fn color(&self, level: LogLevel) -> &str {
+        match level {
+            LogLevel::Debug => &self.debug,
+            LogLevel::Info => &self.info,
+            LogLevel::Warning => &self.warning,
+            LogLevel::Error => &self.error,
+            LogLevel::Critical => &self.critical,
+        }
+    }
+}
+
+/// Writes log records to stdout or stderr, optionally with ANSI color codes per
+/// log level.
 pub struct ConsoleOutput {
-    colored: bool,
+    target: ConsoleTarget,
+    mode: ColorMode,
+    theme: ColorTheme,
+    format: ConsoleFormat,
+    stdout_colored: bool,
+    stderr_colored: bool,
+    /// Reference point for relative timestamps.
+    start: DateTime<Utc>,
+    tracker: Option<Arc<ComponentTracker>>,
 }
 
 impl ConsoleOutput {
-    /// Creates a new console output.
+    /// Creates a new console output writing to stdout.
     ///
     /// # Arguments
     ///
-    /// * `colored` - Enable ANSI color codes for log levels (requires `console` feature)
+    /// * `colored` - Use ANSI color codes for log levels where the terminal
+    ///   and environment allow it ([`ColorMode::Auto`]); `false` disables them
     pub fn new(colored: bool) -> Self {
-        Self { colored }
+        let mode = if colored {
+            ColorMode::Auto
+        } else {
+            ColorMode::Never
+        };
+        Self {
+            target: ConsoleTarget::Stdout,
+            mode,
+            theme: ColorTheme::default(),
+            format: ConsoleFormat::Plain,
+            stdout_colored: false,
+            stderr_colored: false,
+            start: Utc::now(),
+            tracker: None,
+        }
+        .detect_colors()
     }
-}
 
-impl OutputDestination for ConsoleOutput {
-    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
-        if self.colored {
-            #[cfg(feature = "console")]
-            {
-                println!(
-                    "{}{} [{}] {}: {}\x1b[0m",
-                    record.level.color(),
-                    record.timestamp,
-                    record.level,
-                    record.logger,
-                    record.message
-                );
-            }
-            #[cfg(not(feature = "console"))]
-            {
-                println!(
-                    "{} [{}] {}: {}",
-                    record.timestamp, record.level, record.logger, record.message
-                );
-            }
+    /// Sets which stream records are written to.
+    pub fn with_target(mut self, target: ConsoleTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Sets when colors are used.
+    pub fn with_color_mode(mut self, mode: ColorMode) -> Self {
+        self.mode = mode;
+        self.detect_colors()
+    }
+
+    /// Sets the colors used for each level.
+    pub fn with_theme(mut self, theme: ColorTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Sets how records are rendered.
+    pub fn with_format(mut self, format: ConsoleFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Indents pretty-formatted messages by the number of components open in
+    /// `tracker`.
+    pub fn with_component_tracker(mut self, tracker: Arc<ComponentTracker>) -> Self {
+        self.tracker = Some(tracker);
+        self
+    }
+
+    fn detect_colors(mut self) -> Self {
+        let env = |name: &str| std::env::var(name).ok();
+        self.stdout_colored = self.mode.enabled(io::stdout().is_terminal(), env);
+        self.stderr_colored = self.mode.enabled(io::stderr().is_terminal(), env);
+        self
+    }
+
+    fn write_record(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        let (mut out, colored): (Box<dyn Write>, bool) = if self.target.uses_stderr(record.level) {
+            (Box::new(io::stderr().lock()), self.stderr_colored)
         } else {
-            println!(
-                "{} [{}] {}: {}",
-                record.timestamp, record.level, record.logger, record.message
-            );
+            (Box::new(io::stdout().lock()), self.stdout_colored)
+        };
+        match self.format {
+            ConsoleFormat::Plain if colored => writeln!(
+                out,
+                "{}{}\x1b[0m",
+                self.theme.color(record.level),
+                TextRecord(record)
+            ),
+            ConsoleFormat::Plain => writeln!(out, "{}", TextRecord(record)),
+            ConsoleFormat::Pretty(style) => {
+                let pretty = PrettyRecord {
+                    record,
+                    style,
+                    start: self.start,
+                    depth: self.tracker.as_ref().map_or(0, |t| t.depth()),
+                    theme: colored.then_some(&self.theme),
+                };
+                writeln!(out, "{}", pretty)
+            }
         }
+    }
+}
 
-        Ok(())
+impl OutputDestination for ConsoleOutput {
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        self.write_record(record)
     }
 
     fn flush(&self) -> io::Result<()> {
-        io::stdout().flush()
+        io::stdout().flush()?;
+        io::stderr().flush()
     }
 
     fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
-        io::stdout().write_all(bytes)
+        let stderr = match self.target {
+            ConsoleTarget::Stdout => false,
+            ConsoleTarget::Stderr => true,
+            ConsoleTarget::Split { .. } => {
+                let level = OwnedRecord::from_json(bytes).map_or(LogLevel::Info, |r| r.level);
+                self.target.uses_stderr(level)
+            }
+        };
+        if stderr {
+            io::stderr().write_all(bytes)
+        } else {
+            io::stdout().write_all(bytes)
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::level::LogLevel;
     use std::collections::HashMap;
 
     #[test]
@@ -76,8 +295,46 @@ mod tests {
                 message: "Test message",
                 context: &HashMap::new(),
                 data: None,
+                location: None,
             })
             .is_ok());
         assert!(output.flush().is_ok());
     }
+
+    #[test]
+    fn test_color_detection() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+
+        assert!(ColorMode::Auto.enabled(true, env(&[])));
+        assert!(!ColorMode::Auto.enabled(false, env(&[])));
+        assert!(!ColorMode::Auto.enabled(true, env(&[("NO_COLOR", "1")])));
+        assert!(ColorMode::Auto.enabled(true, env(&[("NO_COLOR", "")])));
+        assert!(!ColorMode::Auto.enabled(true, env(&[("TERM", "dumb")])));
+        assert!(ColorMode::Auto.enabled(false, env(&[("CLICOLOR_FORCE", "1")])));
+        assert!(!ColorMode::Auto.enabled(false, env(&[("CLICOLOR_FORCE", "0")])));
+        assert!(!ColorMode::Auto.enabled(false, env(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")])));
+        assert!(ColorMode::Always.enabled(false, env(&[("NO_COLOR", "1")])));
+        assert!(!ColorMode::Never.enabled(true, env(&[])));
+    }
+
+    #[test]
+    fn test_targets_and_theme() {
+        let split = ConsoleTarget::Split {
+            stderr_level: LogLevel::Error,
+        };
+        assert!(!split.uses_stderr(LogLevel::Warning));
+        assert!(split.uses_stderr(LogLevel::Critical));
+        assert!(ConsoleTarget::Stderr.uses_stderr(LogLevel::Debug));
+        assert!(!ConsoleTarget::Stdout.uses_stderr(LogLevel::Critical));
+
+        let theme = ColorTheme::default().with_color(LogLevel::Info, "\x1b[34m");
+        assert_eq!(theme.color(LogLevel::Info), "\x1b[34m");
+        assert_eq!(theme.color(LogLevel::Error), "\x1b[31m");
+    }
 }
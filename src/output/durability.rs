@@ -0,0 +1,124 @@
+//! Durability policies for file-based outputs.
+//!
+//! By default file outputs only push data to the operating system when their
+//! buffer fills or [`flush`](crate::output::OutputDestination::flush) is called,
+//! and never ask the OS to persist it. A [`SyncPolicy`] additionally flushes
+//! the buffer and calls `fsync` (`File::sync_data`) after selected records, so
+//! they survive a crash of the process or the machine.
+
+use crate::level::LogLevel;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::{Duration, Instant};
+
+/// When a file output forces written records to stable storage.
+///
+/// # Examples
+///
+/// ```
+/// use telelog::output::SyncPolicy;
+/// use telelog::LogLevel;
+///
+/// // Keep errors on disk even if buffered debug output is lost in a crash.
+/// let policy = SyncPolicy::AtLevel(LogLevel::Error);
+/// assert_ne!(policy, SyncPolicy::Never);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SyncPolicy {
+    /// Never call `fsync`; rely on the operating system to write data back.
+    #[default]
+    Never,
+    /// Sync after every `n` records.
+    EveryRecords(u32),
+    /// Sync on the first record written after `interval` has elapsed since the
+    /// previous sync.
+    Interval(Duration),
+    /// Sync after any record at or above the given level, together with
+    /// everything buffered before it.
+    ///
+    /// Pre-serialized records written through `write_bytes` carry no level and
+    /// never trigger a sync under this policy.
+    AtLevel(LogLevel),
+    /// Sync after every record.
+    Always,
+}
+
+/// Tracks records written since the last sync and decides when the next is due.
+#[derive(Debug)]
+pub(crate) struct SyncTracker {
+    policy: SyncPolicy,
+    unsynced: u32,
+    last_sync: Instant,
+}
+
+impl SyncTracker {
+    pub(crate) fn new(policy: SyncPolicy) -> Self {
+        Self {
+            policy,
+            unsynced: 0,
+            last_sync: Instant::now(),
+        }
+    }
+
+    /// Returns `true` unless the policy is [`SyncPolicy::Never`].
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.policy != SyncPolicy::Never
+    }
+
+    /// Accounts for one written record and returns `true` if a sync is due.
+    pub(crate) fn record_written(&mut self, level: Option<LogLevel>) -> bool {
+        self.unsynced = self.unsynced.saturating_add(1);
+        match self.policy {
+            SyncPolicy::Never => false,
+            SyncPolicy::EveryRecords(n) => self.unsynced >= n.max(1),
+            SyncPolicy::Interval(interval) => self.last_sync.elapsed() >= interval,
+            SyncPolicy::AtLevel(min) => level.is_some_and(|l| l.should_log(min)),
+            SyncPolicy::Always => true,
+        }
+    }
+
+    /// Flushes `writer` and syncs its file data to disk.
+    pub(crate) fn sync(&mut self, writer: &mut BufWriter<File>) -> io::Result<()> {
+        writer.flush()?;
+        writer.get_ref().sync_data()?;
+        self.unsynced = 0;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+}
+
+impl Default for SyncTracker {
+    fn default() -> Self {
+        Self::new(SyncPolicy::Never)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_records() {
+        let mut tracker = SyncTracker::new(SyncPolicy::EveryRecords(3));
+        assert!(!tracker.record_written(None));
+        assert!(!tracker.record_written(None));
+        assert!(tracker.record_written(None));
+    }
+
+    #[test]
+    fn test_at_level() {
+        let mut tracker = SyncTracker::new(SyncPolicy::AtLevel(LogLevel::Error));
+        assert!(!tracker.record_written(Some(LogLevel::Debug)));
+        assert!(!tracker.record_written(None));
+        assert!(tracker.record_written(Some(LogLevel::Error)));
+        assert!(tracker.record_written(Some(LogLevel::Critical)));
+    }
+
+    #[test]
+    fn test_never_and_always() {
+        assert!(!SyncTracker::new(SyncPolicy::Never).record_written(Some(LogLevel::Critical)));
+        assert!(SyncTracker::new(SyncPolicy::Always).record_written(None));
+        assert!(SyncTracker::new(SyncPolicy::Interval(Duration::ZERO)).record_written(None));
+    }
+}
@@ -0,0 +1,291 @@
+//! Failover between a primary and a secondary destination.
+//!
+//! [`FailoverOutput`] writes to its primary destination. A record the primary
+//! fails to write is written to the secondary instead, and after a number of
+//! consecutive failures all records go to the secondary. While failed over,
+//! every probe interval the next record is tried on the primary again; once it
+//! succeeds, the output fails back.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use telelog::output::{FailoverOutput, FileOutput, NetworkOutput, NetworkTransport};
+//! use std::time::Duration;
+//!
+//! let collector = NetworkOutput::new(NetworkTransport::Tcp("10.0.0.5:5170".parse().unwrap()));
+//! let failover = FailoverOutput::new(
+//!     Box::new(collector),
+//!     Box::new(FileOutput::new("fallback.log", true).unwrap()),
+//! )
+//! .with_threshold(5)
+//! .with_probe_interval(Duration::from_secs(10));
+//! ```
+
+use crate::output::{report_error, EncodedRecord, ErrorHandler, LogRecord, OutputDestination};
+use parking_lot::Mutex;
+use std::io;
+use std::time::{Duration, Instant};
+
+const DEFAULT_THRESHOLD: u32 = 3;
+const DEFAULT_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+struct State {
+    consecutive_errors: u32,
+    failed_over: bool,
+    next_probe: Instant,
+}
+
+/// Writes to a primary destination and falls back to a secondary one while
+/// the primary keeps failing.
+pub struct FailoverOutput {
+    primary: Box<dyn OutputDestination>,
+    secondary: Box<dyn OutputDestination>,
+    threshold: u32,
+    probe_interval: Duration,
+    error_handler: Option<ErrorHandler>,
+    state: Mutex<State>,
+}
+
+impl FailoverOutput {
+    /// Creates a failover output writing to `primary`, falling back to
+    /// `secondary`.
+    pub fn new(primary: Box<dyn OutputDestination>, secondary: Box<dyn OutputDestination>) -> Self {
+        Self {
+            primary,
+            secondary,
+            threshold: DEFAULT_THRESHOLD,
+            probe_interval: DEFAULT_PROBE_INTERVAL,
+            error_handler: None,
+            state: Mutex::new(State {
+                consecutive_errors: 0,
+                failed_over: false,
+                next_probe: Instant::now(),
+            }),
+        }
+    }
+
+    /// Sets after how many consecutive primary errors to fail over (3 by
+    /// default).
+    pub fn with_threshold(mut self, threshold: u32) -> Self {
+        self.threshold = threshold.max(1);
+        self
+    }
+
+    /// Sets how often the primary is retried while failed over (30s by
+    /// default).
+    pub fn with_probe_interval(mut self, interval: Duration) -> Self {
+        self.probe_interval = interval;
+        self
+    }
+
+    /// Reports primary errors, fail-over and fail-back to `handler` instead of
+    /// stderr.
+    pub fn with_error_handler(mut self, handler: ErrorHandler) -> Self {
+        self.error_handler = Some(handler);
+        self
+    }
+
+    /// Returns whether records currently go to the secondary destination.
+    pub fn is_failed_over(&self) -> bool {
+        self.state.lock().failed_over
+    }
+
+    fn report(&self, output: &dyn OutputDestination, error: &io::Error) {
+        report_error(self.error_handler.as_ref(), output.name(), error);
+    }
+
+    /// Writes with `write` to the primary if it is active or due for a probe,
+    /// and to the secondary otherwise or if the primary fails.
+    fn write_with(
+        &self,
+        write: impl Fn(&dyn OutputDestination) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let try_primary = {
+            let mut state = self.state.lock();
+            if !state.failed_over {
+                true
+            } else if Instant::now() >= state.next_probe {
+                state.next_probe = Instant::now() + self.probe_interval;
+                true
+            } else {
+                false
+            }
+        };
+
+        if try_primary {
+            match write(&*self.primary) {
+                Ok(()) => {
+                    let mut state = self.state.lock();
+                    let failed_back = state.failed_over;
+                    state.consecutive_errors = 0;
+                    state.failed_over = false;
+                    drop(state);
+                    if failed_back {
+                        let notice =
+                            io::Error::other(format!("failing back to {}", self.primary.name()));
+                        report_error(self.error_handler.as_ref(), self.name(), &notice);
+                    }
+                    return Ok(());
+                }
+                Err(e) => {
+                    self.report(&*self.primary, &e);
+                    let mut state = self.state.lock();
+                    state.consecutive_errors = state.consecutive_errors.saturating_add(1);
+                    if !state.failed_over && state.consecutive_errors >= self.threshold {
+                        state.failed_over = true;
+                        state.next_probe = Instant::now() + self.probe_interval;
+                        let notice = io::Error::other(format!(
+                            "failing over to {} after {} errors",
+                            self.secondary.name(),
+                            state.consecutive_errors
+                        ));
+                        drop(state);
+                        report_error(self.error_handler.as_ref(), self.name(), &notice);
+                    }
+                }
+            }
+        }
+        write(&*self.secondary)
+    }
+}
+
+impl OutputDestination for FailoverOutput {
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        self.write_with(|output| output.write(record))
+    }
+
+    /// Flushes both destinations; returns the first error.
+    fn flush(&self) -> io::Result<()> {
+        let primary = self.primary.flush();
+        let secondary = self.secondary.flush();
+        primary.and(secondary)
+    }
+
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        self.write_with(|output| output.write_bytes(bytes))
+    }
+
+    fn write_encoded(&self, encoded: &EncodedRecord<'_, '_>) -> io::Result<()> {
+        self.write_with(|output| output.write_encoded(encoded))
+    }
+
+    fn reopen(&self) -> io::Result<()> {
+        let primary = self.primary.reopen();
+        let secondary = self.secondary.reopen();
+        primary.and(secondary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::LogLevel;
+    use crate::output::MemoryOutput;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    struct Flaky {
+        failing: AtomicBool,
+        memory: MemoryOutput,
+    }
+
+    impl OutputDestination for Flaky {
+        fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "down"));
+            }
+            self.memory.write(record)
+        }
+
+        fn flush(&self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn write(output: &dyn OutputDestination, message: &str) {
+        let ctx = HashMap::new();
+        output
+            .write(&LogRecord {
+                timestamp: "2025-09-07T10:30:00Z",
+                level: LogLevel::Info,
+                logger: "app",
+                message,
+                context: &ctx,
+                data: None,
+                location: None,
+            })
+            .unwrap();
+    }
+
+    fn messages(memory: &MemoryOutput) -> Vec<String> {
+        memory.drain().iter().map(|r| r.message.clone()).collect()
+    }
+
+    #[test]
+    fn test_fails_over_and_back() {
+        let primary = Arc::new(Flaky {
+            failing: AtomicBool::new(false),
+            memory: MemoryOutput::new(10),
+        });
+        let secondary = Arc::new(MemoryOutput::new(10));
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&errors);
+        let failover = FailoverOutput::new(
+            Box::new(Arc::clone(&primary)),
+            Box::new(Arc::clone(&secondary)),
+        )
+        .with_threshold(2)
+        .with_probe_interval(Duration::from_millis(50))
+        .with_error_handler(ErrorHandler::new(move |name, e| {
+            seen.lock().push(format!("{}: {}", name, e));
+        }));
+
+        write(&failover, "a");
+        primary.failing.store(true, Ordering::SeqCst);
+        write(&failover, "b");
+        assert!(!failover.is_failed_over());
+        write(&failover, "c");
+        assert!(failover.is_failed_over());
+        write(&failover, "d");
+        assert_eq!(
+            *errors.lock(),
+            [
+                "Flaky: down",
+                "Flaky: down",
+                "FailoverOutput: failing over to MemoryOutput after 2 errors"
+            ]
+        );
+
+        primary.failing.store(false, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(60));
+        write(&failover, "e");
+        assert!(!failover.is_failed_over());
+        assert_eq!(
+            errors.lock().last().unwrap(),
+            "FailoverOutput: failing back to Flaky"
+        );
+
+        assert_eq!(messages(&primary.memory), ["a", "e"]);
+        assert_eq!(messages(&secondary), ["b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_logger_error_handler() {
+        use crate::{Config, Logger};
+
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&errors);
+        let config = Config::new()
+            .with_console_output(false)
+            .with_error_handler(move |name, e| seen.lock().push(format!("{}: {}", name, e)));
+        let logger = Logger::with_config("app", config);
+        logger.add_output(Box::new(Arc::new(Flaky {
+            failing: AtomicBool::new(true),
+            memory: MemoryOutput::new(10),
+        })));
+
+        logger.info("lost");
+        assert_eq!(*errors.lock(), ["Flaky: down"]);
+    }
+}
@@ -1,17 +1,54 @@
-//! File-based output destinations: plain/JSON file and size-rotating file.
+//! File-based output destinations: plain/JSON file and size- or time-rotating file.
 
-use crate::output::{LogRecord, OutputDestination};
+use crate::level::LogLevel;
+use crate::output::chunk_queue::ChunkQueue;
+use crate::output::durability::{SyncPolicy, SyncTracker};
+use crate::output::rotation::{Compression, Compressor, RotationSchedule, RotationTimeZone};
+use crate::output::{
+    json, report_error, EncodedRecord, ErrorHandler, LogRecord, OutputDestination, TextRecord,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use parking_lot::Mutex;
 use std::fs::{File, OpenOptions};
 use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{fence, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Most lines queued for the thread holding the file before writers wait for
+/// it instead.
+const MAX_QUEUED_LINES: usize = 1024;
 
 /// Appends log records to a single file in either plain-text or JSON format.
+///
+/// The file can be reopened on demand with [`reopen`](OutputDestination::reopen),
+/// or automatically with [`with_reopen_check`](Self::with_reopen_check) when an
+/// external tool such as logrotate moves or deletes it.
+///
+/// In [multi-process mode](Self::with_multi_process) each record is written
+/// unbuffered with a single `write(2)` on the `O_APPEND` file descriptor, so
+/// several processes can share one log file without interleaving records.
+///
+/// A [`SyncPolicy`] set with [`with_sync_policy`](Self::with_sync_policy)
+/// controls when written records are forced to disk with `fsync`.
+///
+/// Records are serialized into a reusable per-thread buffer without holding
+/// any lock. A thread that finds the file busy pushes its line onto a
+/// lock-free queue and returns; whichever thread holds the file writes the
+/// queued lines, in order, before its own. Once the queue is full, writers wait
+/// for the file instead. If a queued line cannot be written, the error is
+/// returned by the next write of the thread that queued it. Lines still queued
+/// when the output is flushed or dropped are written.
 pub struct FileOutput {
     writer: Arc<Mutex<BufWriter<File>>>,
+    pending: ChunkQueue,
     path: PathBuf,
     json_format: bool,
+    watch: Option<Mutex<FileWatch>>,
+    multi_process: bool,
+    sync: Option<Mutex<SyncTracker>>,
+    error_handler: Option<ErrorHandler>,
 }
 
 impl FileOutput {
@@ -37,52 +74,355 @@ impl FileOutput {
 
         Ok(Self {
             writer,
+            pending: ChunkQueue::new(MAX_QUEUED_LINES),
             path,
             json_format,
+            watch: None,
+            multi_process: false,
+            sync: None,
+            error_handler: None,
         })
     }
 
+    /// Sets when written records are flushed and synced to disk.
+    pub fn with_sync_policy(mut self, policy: SyncPolicy) -> Self {
+        let tracker = SyncTracker::new(policy);
+        self.sync = tracker.is_enabled().then(|| Mutex::new(tracker));
+        self
+    }
+
+    /// Writes every record with a single unbuffered append so that other
+    /// processes appending to the same file never split it.
+    pub fn with_multi_process(mut self, enabled: bool) -> Self {
+        self.multi_process = enabled;
+        self
+    }
+
+    /// Reports errors flushing the file on drop to `handler` instead of
+    /// stderr.
+    pub fn with_error_handler(mut self, handler: ErrorHandler) -> Self {
+        self.error_handler = Some(handler);
+        self
+    }
+
+    /// Checks at most once per `interval` whether the path still refers to the
+    /// open file, and reopens it if the file was moved or deleted.
+    pub fn with_reopen_check(mut self, interval: Duration) -> Self {
+        let mut watch = FileWatch::new(interval);
+        watch.opened(&self.path);
+        self.watch = Some(Mutex::new(watch));
+        self
+    }
+
     /// Returns the path to the log file.
     pub fn path(&self) -> &Path {
         &self.path
     }
 }
 
+impl FileOutput {
+    fn reopen_if_moved(&self) -> io::Result<()> {
+        if let Some(watch) = &self.watch {
+            if watch.lock().is_stale(&self.path) {
+                self.reopen()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn sync_if_due(&self, writer: &mut BufWriter<File>, level: Option<LogLevel>) -> io::Result<()> {
+        if let Some(sync) = &self.sync {
+            let mut sync = sync.lock();
+            if sync.record_written(level) {
+                sync.sync(writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn append(&self, bytes: &[u8], level: Option<LogLevel>) -> io::Result<()> {
+        self.reopen_if_moved()?;
+        if !self.multi_process {
+            return self.write_line(bytes, level);
+        }
+        let result = {
+            let mut writer = self.writer.lock();
+            // Bypass the buffer: one append per record keeps it contiguous.
+            writer
+                .get_mut()
+                .write_all(bytes)
+                .and_then(|()| self.sync_if_due(&mut writer, level))
+        };
+        result.and(self.write_stragglers())
+    }
+
+    /// Writes a line if the file is free, or queues it for the thread holding
+    /// the file otherwise.
+    ///
+    /// An error writing a line this thread queued earlier is returned here,
+    /// once the line has been written by another thread.
+    fn write_line(&self, line: &[u8], level: Option<LogLevel>) -> io::Result<()> {
+        let result = match self.writer.try_lock() {
+            Some(mut writer) => self.write_locked(&mut writer, line, level),
+            None if self.pending.try_push(line, level) => Ok(()),
+            // The queue is full: wait for the file rather than grow it.
+            None => self.write_locked(&mut self.writer.lock(), line, level),
+        };
+        result
+            .and(self.write_stragglers())
+            .and(self.pending.take_error())
+    }
+
+    /// Writes the queued lines, in one pass, followed by `line`.
+    fn write_locked(
+        &self,
+        writer: &mut BufWriter<File>,
+        line: &[u8],
+        level: Option<LogLevel>,
+    ) -> io::Result<()> {
+        let pending = self.write_pending(writer);
+        let written = writer
+            .write_all(line)
+            .and_then(|()| self.sync_if_due(writer, level));
+        written.and(pending)
+    }
+
+    /// Writes, in one pass, the lines queued while the file was held, if it is
+    /// free. Each writer makes at most this one extra pass after releasing the
+    /// file, so it never keeps writing other threads' lines indefinitely;
+    /// lines queued after the pass are written by the next write or flush.
+    fn write_stragglers(&self) -> io::Result<()> {
+        fence(Ordering::SeqCst);
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        match self.writer.try_lock() {
+            Some(mut writer) => self.write_pending(&mut writer),
+            // The holder checks the queue after releasing the file.
+            None => Ok(()),
+        }
+    }
+
+    fn write_pending(&self, writer: &mut BufWriter<File>) -> io::Result<()> {
+        self.pending.drain(|line, level| {
+            writer.write_all(line)?;
+            self.sync_if_due(writer, level)
+        })
+    }
+}
+
+/// Serializes `record` as one newline-terminated JSON or text line.
+fn serialize_line(line: &mut Vec<u8>, record: &LogRecord<'_>, json: bool) -> io::Result<()> {
+    if json {
+        json::encode_line(line, record);
+    } else {
+        writeln!(line, "{}", TextRecord(record))?;
+    }
+    Ok(())
+}
+
 impl OutputDestination for FileOutput {
     fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        json::with_buffer(|line| {
+            serialize_line(line, record, self.json_format)?;
+            self.append(line, Some(record.level))
+        })
+    }
+
+    fn write_encoded(&self, encoded: &EncodedRecord<'_, '_>) -> io::Result<()> {
+        if !self.json_format {
+            return self.write(encoded.record());
+        }
+        self.append(encoded.json(), Some(encoded.record().level))
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let result = {
+            let mut writer = self.writer.lock();
+            self.write_pending(&mut writer)
+                .and_then(|()| writer.flush())
+        };
+        result
+            .and(self.write_stragglers())
+            .and(self.pending.take_error())
+    }
+
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        self.append(bytes, None)
+    }
+
+    fn reopen(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
         let mut writer = self.writer.lock();
+        // The old file may already be gone; losing its tail is preferable to
+        // failing the reopen.
+        let _ = self.write_pending(&mut writer);
+        match &self.sync {
+            Some(sync) => {
+                let _ = sync.lock().sync(&mut writer);
+            }
+            None => {
+                let _ = writer.flush();
+            }
+        }
+        *writer = BufWriter::new(file);
+        drop(writer);
 
-        if self.json_format {
-            let json = serde_json::to_string(record)
-                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-            writeln!(writer, "{}", json)?;
-        } else {
-            writeln!(
-                writer,
-                "{} [{}] {}: {}",
-                record.timestamp, record.level, record.logger, record.message
-            )?;
+        if let Some(watch) = &self.watch {
+            watch.lock().opened(&self.path);
         }
+        self.write_stragglers()
+    }
+}
 
-        Ok(())
+impl Drop for FileOutput {
+    fn drop(&mut self) {
+        let mut writer = self.writer.lock();
+        let result = self
+            .pending
+            .drain(|line, _| writer.write_all(line))
+            .and_then(|()| writer.flush());
+        if let Err(e) = result {
+            report_error(self.error_handler.as_ref(), "FileOutput", &e);
+        }
     }
+}
 
-    fn flush(&self) -> io::Result<()> {
-        self.writer.lock().flush()
+/// Device and inode pair identifying a file independently of its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileIdentity {
+    dev: u64,
+    ino: u64,
+}
+
+impl FileIdentity {
+    #[cfg(unix)]
+    fn of(metadata: &std::fs::Metadata) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        Some(Self {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn of(_metadata: &std::fs::Metadata) -> Option<Self> {
+        None
+    }
+}
+
+/// Periodically detects that a log file was moved or deleted behind the writer.
+///
+/// On platforms without inode numbers only deletion is detected.
+struct FileWatch {
+    interval: Duration,
+    next_check: Instant,
+    identity: Option<FileIdentity>,
+}
+
+impl FileWatch {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_check: Instant::now() + interval,
+            identity: None,
+        }
+    }
+
+    /// Records the identity of the file just opened at `path`.
+    fn opened(&mut self, path: &Path) {
+        self.identity = std::fs::metadata(path)
+            .ok()
+            .and_then(|m| FileIdentity::of(&m));
+        self.next_check = Instant::now() + self.interval;
+    }
+
+    /// Returns `true` if a check is due and `path` no longer refers to the open file.
+    fn is_stale(&mut self, path: &Path) -> bool {
+        let now = Instant::now();
+        if now < self.next_check {
+            return false;
+        }
+        self.next_check = now + self.interval;
+
+        match std::fs::metadata(path) {
+            Ok(metadata) => match (self.identity, FileIdentity::of(&metadata)) {
+                (Some(open), Some(current)) => open != current,
+                _ => false,
+            },
+            Err(_) => true,
+        }
     }
 }
 
-/// Appends log records to a file, rotating to a new file when a size limit is reached.
+/// Appends log records to a file, rotating to a new file when a size limit is
+/// reached or, with a [`RotationSchedule`], when a time boundary is crossed.
+///
+/// With size-only rotation, rotated files are numbered (e.g. `app.1.log`,
+/// `app.2.log`). With a schedule, they are stamped with the period they cover
+/// (e.g. `app.2026-10-16.log`), and further size rotations within the same
+/// period add a counter (`app.2026-10-16.1.log`).
 ///
-/// Rotated files are numbered (e.g. `app.log.1`, `app.log.2`); the oldest is deleted
-/// once `max_files` is exceeded.
+/// After every rotation, and once on startup, rotated segments are pruned
+/// oldest-first until at most `max_files` remain and, if configured, none is
+/// older than the maximum age and all segments plus the active file fit in the
+/// total size budget. Segments can optionally be compressed in the background.
+///
+/// In [multi-process mode](Self::with_multi_process) records are appended
+/// unbuffered, the size limit is checked against the file on disk, and
+/// rotation is serialized through an advisory lock on `<base_path>.lock`: the
+/// first process to take the lock rotates, the others notice that the base
+/// path now refers to a new file and simply reopen it.
+///
+/// With a [`SyncPolicy`] other than `Never`, each segment is also synced to
+/// disk before it is renamed.
 pub struct RotatingFileOutput {
     base_path: PathBuf,
     max_size: u64,
     max_files: u32,
-    current_size: Arc<Mutex<u64>>,
-    current_file: Arc<Mutex<Option<BufWriter<File>>>>,
+    schedule: RotationSchedule,
+    time_zone: RotationTimeZone,
+    compression: Compression,
+    max_age: Option<Duration>,
+    max_total_size: Option<u64>,
+    state: Mutex<RotatingState>,
+    /// Serializes segment renames with the background compressor.
+    fs_lock: Arc<Mutex<()>>,
+    compressor: Option<Compressor>,
+    reopen_check: Option<Duration>,
+    multi_process: bool,
     json_format: bool,
+    error_handler: Option<ErrorHandler>,
+}
+
+/// Mutable state of a [`RotatingFileOutput`], guarded by a single lock.
+#[derive(Default)]
+struct RotatingState {
+    writer: Option<BufWriter<File>>,
+    size: u64,
+    /// Local start of the period the current file belongs to.
+    period: Option<NaiveDateTime>,
+    /// Instant at which the current period ends.
+    next_rotation: Option<DateTime<Utc>>,
+    /// Whether leftover segments from earlier runs have been cleaned up.
+    started: bool,
+    watch: Option<FileWatch>,
+    sync: SyncTracker,
+}
+
+/// A rotated file found next to the active log file.
+struct Segment {
+    modified: SystemTime,
+    path: PathBuf,
+    size: u64,
+    compressed: bool,
 }
 
 impl RotatingFileOutput {
@@ -110,123 +450,475 @@ impl RotatingFileOutput {
             base_path,
             max_size,
             max_files,
-            current_size: Arc::new(Mutex::new(0)),
-            current_file: Arc::new(Mutex::new(None)),
+            schedule: RotationSchedule::Never,
+            time_zone: RotationTimeZone::Utc,
+            compression: Compression::None,
+            max_age: None,
+            max_total_size: None,
+            state: Mutex::new(RotatingState::default()),
+            fs_lock: Arc::new(Mutex::new(())),
+            compressor: None,
+            reopen_check: None,
+            multi_process: false,
             json_format,
+            error_handler: None,
         })
     }
 
-    fn rotate_if_needed(&self) -> io::Result<()> {
-        let mut size = self.current_size.lock();
-        if *size >= self.max_size {
-            self.rotate_files()?;
-            *size = 0;
+    /// Also rotates whenever a period of `schedule` ends.
+    ///
+    /// Size-based rotation stays active; pass `u64::MAX` as `max_size` for
+    /// purely time-based rotation.
+    pub fn with_schedule(mut self, schedule: RotationSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Sets the time zone used for period boundaries and file-name dates.
+    pub fn with_time_zone(mut self, time_zone: RotationTimeZone) -> Self {
+        self.time_zone = time_zone;
+        self
+    }
+
+    /// Compresses rotated segments on a background thread.
+    ///
+    /// If the compression thread cannot be started, segments are left uncompressed.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compressor = None;
+        self.compression = compression;
+        if compression.extension().is_some() {
+            let handler = self.error_handler.clone();
+            match Compressor::spawn(compression, Arc::clone(&self.fs_lock), handler) {
+                Ok(compressor) => self.compressor = Some(compressor),
+                Err(e) => report_error(self.error_handler.as_ref(), "RotatingFileOutput", &e),
+            }
         }
+        self
+    }
+
+    /// Reports compression errors to `handler` instead of stderr.
+    pub fn with_error_handler(mut self, handler: ErrorHandler) -> Self {
+        self.error_handler = Some(handler);
+        // Restart a running compressor so that it reports to `handler` too.
+        let compression = self.compression;
+        self.with_compression(compression)
+    }
+
+    /// Deletes rotated segments last modified more than `max_age` ago.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Deletes the oldest rotated segments once all segments plus the active
+    /// file exceed `max_total_size` bytes.
+    pub fn with_max_total_size(mut self, max_total_size: u64) -> Self {
+        self.max_total_size = Some(max_total_size);
+        self
+    }
+
+    /// Checks at most once per `interval` whether the active file was moved or
+    /// deleted externally, and starts a new one if so.
+    pub fn with_reopen_check(mut self, interval: Duration) -> Self {
+        self.reopen_check = Some(interval);
+        self
+    }
+
+    /// Sets when written records are flushed and synced to disk.
+    pub fn with_sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.state.get_mut().sync = SyncTracker::new(policy);
+        self
+    }
+
+    /// Coordinates appends and rotation with other processes writing to the
+    /// same base path.
+    pub fn with_multi_process(mut self, enabled: bool) -> Self {
+        self.multi_process = enabled;
+        self
+    }
+
+    /// Returns the path of the file currently being written.
+    pub fn path(&self) -> &Path {
+        &self.base_path
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        with_suffix(self.base_path.clone(), Some("lock"))
+    }
+
+    fn write_chunk(
+        &self,
+        bytes: &[u8],
+        level: Option<LogLevel>,
+        now: DateTime<Utc>,
+    ) -> io::Result<()> {
+        let mut state = self.state.lock();
+        let moved = state
+            .watch
+            .as_mut()
+            .is_some_and(|watch| watch.is_stale(&self.base_path));
+        if moved {
+            Self::close_file(&mut state);
+        }
+        self.ensure_file(&mut state, now)?;
+
+        if self.multi_process {
+            // Other processes append too, so the local byte count is not enough.
+            if let Some(ref writer) = state.writer {
+                state.size = writer.get_ref().metadata()?.len();
+            }
+        }
+
+        let size_exceeded = state.size >= self.max_size;
+        let period_ended = state.next_rotation.is_some_and(|next| now >= next);
+        if size_exceeded || period_ended {
+            if self.multi_process {
+                self.rotate_shared(&mut state, now)?;
+            } else {
+                self.rotate_files(&mut state)?;
+                self.ensure_file(&mut state, now)?;
+            }
+        }
+
+        let state = &mut *state;
+        if let Some(ref mut writer) = state.writer {
+            if self.multi_process {
+                writer.get_mut().write_all(bytes)?;
+            } else {
+                writer.write_all(bytes)?;
+            }
+            if state.sync.record_written(level) {
+                state.sync.sync(writer)?;
+            }
+        }
+        state.size += bytes.len() as u64;
         Ok(())
     }
 
-    fn rotate_files(&self) -> io::Result<()> {
-        {
-            let mut current = self.current_file.lock();
-            if let Some(mut writer) = current.take() {
+    /// Rotates under the shared lock file, unless another process already did.
+    fn rotate_shared(&self, state: &mut RotatingState, now: DateTime<Utc>) -> io::Result<()> {
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.lock_path())?;
+        lock_file.lock()?;
+
+        let open = state
+            .writer
+            .as_ref()
+            .and_then(|w| w.get_ref().metadata().ok())
+            .and_then(|m| FileIdentity::of(&m));
+        let on_disk = std::fs::metadata(&self.base_path)
+            .ok()
+            .and_then(|m| FileIdentity::of(&m));
+
+        let result = if open == on_disk {
+            self.rotate_files(state)
+        } else {
+            Self::close_file(state);
+            Ok(())
+        }
+        .and_then(|()| self.ensure_file(state, now));
+
+        lock_file.unlock()?;
+        result
+    }
+
+    /// Closes the active file without rotating it; the next write reopens the path.
+    fn close_file(state: &mut RotatingState) {
+        if let Some(mut writer) = state.writer.take() {
+            let _ = writer.flush();
+            if state.sync.is_enabled() {
+                let _ = state.sync.sync(&mut writer);
+            }
+        }
+        state.period = None;
+        state.next_rotation = None;
+        state.size = 0;
+    }
+
+    fn rotate_files(&self, state: &mut RotatingState) -> io::Result<()> {
+        if let Some(mut writer) = state.writer.take() {
+            if state.sync.is_enabled() {
+                state.sync.sync(&mut writer)?;
+            } else {
                 writer.flush()?;
             }
         }
+        let period = state.period.take();
+        state.next_rotation = None;
+        state.size = 0;
 
+        {
+            let _fs = self.fs_lock.lock();
+            match period {
+                Some(period) if self.schedule.is_timed() => self.rotate_dated(period)?,
+                _ => self.rotate_numbered()?,
+            }
+        }
+
+        self.cleanup_segments()
+    }
+
+    fn rotate_numbered(&self) -> io::Result<()> {
         for i in (1..self.max_files).rev() {
-            let old_path = self.get_rotated_path(i);
-            let new_path = self.get_rotated_path(i + 1);
-            if old_path.exists() {
-                if new_path.exists() {
-                    std::fs::remove_file(&new_path)?;
+            for suffix in self.segment_suffixes() {
+                let old_path = with_suffix(self.get_rotated_path(&i.to_string()), suffix);
+                let new_path = with_suffix(self.get_rotated_path(&(i + 1).to_string()), suffix);
+                if old_path.exists() {
+                    if new_path.exists() {
+                        std::fs::remove_file(&new_path)?;
+                    }
+                    std::fs::rename(&old_path, &new_path)?;
                 }
-                std::fs::rename(&old_path, &new_path)?;
             }
         }
 
         if self.base_path.exists() {
-            std::fs::rename(&self.base_path, self.get_rotated_path(1))?;
+            std::fs::rename(&self.base_path, self.get_rotated_path("1"))?;
         }
 
         Ok(())
     }
 
-    fn get_rotated_path(&self, number: u32) -> PathBuf {
-        let mut path = self.base_path.clone();
-        let stem = path
+    fn rotate_dated(&self, period: NaiveDateTime) -> io::Result<()> {
+        if !self.base_path.exists() {
+            return Ok(());
+        }
+
+        let label = self.schedule.label(period);
+        let mut tag = label.clone();
+        let mut counter = 1;
+        while self
+            .segment_suffixes()
+            .any(|suffix| with_suffix(self.get_rotated_path(&tag), suffix).exists())
+        {
+            tag = format!("{}.{}", label, counter);
+            counter += 1;
+        }
+        std::fs::rename(&self.base_path, self.get_rotated_path(&tag))
+    }
+
+    /// Applies retention limits and queues uncompressed segments for compression.
+    fn cleanup_segments(&self) -> io::Result<()> {
+        let pending = {
+            let _fs = self.fs_lock.lock();
+            self.apply_retention(SystemTime::now())?
+        };
+
+        if let Some(compressor) = &self.compressor {
+            for path in pending {
+                compressor.submit(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes segments that violate the retention limits, newest kept first.
+    ///
+    /// Returns the uncompressed segments that were kept.
+    fn apply_retention(&self, now: SystemTime) -> io::Result<Vec<PathBuf>> {
+        let mut segments = self.rotated_segments()?;
+        segments.sort_by(|a, b| (b.modified, &b.path).cmp(&(a.modified, &a.path)));
+
+        let mut total = std::fs::metadata(&self.base_path)
+            .map(|m| m.len())
+            .unwrap_or(0);
+        let mut pending = Vec::new();
+        for (index, segment) in segments.into_iter().enumerate() {
+            total += segment.size;
+            let too_many = index >= self.max_files as usize;
+            let too_old = self.max_age.is_some_and(|max_age| {
+                now.duration_since(segment.modified)
+                    .is_ok_and(|age| age > max_age)
+            });
+            let too_big = self.max_total_size.is_some_and(|max| total > max);
+
+            if too_many || too_old || too_big {
+                remove_if_exists(&segment.path)?;
+            } else if !segment.compressed {
+                pending.push(segment.path);
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Lists rotated segments (numbered or date-stamped, plain or compressed)
+    /// next to the base file.
+    fn rotated_segments(&self) -> io::Result<Vec<Segment>> {
+        let (stem, ext) = self.stem_and_ext();
+        let dir = match self.base_path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let prefix = format!("{}.", stem);
+        let suffix = if ext.is_empty() {
+            String::new()
+        } else {
+            format!(".{}", ext)
+        };
+
+        let mut segments = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(rest) = name.strip_prefix(&prefix) else {
+                continue;
+            };
+            let (rest, compressed) = match rest
+                .strip_suffix(".gz")
+                .or_else(|| rest.strip_suffix(".zst"))
+            {
+                Some(rest) => (rest, true),
+                None => (rest, false),
+            };
+            let is_segment = rest.strip_suffix(&suffix).is_some_and(|tag| {
+                tag.starts_with(|c: char| c.is_ascii_digit())
+                    && tag
+                        .chars()
+                        .all(|c| c.is_ascii_digit() || matches!(c, '-' | 'T' | '.'))
+            });
+            if is_segment {
+                // Another process may have removed or compressed it meanwhile.
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                segments.push(Segment {
+                    modified: metadata.modified()?,
+                    path: entry.path(),
+                    size: metadata.len(),
+                    compressed,
+                });
+            }
+        }
+        Ok(segments)
+    }
+
+    /// File-name suffixes a rotated segment may carry: none, or the compression extension.
+    fn segment_suffixes(&self) -> impl Iterator<Item = Option<&'static str>> {
+        std::iter::once(None).chain(self.compression.extension().map(Some))
+    }
+
+    fn stem_and_ext(&self) -> (String, String) {
+        let stem = self
+            .base_path
             .file_stem()
             .unwrap_or_default()
             .to_string_lossy()
             .into_owned();
-        let ext = path
+        let ext = self
+            .base_path
             .extension()
             .unwrap_or_default()
             .to_string_lossy()
             .into_owned();
+        (stem, ext)
+    }
+
+    fn get_rotated_path(&self, tag: &str) -> PathBuf {
+        let mut path = self.base_path.clone();
+        let (stem, ext) = self.stem_and_ext();
 
         if ext.is_empty() {
-            path.set_file_name(format!("{}.{}", stem, number));
+            path.set_file_name(format!("{}.{}", stem, tag));
         } else {
-            path.set_file_name(format!("{}.{}.{}", stem, number, ext));
+            path.set_file_name(format!("{}.{}.{}", stem, tag, ext));
         }
         path
     }
 
-    fn ensure_file(&self) -> io::Result<()> {
-        let mut current = self.current_file.lock();
-        if current.is_none() {
-            let file = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&self.base_path)?;
-            *self.current_size.lock() = file.metadata()?.len();
-            *current = Some(BufWriter::new(file));
+    fn ensure_file(&self, state: &mut RotatingState, now: DateTime<Utc>) -> io::Result<()> {
+        if state.writer.is_some() {
+            return Ok(());
+        }
+
+        if !state.started {
+            state.started = true;
+            self.cleanup_segments()?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.base_path)?;
+        let metadata = file.metadata()?;
+        state.size = metadata.len();
+
+        if self.schedule.is_timed() {
+            // A non-empty file left over from an earlier period is attributed to
+            // the period it was last written in, so it rotates out immediately.
+            let written_at = match metadata.modified() {
+                Ok(modified) if state.size > 0 => DateTime::<Utc>::from(modified).min(now),
+                _ => now,
+            };
+            let period = self
+                .schedule
+                .period_start(self.time_zone.to_local(written_at));
+            state.period = Some(period);
+            state.next_rotation = Some(self.time_zone.to_utc(self.schedule.next_period(period)));
+        }
+
+        if let Some(interval) = self.reopen_check {
+            state
+                .watch
+                .get_or_insert_with(|| FileWatch::new(interval))
+                .opened(&self.base_path);
         }
+
+        state.writer = Some(BufWriter::new(file));
         Ok(())
     }
 }
 
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn with_suffix(path: PathBuf, suffix: Option<&str>) -> PathBuf {
+    match suffix {
+        Some(ext) => {
+            let mut path = path.into_os_string();
+            path.push(".");
+            path.push(ext);
+            PathBuf::from(path)
+        }
+        None => path,
+    }
+}
+
 impl OutputDestination for RotatingFileOutput {
     fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
-        self.rotate_if_needed()?;
-        self.ensure_file()?;
-
-        let mut current = self.current_file.lock();
-        if let Some(ref mut writer) = *current {
-            let content = if self.json_format {
-                let json = serde_json::to_string(record)
-                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-                format!("{}\n", json)
-            } else {
-                format!(
-                    "{} [{}] {}: {}\n",
-                    record.timestamp, record.level, record.logger, record.message
-                )
-            };
+        json::with_buffer(|line| {
+            serialize_line(line, record, self.json_format)?;
+            self.write_chunk(line, Some(record.level), Utc::now())
+        })
+    }
 
-            writer.write_all(content.as_bytes())?;
-            *self.current_size.lock() += content.len() as u64;
+    fn write_encoded(&self, encoded: &EncodedRecord<'_, '_>) -> io::Result<()> {
+        if !self.json_format {
+            return self.write(encoded.record());
         }
-
-        Ok(())
+        self.write_chunk(encoded.json(), Some(encoded.record().level), Utc::now())
     }
 
     fn flush(&self) -> io::Result<()> {
-        let mut current = self.current_file.lock();
-        if let Some(ref mut writer) = *current {
+        let mut state = self.state.lock();
+        if let Some(ref mut writer) = state.writer {
             writer.flush()?;
         }
         Ok(())
     }
 
     fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
-        self.rotate_if_needed()?;
-        self.ensure_file()?;
-        let mut current = self.current_file.lock();
-        if let Some(ref mut writer) = *current {
-            writer.write_all(bytes)?;
-            *self.current_size.lock() += bytes.len() as u64;
-        }
+        self.write_chunk(bytes, None, Utc::now())
+    }
+
+    fn reopen(&self) -> io::Result<()> {
+        Self::close_file(&mut self.state.lock());
         Ok(())
     }
 }
@@ -251,6 +943,7 @@ mod tests {
                 message: "Test message",
                 context: &HashMap::new(),
                 data: None,
+                location: None,
             })
             .is_ok());
         assert!(output.flush().is_ok());
@@ -258,4 +951,397 @@ mod tests {
         let content = std::fs::read_to_string(temp_file.path()).unwrap();
         assert!(content.contains("Test message"));
     }
+
+    #[test]
+    fn test_file_output_concurrent_writers() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let output = Arc::new(FileOutput::new(temp_file.path(), true).unwrap());
+
+        let handles: Vec<_> = (0..8)
+            .map(|t| {
+                let output = Arc::clone(&output);
+                std::thread::spawn(move || {
+                    let ctx = HashMap::new();
+                    for i in 0..500 {
+                        let message = format!("{} {}", t, i);
+                        output
+                            .write(&LogRecord {
+                                timestamp: "2025-09-07T10:30:00Z",
+                                level: LogLevel::Info,
+                                logger: "test",
+                                message: &message,
+                                context: &ctx,
+                                data: None,
+                                location: None,
+                            })
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        output.flush().unwrap();
+
+        // Every line is complete and each thread's records keep their order.
+        let content = std::fs::read_to_string(temp_file.path()).unwrap();
+        let mut last = [None::<u32>; 8];
+        for line in content.lines() {
+            let record: serde_json::Value = serde_json::from_str(line).unwrap();
+            let (t, i) = record["message"].as_str().unwrap().split_once(' ').unwrap();
+            let (t, i): (usize, u32) = (t.parse().unwrap(), i.parse().unwrap());
+            assert!(last[t].is_none_or(|prev| prev < i));
+            last[t] = Some(i);
+        }
+        assert_eq!(content.lines().count(), 4000);
+    }
+
+    #[test]
+    fn test_file_output_waits_when_queue_is_full() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let output = FileOutput::new(temp_file.path(), true).unwrap();
+        for _ in 0..MAX_QUEUED_LINES {
+            assert!(output.pending.try_push(b"queued\n", None));
+        }
+
+        let holder = output.writer.lock();
+        std::thread::scope(|scope| {
+            let writer = scope.spawn(|| output.write_bytes(b"waited\n"));
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!writer.is_finished());
+            drop(holder);
+            writer.join().unwrap().unwrap();
+        });
+        output.flush().unwrap();
+
+        let content = std::fs::read_to_string(temp_file.path()).unwrap();
+        let lines: Vec<_> = content.lines().collect();
+        assert_eq!(lines.len(), MAX_QUEUED_LINES + 1);
+        assert_eq!(lines.last(), Some(&"waited"));
+    }
+
+    #[test]
+    fn test_file_output_drop_writes_queued_lines() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let output = FileOutput::new(temp_file.path(), true).unwrap();
+        assert!(output.pending.try_push(b"queued\n", None));
+        drop(output);
+
+        let content = std::fs::read_to_string(temp_file.path()).unwrap();
+        assert_eq!(content, "queued\n");
+    }
+
+    #[test]
+    fn test_file_output_plain_text_fields() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let output = FileOutput::new(temp_file.path(), false).unwrap();
+        let mut ctx = HashMap::new();
+        ctx.insert("request_id".to_string(), "abc".to_string());
+
+        output
+            .write(&LogRecord {
+                timestamp: "2025-09-07T10:30:00Z",
+                level: LogLevel::Info,
+                logger: "test",
+                message: "User logged in",
+                context: &ctx,
+                data: Some(&[("user_id", "12345")]),
+                location: None,
+            })
+            .unwrap();
+        output.flush().unwrap();
+
+        let content = std::fs::read_to_string(temp_file.path()).unwrap();
+        assert_eq!(
+            content,
+            "2025-09-07T10:30:00Z [INFO] test: User logged in {request_id=abc} user_id=12345\n"
+        );
+    }
+
+    fn write_at(output: &RotatingFileOutput, message: &str, now: DateTime<Utc>) {
+        output
+            .write_chunk(format!("{}\n", message).as_bytes(), None, now)
+            .unwrap();
+    }
+
+    fn utc(day: u32, hour: u32) -> DateTime<Utc> {
+        chrono::NaiveDate::from_ymd_opt(2026, 10, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn test_rotating_output_numbered() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("app.log");
+        let output = RotatingFileOutput::new(&base, 10, 3, false).unwrap();
+
+        for i in 0..3 {
+            write_at(&output, &format!("record number {}", i), Utc::now());
+        }
+        output.flush().unwrap();
+
+        assert!(dir.path().join("app.1.log").exists());
+        assert!(dir.path().join("app.2.log").exists());
+        assert!(std::fs::read_to_string(&base)
+            .unwrap()
+            .contains("record number 2"));
+    }
+
+    #[test]
+    fn test_rotating_output_daily() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("app.log");
+        let output = RotatingFileOutput::new(&base, u64::MAX, 5, false)
+            .unwrap()
+            .with_schedule(RotationSchedule::Daily);
+
+        write_at(&output, "day one", utc(16, 10));
+        write_at(&output, "day one later", utc(16, 23));
+        write_at(&output, "day two", utc(17, 1));
+        output.flush().unwrap();
+
+        let rotated = std::fs::read_to_string(dir.path().join("app.2026-10-16.log")).unwrap();
+        assert_eq!(rotated, "day one\nday one later\n");
+        assert_eq!(std::fs::read_to_string(&base).unwrap(), "day two\n");
+    }
+
+    #[test]
+    fn test_rotating_output_size_and_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("app.log");
+        let output = RotatingFileOutput::new(&base, 4, 2, false)
+            .unwrap()
+            .with_schedule(RotationSchedule::Hourly)
+            .with_time_zone(RotationTimeZone::FixedOffset(3600));
+
+        // 10:00 UTC is 11:00 at UTC+01:00.
+        write_at(&output, "first", utc(16, 10));
+        write_at(&output, "second", utc(16, 10));
+        write_at(&output, "third", utc(16, 10));
+        assert!(dir.path().join("app.2026-10-16T11.log").exists());
+        assert!(dir.path().join("app.2026-10-16T11.1.log").exists());
+
+        // Crossing the hour adds a third segment; only `max_files` are kept.
+        write_at(&output, "fourth", utc(16, 11));
+        output.flush().unwrap();
+        let segments = output.rotated_segments().unwrap();
+        assert_eq!(segments.len(), 2);
+        assert!(!dir.path().join("app.2026-10-16T11.log").exists());
+        assert!(dir.path().join("app.2026-10-16T11.2.log").exists());
+        assert_eq!(std::fs::read_to_string(&base).unwrap(), "fourth\n");
+    }
+
+    fn touch_segment(path: &Path, contents: &str, age: Duration) {
+        std::fs::write(path, contents).unwrap();
+        let file = OpenOptions::new().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    #[test]
+    fn test_startup_retention_by_age_and_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("app.log");
+        let day = Duration::from_secs(86400);
+        touch_segment(&dir.path().join("app.2026-10-01.log"), "old", 10 * day);
+        touch_segment(
+            &dir.path().join("app.2026-10-14.log"),
+            "0123456789",
+            2 * day,
+        );
+        touch_segment(&dir.path().join("app.2026-10-15.log"), "0123456789", day);
+        touch_segment(&dir.path().join("unrelated.log"), "keep", 30 * day);
+
+        let output = RotatingFileOutput::new(&base, u64::MAX, 10, false)
+            .unwrap()
+            .with_schedule(RotationSchedule::Daily)
+            .with_max_age(7 * day)
+            .with_max_total_size(15);
+        write_at(&output, "now", Utc::now());
+
+        assert!(!dir.path().join("app.2026-10-01.log").exists());
+        assert!(!dir.path().join("app.2026-10-14.log").exists());
+        assert!(dir.path().join("app.2026-10-15.log").exists());
+        assert!(dir.path().join("unrelated.log").exists());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_rotated_segments_are_compressed() {
+        use std::io::Read;
+
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("app.log");
+        let output = RotatingFileOutput::new(&base, u64::MAX, 5, false)
+            .unwrap()
+            .with_schedule(RotationSchedule::Daily)
+            .with_compression(Compression::Gzip);
+
+        write_at(&output, "day one", utc(16, 10));
+        write_at(&output, "day two", utc(17, 10));
+        drop(output);
+
+        assert!(!dir.path().join("app.2026-10-16.log").exists());
+        let compressed = File::open(dir.path().join("app.2026-10-16.log.gz")).unwrap();
+        let mut contents = String::new();
+        flate2::read::GzDecoder::new(compressed)
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "day one\n");
+    }
+
+    #[test]
+    fn test_file_output_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        let moved = dir.path().join("app.log.1");
+        let output = FileOutput::new(&path, false).unwrap();
+        let ctx = HashMap::new();
+        let record = |message| LogRecord {
+            timestamp: "2025-09-07T10:30:00Z",
+            level: LogLevel::Info,
+            logger: "test",
+            message,
+            context: &ctx,
+            data: None,
+            location: None,
+        };
+
+        output.write(&record("before")).unwrap();
+        output.flush().unwrap();
+        std::fs::rename(&path, &moved).unwrap();
+
+        output.reopen().unwrap();
+        output.write(&record("after")).unwrap();
+        output.flush().unwrap();
+
+        assert!(std::fs::read_to_string(&moved).unwrap().contains("before"));
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("after") && !content.contains("before"));
+    }
+
+    #[test]
+    fn test_reopen_check_detects_external_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        let output = FileOutput::new(&path, false)
+            .unwrap()
+            .with_reopen_check(Duration::ZERO);
+        let ctx = HashMap::new();
+        let record = |message| LogRecord {
+            timestamp: "2025-09-07T10:30:00Z",
+            level: LogLevel::Info,
+            logger: "test",
+            message,
+            context: &ctx,
+            data: None,
+            location: None,
+        };
+
+        output.write(&record("before")).unwrap();
+        output.flush().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        output.write(&record("after")).unwrap();
+        output.flush().unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().contains("after"));
+
+        let rotating_path = dir.path().join("rotating.log");
+        let rotating = RotatingFileOutput::new(&rotating_path, u64::MAX, 3, false)
+            .unwrap()
+            .with_reopen_check(Duration::ZERO);
+        write_at(&rotating, "before", Utc::now());
+        rotating.flush().unwrap();
+        std::fs::rename(&rotating_path, dir.path().join("rotating.log.old")).unwrap();
+        write_at(&rotating, "after", Utc::now());
+        rotating.flush().unwrap();
+        assert_eq!(std::fs::read_to_string(&rotating_path).unwrap(), "after\n");
+    }
+
+    #[test]
+    fn test_multi_process_appends_stay_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shared.log");
+        let payload = "x".repeat(20_000);
+
+        let handles: Vec<_> = (0..4)
+            .map(|writer| {
+                // Separate outputs stand in for separate processes.
+                let output = FileOutput::new(&path, false)
+                    .unwrap()
+                    .with_multi_process(true);
+                let payload = payload.clone();
+                std::thread::spawn(move || {
+                    for i in 0..25 {
+                        let line = format!("{}-{} {}\n", writer, i, payload);
+                        output.write_bytes(line.as_bytes()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 100);
+        assert!(lines.iter().all(|l| l.ends_with(&payload)));
+    }
+
+    #[test]
+    fn test_multi_process_rotation_is_coordinated() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("app.log");
+        let open = || {
+            RotatingFileOutput::new(&base, 64, 100, false)
+                .unwrap()
+                .with_multi_process(true)
+        };
+        let (a, b) = (open(), open());
+
+        for i in 0..20 {
+            let output = if i % 2 == 0 { &a } else { &b };
+            write_at(output, &format!("record {:02}", i), Utc::now());
+        }
+
+        let mut all = std::fs::read_to_string(&base).unwrap();
+        for segment in a.rotated_segments().unwrap() {
+            all.push_str(&std::fs::read_to_string(segment.path).unwrap());
+        }
+        let mut lines: Vec<&str> = all.lines().collect();
+        lines.sort_unstable();
+        let expected: Vec<String> = (0..20).map(|i| format!("record {:02}", i)).collect();
+        assert_eq!(lines, expected);
+        assert!(std::fs::metadata(&base).unwrap().len() <= 64 + 10);
+    }
+
+    #[test]
+    fn test_sync_policy_at_level() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let output = FileOutput::new(temp_file.path(), false)
+            .unwrap()
+            .with_sync_policy(SyncPolicy::AtLevel(LogLevel::Error));
+        let ctx = HashMap::new();
+        let record = |level, message| LogRecord {
+            timestamp: "2025-09-07T10:30:00Z",
+            level,
+            logger: "test",
+            message,
+            context: &ctx,
+            data: None,
+            location: None,
+        };
+
+        output.write(&record(LogLevel::Debug, "buffered")).unwrap();
+        assert_eq!(std::fs::read_to_string(temp_file.path()).unwrap(), "");
+
+        // The error record forces itself and everything before it to disk.
+        output.write(&record(LogLevel::Error, "synced")).unwrap();
+        let content = std::fs::read_to_string(temp_file.path()).unwrap();
+        assert!(content.contains("buffered") && content.contains("synced"));
+    }
 }
@@ -0,0 +1,657 @@
+//! Fluentd forward protocol output for Fluentd and Fluent Bit.
+//!
+//! [`FluentdOutput`] sends records to a `forward` input as MessagePack in
+//! PackedForward mode: consecutive records with the same tag are sent as one
+//! `[tag, entries, option]` message, where `entries` is a stream of
+//! `[time, record]` pairs and `time` is an `EventTime` with nanosecond
+//! precision. The tag is the logger name, optionally prefixed.
+//!
+//! Like [`NetworkOutput`](crate::output::NetworkOutput), records are queued in
+//! a bounded buffer and sent on the logging thread, reconnecting with
+//! exponential backoff while the input is unreachable. With
+//! [`with_ack`](FluentdOutput::with_ack), each message carries a `chunk` id and
+//! records stay queued until the server acknowledges it, so they are delivered
+//! at least once across connection failures.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use telelog::output::{FluentdOutput, FluentdTransport};
+//!
+//! let fluent = FluentdOutput::new(FluentdTransport::Tcp("127.0.0.1:24224".parse().unwrap()))
+//!     .with_tag_prefix("app")
+//!     .with_ack(true);
+//! ```
+
+use crate::output::{report_error, ErrorHandler, LogRecord, OutputDestination};
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::hash::BuildHasher;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const DEFAULT_QUEUE_CAPACITY: usize = 1000;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Upper bound on the entries stream of one PackedForward message.
+const MAX_MESSAGE_BYTES: usize = 1024 * 1024;
+/// MessagePack extension type of Fluentd's `EventTime`.
+const EVENT_TIME_EXT: i8 = 0;
+
+/// Where a [`FluentdOutput`] sends records.
+#[derive(Debug, Clone)]
+pub enum FluentdTransport {
+    /// A `forward` input listening on TCP, usually port 24224.
+    Tcp(SocketAddr),
+    /// A `forward` input listening on a Unix stream socket.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Connection {
+    fn open(transport: &FluentdTransport, timeout: Duration) -> io::Result<Self> {
+        match transport {
+            FluentdTransport::Tcp(addr) => {
+                let stream = TcpStream::connect_timeout(addr, timeout)?;
+                stream.set_nodelay(true)?;
+                stream.set_write_timeout(Some(timeout))?;
+                stream.set_read_timeout(Some(timeout))?;
+                Ok(Connection::Tcp(stream))
+            }
+            #[cfg(unix)]
+            FluentdTransport::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_write_timeout(Some(timeout))?;
+                stream.set_read_timeout(Some(timeout))?;
+                Ok(Connection::Unix(stream))
+            }
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// One queued record: its tag and the encoded `[time, record]` pair.
+struct Entry {
+    tag: String,
+    event: Vec<u8>,
+}
+
+struct State {
+    connection: Option<Connection>,
+    queue: VecDeque<Entry>,
+    backoff: Duration,
+    next_attempt: Instant,
+    dropped: u64,
+    next_chunk: u64,
+}
+
+/// Sends log records to a Fluentd or Fluent Bit `forward` input.
+///
+/// The connection is opened lazily by the first record. A failed connection
+/// attempt, send or acknowledgement is reported as an error from `write`; the
+/// records stay queued and are sent once a later attempt succeeds.
+pub struct FluentdOutput {
+    transport: FluentdTransport,
+    state: Mutex<State>,
+    tag_prefix: Option<String>,
+    ack: bool,
+    queue_capacity: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    timeout: Duration,
+    error_handler: Option<ErrorHandler>,
+    /// Base for chunk ids, so ids differ between processes.
+    chunk_seed: u64,
+}
+
+impl FluentdOutput {
+    /// Creates a forward output for `transport`.
+    pub fn new(transport: FluentdTransport) -> Self {
+        Self {
+            transport,
+            state: Mutex::new(State {
+                connection: None,
+                queue: VecDeque::new(),
+                backoff: DEFAULT_INITIAL_BACKOFF,
+                next_attempt: Instant::now(),
+                dropped: 0,
+                next_chunk: 0,
+            }),
+            tag_prefix: None,
+            ack: false,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            timeout: DEFAULT_TIMEOUT,
+            error_handler: None,
+            chunk_seed: RandomState::new().hash_one(std::process::id()),
+        }
+    }
+
+    /// Prefixes tags, so records of logger `api` are tagged `<prefix>.api`.
+    pub fn with_tag_prefix(mut self, prefix: &str) -> Self {
+        self.tag_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Requests an acknowledgement for every message (off by default).
+    ///
+    /// Records are only removed from the queue once the server has
+    /// acknowledged them; a missing or wrong acknowledgement drops the
+    /// connection and the records are sent again after reconnecting.
+    pub fn with_ack(mut self, enabled: bool) -> Self {
+        self.ack = enabled;
+        self
+    }
+
+    /// Sets how many records are kept while disconnected (1000 by default).
+    ///
+    /// When the queue is full the oldest record is dropped.
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets the delay before the first reconnection attempt and the cap it
+    /// doubles up to (100ms and 30s by default).
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self.state.get_mut().backoff = initial;
+        self
+    }
+
+    /// Sets the connect, write and acknowledgement timeout (5s by default).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Reports records dropped while disconnected to `handler` instead of
+    /// stderr.
+    pub fn with_error_handler(mut self, handler: ErrorHandler) -> Self {
+        self.error_handler = Some(handler);
+        self
+    }
+
+    /// Returns the number of records waiting to be sent or acknowledged.
+    pub fn queued(&self) -> usize {
+        self.state.lock().queue.len()
+    }
+
+    fn tag(&self, logger: &str) -> String {
+        let logger = if logger.is_empty() { "telelog" } else { logger };
+        let tag = match &self.tag_prefix {
+            Some(prefix) => format!("{}.{}", prefix, logger),
+            None => logger.to_string(),
+        };
+        tag.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+
+    fn enqueue(&self, entry: Entry) -> io::Result<()> {
+        let mut state = self.state.lock();
+        if state.queue.len() >= self.queue_capacity {
+            state.queue.pop_front();
+            state.dropped += 1;
+        }
+        state.queue.push_back(entry);
+        self.drain(&mut state)
+    }
+
+    /// Sends queued records, connecting first if the backoff delay has passed.
+    fn drain(&self, state: &mut State) -> io::Result<()> {
+        if state.connection.is_none() {
+            if Instant::now() < state.next_attempt {
+                return Ok(());
+            }
+            match Connection::open(&self.transport, self.timeout) {
+                Ok(connection) => {
+                    state.connection = Some(connection);
+                    state.backoff = self.initial_backoff;
+                    if state.dropped > 0 {
+                        let notice = io::Error::other(format!(
+                            "dropped {} records while disconnected",
+                            state.dropped
+                        ));
+                        report_error(self.error_handler.as_ref(), "FluentdOutput", &notice);
+                        state.dropped = 0;
+                    }
+                }
+                Err(e) => {
+                    self.schedule_retry(state);
+                    return Err(e);
+                }
+            }
+        }
+
+        while let Some(first) = state.queue.front() {
+            // Pack the run of records sharing the first record's tag.
+            let mut count = 0;
+            let mut bytes = 0;
+            for entry in state.queue.iter() {
+                if entry.tag != first.tag
+                    || (count > 0 && bytes + entry.event.len() > MAX_MESSAGE_BYTES)
+                {
+                    break;
+                }
+                count += 1;
+                bytes += entry.event.len();
+            }
+            let chunk = self.ack.then(|| {
+                state.next_chunk += 1;
+                format!("{:016x}{:016x}", self.chunk_seed, state.next_chunk)
+            });
+            let tag = &state.queue[0].tag;
+            let events = state.queue.iter().take(count).map(|e| &e.event[..]);
+            let message = pack(tag, events, count, bytes, chunk.as_deref())?;
+
+            let sent = match state.connection.as_mut() {
+                Some(connection) => send(connection, &message, chunk.as_deref()),
+                None => return Ok(()),
+            };
+            if let Err(e) = sent {
+                state.connection = None;
+                self.schedule_retry(state);
+                return Err(e);
+            }
+            state.queue.drain(..count);
+        }
+        Ok(())
+    }
+
+    fn schedule_retry(&self, state: &mut State) {
+        state.next_attempt = Instant::now() + state.backoff;
+        state.backoff = (state.backoff * 2).min(self.max_backoff);
+    }
+}
+
+/// Encodes a PackedForward message `[tag, entries, option]`.
+fn pack<'e>(
+    tag: &str,
+    events: impl Iterator<Item = &'e [u8]>,
+    count: usize,
+    bytes: usize,
+    chunk: Option<&str>,
+) -> io::Result<Vec<u8>> {
+    let mut message = Vec::with_capacity(bytes + tag.len() + 64);
+    rmp::encode::write_array_len(&mut message, 3)?;
+    rmp::encode::write_str(&mut message, tag)?;
+    rmp::encode::write_bin_len(&mut message, bytes as u32)?;
+    for event in events {
+        message.extend_from_slice(event);
+    }
+    rmp::encode::write_map_len(&mut message, if chunk.is_some() { 2 } else { 1 })?;
+    rmp::encode::write_str(&mut message, "size")?;
+    rmp::encode::write_uint(&mut message, count as u64)?;
+    if let Some(chunk) = chunk {
+        rmp::encode::write_str(&mut message, "chunk")?;
+        rmp::encode::write_str(&mut message, chunk)?;
+    }
+    Ok(message)
+}
+
+/// Writes a message and, if `chunk` is set, waits for its acknowledgement.
+fn send(connection: &mut Connection, message: &[u8], chunk: Option<&str>) -> io::Result<()> {
+    connection.write_all(message)?;
+    let Some(chunk) = chunk else {
+        return Ok(());
+    };
+    // The response is a map such as {"ack": "<chunk>"}.
+    let invalid = |e: rmp::decode::ValueReadError<io::Error>| match e {
+        rmp::decode::ValueReadError::InvalidMarkerRead(e)
+        | rmp::decode::ValueReadError::InvalidDataRead(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+    };
+    let mut acked = false;
+    for _ in 0..rmp::decode::read_map_len(connection).map_err(invalid)? {
+        let key = read_string(connection).map_err(invalid)?;
+        let value = read_string(connection).map_err(invalid)?;
+        acked |= key == "ack" && value == chunk;
+    }
+    if acked {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Fluentd forward response did not acknowledge the chunk",
+        ))
+    }
+}
+
+fn read_string(
+    connection: &mut Connection,
+) -> Result<String, rmp::decode::ValueReadError<io::Error>> {
+    let len = rmp::decode::read_str_len(connection)?;
+    let mut buf = vec![0; len as usize];
+    connection
+        .read_exact(&mut buf)
+        .map_err(rmp::decode::ValueReadError::InvalidDataRead)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Encodes `[EventTime, record]` for an RFC 3339 timestamp, falling back to now.
+fn event<'f>(
+    timestamp: &str,
+    fields: impl ExactSizeIterator<Item = (&'f str, Field<'f>)>,
+) -> io::Result<Vec<u8>> {
+    let time = chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.to_utc())
+        .unwrap_or_else(|_| chrono::Utc::now());
+    let mut out = Vec::with_capacity(128);
+    rmp::encode::write_array_len(&mut out, 2)?;
+    rmp::encode::write_ext_meta(&mut out, 8, EVENT_TIME_EXT)?;
+    out.extend_from_slice(&(time.timestamp() as u32).to_be_bytes());
+    out.extend_from_slice(&time.timestamp_subsec_nanos().to_be_bytes());
+    rmp::encode::write_map_len(&mut out, fields.len() as u32)?;
+    for (key, value) in fields {
+        rmp::encode::write_str(&mut out, key)?;
+        match value {
+            Field::Str(s) => rmp::encode::write_str(&mut out, s)?,
+            Field::Json(v) => write_json(&mut out, v)?,
+        }
+    }
+    Ok(out)
+}
+
+enum Field<'a> {
+    Str(&'a str),
+    Json(&'a Value),
+}
+
+/// Encodes a JSON value as the equivalent MessagePack value.
+fn write_json(out: &mut Vec<u8>, value: &Value) -> io::Result<()> {
+    match value {
+        Value::Null => rmp::encode::write_nil(out)?,
+        Value::Bool(b) => rmp::encode::write_bool(out, *b)?,
+        Value::Number(n) => {
+            if let Some(u) = n.as_u64() {
+                rmp::encode::write_uint(out, u)?;
+            } else if let Some(i) = n.as_i64() {
+                rmp::encode::write_sint(out, i)?;
+            } else {
+                rmp::encode::write_f64(out, n.as_f64().unwrap_or_default())?;
+            }
+        }
+        Value::String(s) => rmp::encode::write_str(out, s)?,
+        Value::Array(items) => {
+            rmp::encode::write_array_len(out, items.len() as u32)?;
+            for item in items {
+                write_json(out, item)?;
+            }
+        }
+        Value::Object(map) => {
+            rmp::encode::write_map_len(out, map.len() as u32)?;
+            for (key, item) in map {
+                rmp::encode::write_str(out, key)?;
+                write_json(out, item)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl OutputDestination for FluentdOutput {
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        let data = record.data.unwrap_or_default();
+        let mut fields = Vec::with_capacity(3 + record.context.len() + data.len());
+        fields.push(("level", Field::Str(record.level.as_str())));
+        fields.push(("logger", Field::Str(record.logger)));
+        fields.push(("message", Field::Str(record.message)));
+        for (key, value) in record.context {
+            fields.push((key, Field::Str(value)));
+        }
+        for (key, value) in data {
+            fields.push((key, Field::Str(value)));
+        }
+        self.enqueue(Entry {
+            tag: self.tag(record.logger),
+            event: event(record.timestamp, fields.into_iter())?,
+        })
+    }
+
+    /// Attempts to send any queued records.
+    fn flush(&self) -> io::Result<()> {
+        let mut state = self.state.lock();
+        self.drain(&mut state)
+    }
+
+    /// Converts a pre-serialized JSON record, using its `timestamp` as the
+    /// event time and its `logger` for the tag.
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        let record: serde_json::Map<String, Value> = serde_json::from_slice(bytes.trim_ascii_end())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let field = |key: &str| record.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+        let fields: Vec<(&str, Field<'_>)> = record
+            .iter()
+            .filter(|(key, _)| *key != "timestamp")
+            .map(|(key, value)| (key.as_str(), Field::Json(value)))
+            .collect();
+        self.enqueue(Entry {
+            tag: self.tag(field("logger")),
+            event: event(field("timestamp"), fields.into_iter())?,
+        })
+    }
+
+    /// Drops the current connection and reconnects on the next record without
+    /// waiting for the backoff delay.
+    fn reopen(&self) -> io::Result<()> {
+        let mut state = self.state.lock();
+        state.connection = None;
+        state.backoff = self.initial_backoff;
+        state.next_attempt = Instant::now();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::LogLevel;
+    use rmpv::Value as Msg;
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+
+    fn write(output: &FluentdOutput, logger: &str, message: &str) -> io::Result<()> {
+        let ctx = HashMap::new();
+        output.write(&LogRecord {
+            timestamp: "2025-09-07T10:30:00.123456789Z",
+            level: LogLevel::Warning,
+            logger,
+            message,
+            context: &ctx,
+            data: Some(&[("user_id", "42")]),
+            location: None,
+        })
+    }
+
+    /// Reads one forward message and returns the tag, the decoded
+    /// `[time, record]` entries and the option map.
+    fn read_message(stream: &mut impl Read) -> (String, Vec<Msg>, Msg) {
+        let message = rmpv::decode::read_value(stream).unwrap();
+        let parts = message.as_array().unwrap();
+        let mut entries = parts[1].as_slice().unwrap();
+        let mut events = Vec::new();
+        while !entries.is_empty() {
+            events.push(rmpv::decode::read_value(&mut entries).unwrap());
+        }
+        (
+            parts[0].as_str().unwrap().to_string(),
+            events,
+            parts[2].clone(),
+        )
+    }
+
+    fn field<'a>(record: &'a Msg, key: &str) -> Option<&'a Msg> {
+        record
+            .as_map()?
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+    }
+
+    fn ack(stream: &mut TcpStream, chunk: &str) {
+        let mut response = Vec::new();
+        rmp::encode::write_map_len(&mut response, 1).unwrap();
+        rmp::encode::write_str(&mut response, "ack").unwrap();
+        rmp::encode::write_str(&mut response, chunk).unwrap();
+        stream.write_all(&response).unwrap();
+    }
+
+    #[test]
+    fn test_packed_forward() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let output = FluentdOutput::new(FluentdTransport::Tcp(listener.local_addr().unwrap()))
+            .with_tag_prefix("app");
+
+        write(&output, "api", "first").unwrap();
+        output
+            .write_bytes(b"{\"timestamp\":\"2025-09-07T10:30:01Z\",\"level\":\"INFO\",\"logger\":\"db\",\"message\":\"second\",\"rows\":3}\n")
+            .unwrap();
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let (tag, events, option) = read_message(&mut stream);
+        assert_eq!(tag, "app.api");
+        assert_eq!(events.len(), 1);
+        assert_eq!(field(&option, "size").and_then(Msg::as_u64), Some(1));
+        let event = events[0].as_array().unwrap();
+        match &event[0] {
+            Msg::Ext(0, time) => {
+                assert_eq!(
+                    u32::from_be_bytes(time[..4].try_into().unwrap()),
+                    1757241000
+                );
+                assert_eq!(u32::from_be_bytes(time[4..].try_into().unwrap()), 123456789);
+            }
+            other => panic!("expected EventTime, got {:?}", other),
+        }
+        assert_eq!(
+            field(&event[1], "message").and_then(Msg::as_str),
+            Some("first")
+        );
+        assert_eq!(
+            field(&event[1], "level").and_then(Msg::as_str),
+            Some("WARNING")
+        );
+        assert_eq!(
+            field(&event[1], "user_id").and_then(Msg::as_str),
+            Some("42")
+        );
+
+        let (tag, events, _) = read_message(&mut stream);
+        assert_eq!(tag, "app.db");
+        let record = &events[0].as_array().unwrap()[1];
+        assert_eq!(field(record, "rows").and_then(Msg::as_u64), Some(3));
+        assert!(field(record, "timestamp").is_none());
+    }
+
+    #[test]
+    fn test_ack_and_resend() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let output = FluentdOutput::new(FluentdTransport::Tcp(listener.local_addr().unwrap()))
+            .with_ack(true)
+            .with_timeout(Duration::from_millis(200))
+            .with_backoff(Duration::ZERO, Duration::ZERO);
+
+        let server = std::thread::spawn(move || {
+            // Drop the first message without acknowledging it.
+            let (mut stream, _) = listener.accept().unwrap();
+            let (_, events, _) = read_message(&mut stream);
+            assert_eq!(events.len(), 1);
+            let (mut stream, _) = listener.accept().unwrap();
+            let (tag, events, option) = read_message(&mut stream);
+            ack(
+                &mut stream,
+                field(&option, "chunk").unwrap().as_str().unwrap(),
+            );
+            (tag, events.len())
+        });
+
+        assert!(write(&output, "api", "unacked").is_err());
+        assert_eq!(output.queued(), 1);
+        // Both records go out together once reconnected.
+        write(&output, "api", "retried").unwrap();
+        assert_eq!(output.queued(), 0);
+        assert_eq!(server.join().unwrap(), ("api".to_string(), 2));
+    }
+
+    #[test]
+    fn test_queues_while_disconnected() {
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let output = FluentdOutput::new(FluentdTransport::Tcp(addr))
+            .with_queue_capacity(2)
+            .with_backoff(Duration::from_secs(60), Duration::from_secs(60));
+
+        assert!(write(&output, "api", "a").is_err());
+        write(&output, "api", "b").unwrap();
+        write(&output, "api", "c").unwrap();
+        assert_eq!(output.queued(), 2);
+
+        let listener = TcpListener::bind(addr).unwrap();
+        output.reopen().unwrap();
+        output.flush().unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        let (_, events, _) = read_message(&mut stream);
+        let messages: Vec<&str> = events
+            .iter()
+            .map(|e| {
+                field(&e.as_array().unwrap()[1], "message")
+                    .unwrap()
+                    .as_str()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(messages, ["b", "c"]);
+    }
+}
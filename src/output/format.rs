@@ -0,0 +1,203 @@
+//! Plain-text rendering of log records.
+//!
+//! [`TextRecord`] formats a [`LogRecord`] as a single human-readable line:
+//!
+//! ```text
+//! 2025-09-07T10:30:00Z [INFO] app: User logged in {request_id=abc-123} user_id=12345
+//! ```
+//!
+//! Context entries are sorted by key and wrapped in braces; structured data
+//! follows as bare `key=value` pairs in call order. Keys and values that
+//! contain spaces, quotes, `=`, braces or control characters are quoted and
+//! escaped, and control characters in the message are escaped, so that every
+//! record stays on one line and remains machine-parsable.
+
+use crate::output::LogRecord;
+use std::fmt::{self, Write};
+
+/// Display adapter that renders a [`LogRecord`] as a plain-text line (without
+/// the trailing newline).
+///
+/// # Examples
+///
+/// ```
+/// use telelog::output::{LogRecord, TextRecord};
+/// use telelog::LogLevel;
+/// use std::collections::HashMap;
+///
+/// let ctx = HashMap::new();
+/// let record = LogRecord::new("2025-09-07T10:30:00Z", LogLevel::Info, "app", "User logged in", &ctx)
+///     .with_data(&[("user_id", "12345"), ("note", "two words")]);
+/// assert_eq!(
+///     TextRecord(&record).to_string(),
+///     "2025-09-07T10:30:00Z [INFO] app: User logged in user_id=12345 note=\"two words\""
+/// );
+/// ```
+pub struct TextRecord<'r, 'a>(pub &'r LogRecord<'a>);
+
+impl fmt::Display for TextRecord<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let record = self.0;
+        write!(
+            f,
+            "{} [{}] {}: ",
+            record.timestamp, record.level, record.logger
+        )?;
+        write_message(f, record.message)?;
+        write_fields(f, record)
+    }
+}
+
+/// Writes `message` unquoted, escaping control characters so that it cannot
+/// break the line.
+fn write_message<W: Write>(out: &mut W, message: &str) -> fmt::Result {
+    for c in message.chars() {
+        write_char_escaped(out, c)?;
+    }
+    Ok(())
+}
+
+/// Appends the context block and data fields of `record`, each preceded by a space.
+///
+/// Writes nothing when the record carries no context and no data.
+pub(crate) fn write_fields<W: Write>(out: &mut W, record: &LogRecord<'_>) -> fmt::Result {
+    if !record.context.is_empty() {
+        let mut keys: Vec<&String> = record.context.keys().collect();
+        keys.sort_unstable();
+        out.write_str(" {")?;
+        for (i, key) in keys.into_iter().enumerate() {
+            if i > 0 {
+                out.write_char(' ')?;
+            }
+            write_pair(out, key, &record.context[key])?;
+        }
+        out.write_char('}')?;
+    }
+
+    if let Some(data) = record.data {
+        for (key, value) in data {
+            out.write_char(' ')?;
+            write_pair(out, key, value)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a single `key=value` pair, quoting either side when required.
+pub(crate) fn write_pair<W: Write>(out: &mut W, key: &str, value: &str) -> fmt::Result {
+    write_value(out, key)?;
+    out.write_char('=')?;
+    write_value(out, value)
+}
+
+/// Writes `value` verbatim, or quoted and escaped if it would be ambiguous.
+pub(crate) fn write_value<W: Write>(out: &mut W, value: &str) -> fmt::Result {
+    if !needs_quoting(value) {
+        return out.write_str(value);
+    }
+
+    out.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            c => write_char_escaped(out, c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+/// Writes `c`, escaping control characters.
+fn write_char_escaped<W: Write>(out: &mut W, c: char) -> fmt::Result {
+    match c {
+        '\n' => out.write_str("\\n"),
+        '\r' => out.write_str("\\r"),
+        '\t' => out.write_str("\\t"),
+        c if c.is_control() => write!(out, "\\u{{{:x}}}", c as u32),
+        c => out.write_char(c),
+    }
+}
+
+fn needs_quoting(value: &str) -> bool {
+    value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '"' | '=' | '{' | '}'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::LogLevel;
+    use std::collections::HashMap;
+
+    fn record<'a>(
+        context: &'a HashMap<String, String>,
+        data: Option<&'a [(&'a str, &'a str)]>,
+    ) -> LogRecord<'a> {
+        LogRecord {
+            timestamp: "2025-09-07T10:30:00Z",
+            level: LogLevel::Info,
+            logger: "test",
+            message: "Test message",
+            context,
+            data,
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_plain_record() {
+        let ctx = HashMap::new();
+        assert_eq!(
+            TextRecord(&record(&ctx, None)).to_string(),
+            "2025-09-07T10:30:00Z [INFO] test: Test message"
+        );
+    }
+
+    #[test]
+    fn test_context_and_data_are_separate() {
+        let mut ctx = HashMap::new();
+        ctx.insert("request_id".to_string(), "abc".to_string());
+        ctx.insert("env".to_string(), "prod".to_string());
+        let data = [("user_id", "12345")];
+
+        assert_eq!(
+            TextRecord(&record(&ctx, Some(&data))).to_string(),
+            "2025-09-07T10:30:00Z [INFO] test: Test message {env=prod request_id=abc} user_id=12345"
+        );
+    }
+
+    #[test]
+    fn test_quoting_and_escaping() {
+        let ctx = HashMap::new();
+        let data = [
+            ("path", "/tmp/my file"),
+            ("multi", "a\nb\tc"),
+            ("quote", "say \"hi\""),
+            ("empty", ""),
+            ("bell", "\u{7}"),
+            ("odd key", "x=y"),
+        ];
+
+        assert_eq!(
+            TextRecord(&record(&ctx, Some(&data))).to_string(),
+            "2025-09-07T10:30:00Z [INFO] test: Test message \
+             path=\"/tmp/my file\" multi=\"a\\nb\\tc\" quote=\"say \\\"hi\\\"\" \
+             empty=\"\" bell=\"\\u{7}\" \"odd key\"=\"x=y\""
+        );
+    }
+
+    #[test]
+    fn test_message_stays_on_one_line() {
+        let ctx = HashMap::new();
+        let mut record = record(&ctx, None);
+        record.message = "first\r\nsecond\tthird\u{1b}[31m";
+
+        assert_eq!(
+            TextRecord(&record).to_string(),
+            "2025-09-07T10:30:00Z [INFO] test: first\\r\\nsecond\\tthird\\u{1b}[31m"
+        );
+    }
+}
@@ -0,0 +1,502 @@
+//! GELF 1.1 output for Graylog.
+//!
+//! [`GelfOutput`] encodes each record as a GELF JSON message and sends it over
+//! UDP, optionally compressed and split into GELF chunks when it exceeds the
+//! chunk size, or over TCP as null-byte terminated frames.
+//!
+//! Log levels map to syslog severities via [`LogLevel::syslog_severity`]. The
+//! first line of the message becomes `short_message` and multi-line messages
+//! are also sent in full as `full_message`. The logger name, source location,
+//! context and data are sent as additional fields prefixed with `_`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use telelog::output::{GelfOutput, GelfTransport};
+//!
+//! let gelf = GelfOutput::new(GelfTransport::Udp("10.0.0.5:12201".parse().unwrap()))
+//!     .unwrap()
+//!     .with_host("web-1");
+//! ```
+
+use crate::level::LogLevel;
+use crate::output::{LogRecord, OutputDestination};
+use parking_lot::Mutex;
+use serde_json::{Map, Value};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+use std::panic::Location;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Magic bytes starting every GELF chunk.
+const CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+/// Magic bytes, 8-byte message id, sequence number and sequence count.
+const CHUNK_HEADER_LEN: usize = 12;
+/// Graylog discards messages split into more chunks than this.
+const MAX_CHUNKS: usize = 128;
+/// Datagram size that fits a typical 1500-byte MTU without fragmentation.
+const DEFAULT_CHUNK_SIZE: usize = 1420;
+
+/// Where GELF messages are sent.
+#[derive(Debug, Clone)]
+pub enum GelfTransport {
+    /// A GELF UDP input; large messages are chunked.
+    Udp(SocketAddr),
+    /// A GELF TCP input; messages are terminated by a null byte.
+    Tcp(SocketAddr),
+}
+
+/// Compression applied to GELF UDP messages.
+///
+/// GELF TCP inputs do not accept compressed frames, so this setting only
+/// affects [`GelfTransport::Udp`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GelfCompression {
+    /// Messages are sent as plain JSON.
+    #[default]
+    None,
+    /// Gzip-compress messages (requires `gzip` feature).
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// Zlib-compress messages (requires `gzip` feature).
+    #[cfg(feature = "gzip")]
+    Zlib,
+}
+
+impl GelfCompression {
+    fn compress(&self, payload: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            GelfCompression::None => Ok(payload),
+            #[cfg(feature = "gzip")]
+            GelfCompression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&payload)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "gzip")]
+            GelfCompression::Zlib => {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&payload)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+impl Connection {
+    fn open(transport: &GelfTransport) -> io::Result<Self> {
+        match transport {
+            GelfTransport::Udp(addr) => {
+                let local: SocketAddr = if addr.is_ipv4() {
+                    ([0, 0, 0, 0], 0).into()
+                } else {
+                    ([0u16; 8], 0).into()
+                };
+                let socket = UdpSocket::bind(local)?;
+                socket.connect(addr)?;
+                Ok(Connection::Udp(socket))
+            }
+            GelfTransport::Tcp(addr) => {
+                let stream = TcpStream::connect_timeout(addr, Duration::from_secs(5))?;
+                stream.set_nodelay(true)?;
+                Ok(Connection::Tcp(stream))
+            }
+        }
+    }
+}
+
+/// Sends log records to a Graylog GELF input.
+///
+/// The connection is opened on construction and transparently re-established
+/// once if a send fails.
+pub struct GelfOutput {
+    transport: GelfTransport,
+    connection: Mutex<Option<Connection>>,
+    host: String,
+    compression: GelfCompression,
+    chunk_size: usize,
+    /// Base for chunked message ids, so ids differ between processes.
+    id_seed: u64,
+    next_id: AtomicU64,
+}
+
+impl GelfOutput {
+    /// Connects to a GELF input.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket cannot be created or the TCP connection fails.
+    pub fn new(transport: GelfTransport) -> io::Result<Self> {
+        let connection = Connection::open(&transport)?;
+        Ok(Self {
+            transport,
+            connection: Mutex::new(Some(connection)),
+            host: super::hostname().unwrap_or_else(|| "unknown".to_string()),
+            compression: GelfCompression::None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            id_seed: RandomState::new().hash_one(std::process::id()),
+            next_id: AtomicU64::new(0),
+        })
+    }
+
+    /// Sets the `host` field (defaults to this machine's host name).
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.host = host.to_string();
+        self
+    }
+
+    /// Sets the compression of UDP messages (none by default).
+    pub fn with_compression(mut self, compression: GelfCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets the maximum UDP datagram size (1420 bytes by default).
+    ///
+    /// Larger messages are split into at most 128 chunks of this size,
+    /// including the 12-byte chunk header; messages needing more are dropped
+    /// with an error. Graylog accepts datagrams of up to 8192 bytes.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(CHUNK_HEADER_LEN + 1);
+        self
+    }
+
+    /// Encodes a record as a GELF JSON message.
+    pub(crate) fn format_record(&self, record: &LogRecord<'_>) -> Vec<u8> {
+        let fields = record
+            .context
+            .iter()
+            .map(|(k, v)| (k.as_str(), Value::from(v.as_str())))
+            .chain(
+                record
+                    .data
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|(k, v)| (*k, Value::from(*v))),
+            );
+        self.message(
+            record.timestamp,
+            record.level,
+            record.logger,
+            record.message,
+            record.location,
+            fields,
+        )
+    }
+
+    fn message<'f>(
+        &self,
+        timestamp: &str,
+        level: LogLevel,
+        logger: &str,
+        message: &str,
+        location: Option<&Location<'_>>,
+        fields: impl Iterator<Item = (&'f str, Value)>,
+    ) -> Vec<u8> {
+        let mut gelf = Map::new();
+        gelf.insert("version".into(), "1.1".into());
+        gelf.insert("host".into(), self.host.as_str().into());
+        let short = message.lines().next().unwrap_or_default();
+        gelf.insert("short_message".into(), short.into());
+        if short.len() < message.trim_end().len() {
+            gelf.insert("full_message".into(), message.into());
+        }
+        gelf.insert("timestamp".into(), unix_seconds(timestamp).into());
+        gelf.insert("level".into(), level.syslog_severity().into());
+        gelf.insert("_logger".into(), logger.into());
+        if let Some(location) = location {
+            gelf.insert("_file".into(), location.file().into());
+            gelf.insert("_line".into(), location.line().into());
+        }
+        for (key, value) in fields {
+            gelf.insert(field_name(key), value);
+        }
+        serde_json::to_vec(&Value::Object(gelf)).unwrap_or_default()
+    }
+
+    fn send(&self, message: Vec<u8>) -> io::Result<()> {
+        let datagrams = match self.transport {
+            GelfTransport::Udp(_) => self.datagrams(self.compression.compress(message)?)?,
+            GelfTransport::Tcp(_) => {
+                let mut frame = message;
+                frame.push(0);
+                vec![frame]
+            }
+        };
+
+        let mut connection = self.connection.lock();
+        if let Some(conn) = connection.as_mut() {
+            if send_all(conn, &datagrams).is_ok() {
+                return Ok(());
+            }
+        }
+
+        // Reconnect once; the input may have restarted or the peer closed TCP.
+        *connection = None;
+        let mut conn = Connection::open(&self.transport)?;
+        send_all(&mut conn, &datagrams)?;
+        *connection = Some(conn);
+        Ok(())
+    }
+
+    /// Splits a UDP payload into GELF chunks if it exceeds the chunk size.
+    fn datagrams(&self, payload: Vec<u8>) -> io::Result<Vec<Vec<u8>>> {
+        if payload.len() <= self.chunk_size {
+            return Ok(vec![payload]);
+        }
+        let data_len = self.chunk_size - CHUNK_HEADER_LEN;
+        let count = payload.len().div_ceil(data_len);
+        if count > MAX_CHUNKS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "GELF message of {} bytes needs {} chunks (max {})",
+                    payload.len(),
+                    count,
+                    MAX_CHUNKS
+                ),
+            ));
+        }
+        let id = self
+            .id_seed
+            .wrapping_add(self.next_id.fetch_add(1, Ordering::Relaxed));
+        Ok(payload
+            .chunks(data_len)
+            .enumerate()
+            .map(|(seq, data)| {
+                let mut chunk = Vec::with_capacity(CHUNK_HEADER_LEN + data.len());
+                chunk.extend_from_slice(&CHUNK_MAGIC);
+                chunk.extend_from_slice(&id.to_be_bytes());
+                chunk.push(seq as u8);
+                chunk.push(count as u8);
+                chunk.extend_from_slice(data);
+                chunk
+            })
+            .collect())
+    }
+}
+
+fn send_all(conn: &mut Connection, datagrams: &[Vec<u8>]) -> io::Result<()> {
+    for datagram in datagrams {
+        match conn {
+            Connection::Udp(socket) => socket.send(datagram).map(|_| ())?,
+            Connection::Tcp(stream) => stream.write_all(datagram)?,
+        }
+    }
+    Ok(())
+}
+
+impl OutputDestination for GelfOutput {
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        self.send(self.format_record(record))
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        if let Some(Connection::Tcp(stream)) = self.connection.lock().as_mut() {
+            stream.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Converts a pre-serialized JSON record to GELF, keeping its fields.
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        let mut record: Map<String, Value> = serde_json::from_slice(bytes.trim_ascii_end())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut take = |key: &str| match record.remove(key) {
+            Some(Value::String(s)) => s,
+            _ => String::new(),
+        };
+        let timestamp = take("timestamp");
+        let level = take("level").parse().unwrap_or(LogLevel::Info);
+        let logger = take("logger");
+        let message = take("message");
+        let fields = record.iter().map(|(k, v)| (k.as_str(), v.clone()));
+        self.send(self.message(&timestamp, level, &logger, &message, None, fields))
+    }
+
+    fn reopen(&self) -> io::Result<()> {
+        *self.connection.lock() = Some(Connection::open(&self.transport)?);
+        Ok(())
+    }
+}
+
+/// Converts an RFC 3339 timestamp to fractional Unix seconds, falling back to now.
+fn unix_seconds(timestamp: &str) -> f64 {
+    let micros = chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.timestamp_micros())
+        .unwrap_or_else(|_| chrono::Utc::now().timestamp_micros());
+    micros as f64 / 1_000_000.0
+}
+
+/// Builds an additional field name: `_` plus the key with characters outside
+/// `[A-Za-z0-9_.-]` replaced by `_`. The reserved `_id` becomes `__id`.
+fn field_name(key: &str) -> String {
+    let mut name = String::with_capacity(key.len() + 2);
+    name.push('_');
+    if key == "id" {
+        name.push('_');
+    }
+    name.extend(key.chars().map(|c| {
+        if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+            c
+        } else {
+            '_'
+        }
+    }));
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    fn record<'a>(
+        context: &'a HashMap<String, String>,
+        data: Option<&'a [(&'a str, &'a str)]>,
+        message: &'a str,
+    ) -> LogRecord<'a> {
+        LogRecord {
+            timestamp: "2025-09-07T10:30:00.250+00:00",
+            level: LogLevel::Error,
+            logger: "api",
+            message,
+            context,
+            data,
+            location: None,
+        }
+    }
+
+    fn udp_pair() -> (UdpSocket, GelfOutput) {
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let output = GelfOutput::new(GelfTransport::Udp(listener.local_addr().unwrap()))
+            .unwrap()
+            .with_host("web-1");
+        (listener, output)
+    }
+
+    fn recv(socket: &UdpSocket) -> Vec<u8> {
+        let mut buf = [0u8; 65536];
+        let n = socket.recv(&mut buf).unwrap();
+        buf[..n].to_vec()
+    }
+
+    #[test]
+    fn test_gelf_fields() {
+        let (listener, output) = udp_pair();
+        let mut ctx = HashMap::new();
+        ctx.insert("request_id".to_string(), "abc".to_string());
+        let data = [("id", "42"), ("user name", "alice")];
+
+        output
+            .write(&record(&ctx, Some(&data), "Payment failed\ncard declined"))
+            .unwrap();
+
+        let gelf: Value = serde_json::from_slice(&recv(&listener)).unwrap();
+        assert_eq!(gelf["version"], "1.1");
+        assert_eq!(gelf["host"], "web-1");
+        assert_eq!(gelf["short_message"], "Payment failed");
+        assert_eq!(gelf["full_message"], "Payment failed\ncard declined");
+        assert_eq!(gelf["timestamp"], 1757241000.25);
+        assert_eq!(gelf["level"], 3);
+        assert_eq!(gelf["_logger"], "api");
+        assert_eq!(gelf["_request_id"], "abc");
+        assert_eq!(gelf["__id"], "42");
+        assert_eq!(gelf["_user_name"], "alice");
+    }
+
+    #[test]
+    fn test_udp_chunking() {
+        let (listener, output) = udp_pair();
+        let output = output.with_chunk_size(200);
+        let ctx = HashMap::new();
+        let message = "x".repeat(1000);
+
+        output.write(&record(&ctx, None, &message)).unwrap();
+
+        let chunks: Vec<Vec<u8>> = (0..6).map(|_| recv(&listener)).collect();
+        let mut payload = Vec::new();
+        for (seq, chunk) in chunks.iter().enumerate() {
+            assert!(chunk.len() <= 200);
+            assert_eq!(chunk[..2], CHUNK_MAGIC);
+            assert_eq!(chunk[2..10], chunks[0][2..10]);
+            assert_eq!(chunk[10] as usize, seq);
+            assert_eq!(chunk[11], 6);
+            payload.extend_from_slice(&chunk[CHUNK_HEADER_LEN..]);
+        }
+        let gelf: Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(gelf["short_message"], message.as_str());
+
+        let huge = "x".repeat(200 * MAX_CHUNKS);
+        let err = output.write(&record(&ctx, None, &huge)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_compression() {
+        use std::io::Read;
+
+        let (listener, output) = udp_pair();
+        let ctx = HashMap::new();
+
+        let output = output.with_compression(GelfCompression::Gzip);
+        output.write(&record(&ctx, None, "zipped")).unwrap();
+        let mut json = String::new();
+        flate2::read::GzDecoder::new(&recv(&listener)[..])
+            .read_to_string(&mut json)
+            .unwrap();
+        assert!(json.contains("\"short_message\":\"zipped\""));
+
+        let output = output.with_compression(GelfCompression::Zlib);
+        output.write(&record(&ctx, None, "deflated")).unwrap();
+        let mut json = String::new();
+        flate2::read::ZlibDecoder::new(&recv(&listener)[..])
+            .read_to_string(&mut json)
+            .unwrap();
+        assert!(json.contains("\"short_message\":\"deflated\""));
+    }
+
+    #[test]
+    fn test_tcp_null_framing() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let output = GelfOutput::new(GelfTransport::Tcp(listener.local_addr().unwrap())).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let ctx = HashMap::new();
+
+        output.write(&record(&ctx, None, "first")).unwrap();
+        output
+            .write_bytes(b"{\"timestamp\":\"2025-09-07T10:30:00Z\",\"level\":\"WARNING\",\"logger\":\"db\",\"message\":\"second\",\"rows\":3}\n")
+            .unwrap();
+
+        let mut reader = BufReader::new(stream);
+        let mut frame = Vec::new();
+        reader.read_until(0, &mut frame).unwrap();
+        let gelf: Value = serde_json::from_slice(&frame[..frame.len() - 1]).unwrap();
+        assert_eq!(gelf["short_message"], "first");
+
+        frame.clear();
+        reader.read_until(0, &mut frame).unwrap();
+        assert_eq!(frame.last(), Some(&0));
+        let gelf: Value = serde_json::from_slice(&frame[..frame.len() - 1]).unwrap();
+        assert_eq!(gelf["short_message"], "second");
+        assert_eq!(gelf["level"], 4);
+        assert_eq!(gelf["_logger"], "db");
+        assert_eq!(gelf["_rows"], 3);
+    }
+}
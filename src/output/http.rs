@@ -0,0 +1,847 @@
+//! HTTP batch output for Loki, Elasticsearch and generic JSON endpoints
+//! (requires the `http` feature).
+//!
+//! [`HttpOutput`] collects records on a background thread and POSTs them in
+//! batches. A batch is sent when it reaches a record count, a byte size or an
+//! age limit, whichever comes first, and on [`flush`](OutputDestination::flush)
+//! or drop. Failed requests are retried with exponential backoff on transport
+//! errors, `429 Too Many Requests` and `5xx` responses.
+//!
+//! Wire formats are selected with [`HttpFormat`]:
+//!
+//! - [`Loki`](HttpFormat::Loki): the Loki push API. Each record's JSON is the
+//!   log line; streams are labelled with `logger`, `level` and any chosen
+//!   context keys present on the record.
+//! - [`ElasticsearchBulk`](HttpFormat::ElasticsearchBulk): `_bulk` NDJSON with
+//!   one `create` action per record.
+//! - [`JsonArray`](HttpFormat::JsonArray): a JSON array of records.
+//!
+//! With the `gzip` feature request bodies can be compressed, and with the
+//! `tls` feature `https://` URLs are supported.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use telelog::output::{HttpFormat, HttpOutput};
+//! use std::time::Duration;
+//!
+//! let loki = HttpOutput::new(
+//!     "http://loki:3100/loki/api/v1/push",
+//!     HttpFormat::Loki { labels: vec!["service".into()] },
+//! )
+//! .with_batch_size(500)
+//! .with_max_batch_age(Duration::from_secs(2));
+//! ```
+
+use crate::output::{report_error, ErrorHandler, LogRecord, OutputDestination};
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const DEFAULT_BATCH_SIZE: usize = 100;
+const DEFAULT_MAX_BATCH_BYTES: usize = 1024 * 1024;
+const DEFAULT_MAX_BATCH_AGE: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Records that may wait for the background thread, as a multiple of the batch size.
+const QUEUE_BATCHES: usize = 10;
+
+/// Request body format of an [`HttpOutput`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HttpFormat {
+    /// Loki push API (`/loki/api/v1/push`). `labels` names the context keys
+    /// that become stream labels in addition to `logger` and `level`.
+    Loki { labels: Vec<String> },
+    /// Elasticsearch or OpenSearch `_bulk` API, indexing into `index`.
+    ElasticsearchBulk { index: String },
+    /// A JSON array of records.
+    JsonArray,
+}
+
+/// Turns records into batch entries and batches into request bodies.
+///
+/// Implemented by [`HttpFormat`] and by exporters that reuse the batching and
+/// retry machinery of [`HttpOutput`] with their own wire format.
+pub(crate) trait BatchFormat: Send + Sync {
+    /// `Content-Type` of encoded batches.
+    fn content_type(&self) -> &'static str;
+
+    /// Converts a record into a batch entry.
+    fn entry(&self, record: &LogRecord<'_>) -> io::Result<Entry>;
+
+    /// Converts a pre-serialized JSON record into a batch entry.
+    fn entry_from_json(&self, bytes: &[u8]) -> io::Result<Entry>;
+
+    /// Builds the request body for a batch.
+    fn encode(&self, batch: &[Entry]) -> Vec<u8>;
+
+    /// Inspects the body of a successful response and returns an error if
+    /// the server rejected part of the batch.
+    fn check_response(&self, _body: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl BatchFormat for HttpFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            HttpFormat::ElasticsearchBulk { .. } => "application/x-ndjson",
+            HttpFormat::Loki { .. } | HttpFormat::JsonArray => "application/json",
+        }
+    }
+
+    fn entry(&self, record: &LogRecord<'_>) -> io::Result<Entry> {
+        Entry::from_record(record, self)
+    }
+
+    fn entry_from_json(&self, bytes: &[u8]) -> io::Result<Entry> {
+        Entry::from_json(bytes, self)
+    }
+
+    fn encode(&self, batch: &[Entry]) -> Vec<u8> {
+        match self {
+            HttpFormat::Loki { .. } => {
+                let mut streams: Vec<(&BTreeMap<String, String>, Vec<serde_json::Value>)> =
+                    Vec::new();
+                for entry in batch {
+                    let value = serde_json::json!([
+                        entry.timestamp_ns.to_string(),
+                        String::from_utf8_lossy(&entry.doc),
+                    ]);
+                    match streams
+                        .iter_mut()
+                        .find(|(labels, _)| **labels == entry.labels)
+                    {
+                        Some((_, values)) => values.push(value),
+                        None => streams.push((&entry.labels, vec![value])),
+                    }
+                }
+                let streams: Vec<serde_json::Value> = streams
+                    .into_iter()
+                    .map(|(labels, values)| serde_json::json!({ "stream": labels, "values": values }))
+                    .collect();
+                serde_json::to_vec(&serde_json::json!({ "streams": streams })).unwrap_or_default()
+            }
+            HttpFormat::ElasticsearchBulk { index } => {
+                let action =
+                    serde_json::to_vec(&serde_json::json!({ "create": { "_index": index } }))
+                        .unwrap_or_default();
+                let mut body = Vec::new();
+                for entry in batch {
+                    body.extend_from_slice(&action);
+                    body.push(b'\n');
+                    body.extend_from_slice(&entry.doc);
+                    body.push(b'\n');
+                }
+                body
+            }
+            HttpFormat::JsonArray => {
+                let mut body = vec![b'['];
+                for (i, entry) in batch.iter().enumerate() {
+                    if i > 0 {
+                        body.push(b',');
+                    }
+                    body.extend_from_slice(&entry.doc);
+                }
+                body.push(b']');
+                body
+            }
+        }
+    }
+
+    /// The bulk API reports per-document failures in a 200 response.
+    fn check_response(&self, body: &str) -> io::Result<()> {
+        if let HttpFormat::ElasticsearchBulk { .. } = self {
+            if body.contains("\"errors\":true") {
+                return Err(io::Error::other(format!(
+                    "Elasticsearch rejected some documents: {}",
+                    body
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A record waiting to be sent.
+pub(crate) struct Entry {
+    /// The encoded record; JSON for [`HttpFormat`].
+    pub(crate) doc: Vec<u8>,
+    /// Record time in Unix nanoseconds.
+    pub(crate) timestamp_ns: i64,
+    /// Labels records are grouped by within a request (Loki streams).
+    pub(crate) labels: BTreeMap<String, String>,
+}
+
+impl Entry {
+    fn from_record(record: &LogRecord<'_>, format: &HttpFormat) -> io::Result<Self> {
+        let doc = serde_json::to_vec(record)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut labels = BTreeMap::new();
+        if let HttpFormat::Loki { labels: keys } = format {
+            labels.insert("logger".to_string(), record.logger.to_string());
+            labels.insert("level".to_string(), record.level.as_str().to_lowercase());
+            for key in keys {
+                if let Some(value) = record.context.get(key) {
+                    labels.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        Ok(Self {
+            doc,
+            timestamp_ns: timestamp_ns(record.timestamp),
+            labels,
+        })
+    }
+
+    /// Builds an entry from a pre-serialized JSON record.
+    fn from_json(bytes: &[u8], format: &HttpFormat) -> io::Result<Self> {
+        let doc = bytes.trim_ascii_end().to_vec();
+        let value: serde_json::Value = serde_json::from_slice(&doc)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let field = |key: &str| value.get(key).and_then(|v| v.as_str());
+        let mut labels = BTreeMap::new();
+        if let HttpFormat::Loki { labels: keys } = format {
+            labels.insert(
+                "logger".to_string(),
+                field("logger").unwrap_or("unknown").to_string(),
+            );
+            labels.insert(
+                "level".to_string(),
+                field("level").unwrap_or("info").to_lowercase(),
+            );
+            for key in keys {
+                if let Some(v) = field(key) {
+                    labels.insert(key.clone(), v.to_string());
+                }
+            }
+        }
+        Ok(Self {
+            timestamp_ns: timestamp_ns(field("timestamp").unwrap_or_default()),
+            doc,
+            labels,
+        })
+    }
+}
+
+/// Parses an RFC 3339 timestamp into Unix nanoseconds, falling back to now.
+pub(crate) fn timestamp_ns(timestamp: &str) -> i64 {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .and_then(|t| t.timestamp_nanos_opt())
+        .or_else(|| chrono::Utc::now().timestamp_nanos_opt())
+        .unwrap_or_default()
+}
+
+enum Message {
+    Entry(Entry),
+    Flush(SyncSender<io::Result<()>>),
+}
+
+/// Outcome of a failed POST.
+enum PostError {
+    /// Transport errors, `429` and `5xx`; worth retrying.
+    Retryable(io::Error),
+    /// Any other non-success status.
+    Fatal(io::Error),
+}
+
+/// POSTs request bodies with retries and optional gzip compression.
+///
+/// Shared by the batching worker of [`HttpOutput`] and the OTLP exporters.
+pub(crate) struct HttpClient {
+    pub(crate) url: String,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) max_retries: u32,
+    pub(crate) retry_backoff: Duration,
+    pub(crate) gzip: bool,
+    pub(crate) error_handler: Option<ErrorHandler>,
+    agent: ureq::Agent,
+}
+
+impl HttpClient {
+    pub(crate) fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            headers: Vec::new(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            gzip: false,
+            error_handler: None,
+            agent: Self::agent(DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// Sets the timeout for a whole request.
+    pub(crate) fn set_timeout(&mut self, timeout: Duration) {
+        self.agent = Self::agent(timeout);
+    }
+
+    fn agent(timeout: Duration) -> ureq::Agent {
+        ureq::Agent::config_builder()
+            .timeout_global(Some(timeout))
+            .http_status_as_error(false)
+            .build()
+            .into()
+    }
+
+    /// POSTs `body`, retrying transient failures, and returns the response body.
+    pub(crate) fn post(&self, body: &[u8], content_type: &str) -> io::Result<String> {
+        let compressed;
+        let body = if self.gzip {
+            compressed = gzip(body)?;
+            &compressed[..]
+        } else {
+            body
+        };
+
+        let mut backoff = self.retry_backoff;
+        let mut attempt = 0;
+        loop {
+            match self.post_once(body, content_type) {
+                Ok(text) => return Ok(text),
+                Err(PostError::Retryable(e)) if attempt < self.max_retries => {
+                    attempt += 1;
+                    let notice = io::Error::new(
+                        e.kind(),
+                        format!(
+                            "request to {} failed ({}), retry {} of {}",
+                            self.url, e, attempt, self.max_retries
+                        ),
+                    );
+                    report_error(self.error_handler.as_ref(), "HttpOutput", &notice);
+                    std::thread::sleep(backoff);
+                    backoff *= 2;
+                }
+                Err(PostError::Retryable(e)) | Err(PostError::Fatal(e)) => return Err(e),
+            }
+        }
+    }
+
+    fn post_once(&self, body: &[u8], content_type: &str) -> Result<String, PostError> {
+        let mut request = self
+            .agent
+            .post(&self.url)
+            .header("Content-Type", content_type);
+        if self.gzip {
+            request = request.header("Content-Encoding", "gzip");
+        }
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+
+        let mut response = request
+            .send(body)
+            .map_err(|e| PostError::Retryable(io::Error::other(e)))?;
+        let status = response.status().as_u16();
+        if !(200..300).contains(&status) {
+            let error = io::Error::other(format!("HTTP status {} from {}", status, self.url));
+            return Err(if status == 429 || status >= 500 {
+                PostError::Retryable(error)
+            } else {
+                PostError::Fatal(error)
+            });
+        }
+        Ok(response.body_mut().read_to_string().unwrap_or_default())
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn gzip(body: &[u8]) -> io::Result<Vec<u8>> {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(body)?;
+    encoder.finish()
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip(body: &[u8]) -> io::Result<Vec<u8>> {
+    Ok(body.to_vec())
+}
+
+/// Batches log records on a background thread and POSTs them to an HTTP endpoint.
+///
+/// Writing never blocks on the network: records are handed to the background
+/// thread through a bounded queue, and if it is full the record is dropped and
+/// `write` returns an error.
+pub struct HttpOutput {
+    url: String,
+    format: Arc<dyn BatchFormat>,
+    headers: Vec<(String, String)>,
+    batch_size: usize,
+    max_batch_bytes: usize,
+    max_batch_age: Duration,
+    max_retries: u32,
+    retry_backoff: Duration,
+    timeout: Duration,
+    gzip: bool,
+    error_handler: Option<ErrorHandler>,
+    worker: OnceLock<Option<(SyncSender<Message>, JoinHandle<()>)>>,
+}
+
+impl HttpOutput {
+    /// Creates an output that POSTs batches to `url` in the given format.
+    ///
+    /// The background thread is started by the first record, so builder
+    /// options apply to every batch.
+    pub fn new(url: &str, format: HttpFormat) -> Self {
+        Self::with_batch_format(url, Arc::new(format))
+    }
+
+    /// Creates an output that POSTs batches encoded by a custom format.
+    pub(crate) fn with_batch_format(url: &str, format: Arc<dyn BatchFormat>) -> Self {
+        Self {
+            url: url.to_string(),
+            format,
+            headers: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            max_batch_bytes: DEFAULT_MAX_BATCH_BYTES,
+            max_batch_age: DEFAULT_MAX_BATCH_AGE,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            timeout: DEFAULT_TIMEOUT,
+            gzip: false,
+            error_handler: None,
+            worker: OnceLock::new(),
+        }
+    }
+
+    /// Replaces the batch format; only effective before the first record.
+    #[cfg(feature = "otlp")]
+    pub(crate) fn set_batch_format(&mut self, format: Arc<dyn BatchFormat>) {
+        self.format = format;
+    }
+
+    /// Sends a batch once it holds this many records (100 by default).
+    pub fn with_batch_size(mut self, records: usize) -> Self {
+        self.batch_size = records.max(1);
+        self
+    }
+
+    /// Sends a batch once its records total this many bytes of JSON (1 MiB by default).
+    pub fn with_max_batch_bytes(mut self, bytes: usize) -> Self {
+        self.max_batch_bytes = bytes.max(1);
+        self
+    }
+
+    /// Sends a batch at the latest this long after its first record (1s by default).
+    pub fn with_max_batch_age(mut self, age: Duration) -> Self {
+        self.max_batch_age = age;
+        self
+    }
+
+    /// Sets how often a failed request is retried and the delay before the
+    /// first retry, which doubles on each attempt (3 and 100ms by default).
+    pub fn with_retry(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Adds a request header, e.g. `Authorization` or `X-Scope-OrgID`.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the timeout for a whole request (10s by default).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Compresses request bodies with gzip (requires `gzip` feature).
+    #[cfg(feature = "gzip")]
+    pub fn with_gzip(mut self, enabled: bool) -> Self {
+        self.gzip = enabled;
+        self
+    }
+
+    /// Reports failed requests, retries and rejected documents to `handler`
+    /// instead of stderr.
+    pub fn with_error_handler(mut self, handler: ErrorHandler) -> Self {
+        self.error_handler = Some(handler);
+        self
+    }
+
+    fn sender(&self) -> io::Result<&SyncSender<Message>> {
+        match self.worker.get_or_init(|| self.spawn()) {
+            Some((sender, _)) => Ok(sender),
+            None => Err(io::Error::other("HttpOutput worker is not running")),
+        }
+    }
+
+    fn send(&self, entry: Entry) -> io::Result<()> {
+        match self.sender()?.try_send(Message::Entry(entry)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "HttpOutput queue full, record dropped",
+            )),
+            Err(TrySendError::Disconnected(_)) => {
+                Err(io::Error::other("HttpOutput worker has stopped"))
+            }
+        }
+    }
+
+    /// Starts the background thread with the configured options.
+    fn spawn(&self) -> Option<(SyncSender<Message>, JoinHandle<()>)> {
+        let mut client = HttpClient::new(&self.url);
+        client.headers = self.headers.clone();
+        client.max_retries = self.max_retries;
+        client.retry_backoff = self.retry_backoff;
+        client.gzip = self.gzip;
+        client.error_handler = self.error_handler.clone();
+        client.set_timeout(self.timeout);
+        let format = Arc::clone(&self.format);
+        let limits = (self.batch_size, self.max_batch_bytes, self.max_batch_age);
+        let (sender, receiver) = mpsc::sync_channel(self.batch_size * QUEUE_BATCHES);
+        match std::thread::Builder::new()
+            .name("telelog-http".to_string())
+            .spawn(move || run(receiver, client, format, limits))
+        {
+            Ok(handle) => Some((sender, handle)),
+            Err(e) => {
+                let error = io::Error::new(e.kind(), format!("cannot start worker: {}", e));
+                report_error(self.error_handler.as_ref(), "HttpOutput", &error);
+                None
+            }
+        }
+    }
+}
+
+/// Background loop: collects entries and delivers a batch when a limit is hit.
+fn run(
+    receiver: Receiver<Message>,
+    client: HttpClient,
+    format: Arc<dyn BatchFormat>,
+    limits: (usize, usize, Duration),
+) {
+    let (max_records, max_bytes, max_age) = limits;
+    let mut batch: Vec<Entry> = Vec::new();
+    let mut bytes = 0;
+    let mut deadline: Option<Instant> = None;
+
+    let ship = |batch: &mut Vec<Entry>, bytes: &mut usize| -> io::Result<()> {
+        *bytes = 0;
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let handler = client.error_handler.as_ref();
+        let result = client
+            .post(&format.encode(batch), format.content_type())
+            .map(|response| {
+                if let Err(e) = format.check_response(&response) {
+                    report_error(handler, "HttpOutput", &e);
+                }
+            });
+        if let Err(e) = &result {
+            let error = io::Error::new(e.kind(), format!("dropped {} records: {}", batch.len(), e));
+            report_error(handler, "HttpOutput", &error);
+        }
+        batch.clear();
+        result
+    };
+
+    loop {
+        let message = match deadline {
+            Some(at) => match receiver.recv_timeout(at.saturating_duration_since(Instant::now())) {
+                Ok(message) => Some(message),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            None => match receiver.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break,
+            },
+        };
+
+        match message {
+            Some(Message::Entry(entry)) => {
+                if batch.is_empty() {
+                    deadline = Some(Instant::now() + max_age);
+                }
+                bytes += entry.doc.len();
+                batch.push(entry);
+                if batch.len() >= max_records || bytes >= max_bytes {
+                    let _ = ship(&mut batch, &mut bytes);
+                    deadline = None;
+                }
+            }
+            Some(Message::Flush(ack)) => {
+                let _ = ack.send(ship(&mut batch, &mut bytes));
+                deadline = None;
+            }
+            None => {
+                let _ = ship(&mut batch, &mut bytes);
+                deadline = None;
+            }
+        }
+    }
+
+    let _ = ship(&mut batch, &mut bytes);
+}
+
+impl OutputDestination for HttpOutput {
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        self.send(self.format.entry(record)?)
+    }
+
+    /// Sends the pending batch and waits for the request to complete.
+    fn flush(&self) -> io::Result<()> {
+        let (ack, done) = mpsc::sync_channel(1);
+        self.sender()?
+            .send(Message::Flush(ack))
+            .map_err(|_| io::Error::other("HttpOutput worker has stopped"))?;
+        done.recv()
+            .map_err(|_| io::Error::other("HttpOutput worker has stopped"))?
+    }
+
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        self.send(self.format.entry_from_json(bytes)?)
+    }
+}
+
+impl Drop for HttpOutput {
+    fn drop(&mut self) {
+        if let Some(Some((sender, handle))) = self.worker.take() {
+            drop(sender);
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::level::LogLevel;
+    use std::collections::{HashMap, VecDeque};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    pub(crate) struct Request {
+        pub(crate) headers: HashMap<String, String>,
+        pub(crate) body: Vec<u8>,
+    }
+
+    /// Starts a minimal HTTP/1.1 server answering with `statuses` in order,
+    /// then `204`, and reports every request it receives.
+    pub(crate) fn stub_server(statuses: &[u16]) -> (String, mpsc::Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/push", listener.local_addr().unwrap());
+        let statuses = Arc::new(Mutex::new(
+            statuses.iter().copied().collect::<VecDeque<_>>(),
+        ));
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let statuses = Arc::clone(&statuses);
+                let tx = tx.clone();
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    loop {
+                        let mut headers = HashMap::new();
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        loop {
+                            line.clear();
+                            reader.read_line(&mut line).unwrap();
+                            let Some((name, value)) = line.trim_end().split_once(": ") else {
+                                break;
+                            };
+                            headers.insert(name.to_ascii_lowercase(), value.to_string());
+                        }
+                        let len = headers["content-length"].parse().unwrap();
+                        let mut body = vec![0u8; len];
+                        reader.read_exact(&mut body).unwrap();
+
+                        let _ = tx.send(Request { headers, body });
+                        let status = statuses.lock().unwrap().pop_front().unwrap_or(204);
+                        write!(
+                            stream,
+                            "HTTP/1.1 {} Stub\r\nContent-Length: 0\r\n\r\n",
+                            status
+                        )
+                        .unwrap();
+                    }
+                });
+            }
+        });
+        (url, rx)
+    }
+
+    fn write(output: &HttpOutput, message: &str, service: &str) {
+        let mut ctx = HashMap::new();
+        ctx.insert("service".to_string(), service.to_string());
+        output
+            .write(&LogRecord {
+                timestamp: "2025-09-07T10:30:00Z",
+                level: LogLevel::Info,
+                logger: "web",
+                message,
+                context: &ctx,
+                data: None,
+                location: None,
+            })
+            .unwrap();
+    }
+
+    fn json(body: &[u8]) -> serde_json::Value {
+        serde_json::from_slice(body).unwrap()
+    }
+
+    #[test]
+    fn test_json_array_batches_by_count() {
+        let (url, requests) = stub_server(&[]);
+        let output = HttpOutput::new(&url, HttpFormat::JsonArray).with_batch_size(2);
+
+        write(&output, "one", "api");
+        write(&output, "two", "api");
+        write(&output, "three", "api");
+
+        let request = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(request.headers["content-type"], "application/json");
+        let body = json(&request.body);
+        assert_eq!(body.as_array().unwrap().len(), 2);
+        assert_eq!(body[0]["message"], "one");
+        assert_eq!(body[1]["service"], "api");
+
+        output.flush().unwrap();
+        let request = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(json(&request.body)[0]["message"], "three");
+    }
+
+    #[test]
+    fn test_batch_sent_after_max_age() {
+        let (url, requests) = stub_server(&[]);
+        let output = HttpOutput::new(&url, HttpFormat::JsonArray)
+            .with_max_batch_age(Duration::from_millis(50));
+
+        write(&output, "aged", "api");
+
+        let request = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(json(&request.body)[0]["message"], "aged");
+    }
+
+    #[test]
+    fn test_loki_streams_by_label() {
+        let (url, requests) = stub_server(&[]);
+        let output = HttpOutput::new(
+            &url,
+            HttpFormat::Loki {
+                labels: vec!["service".to_string()],
+            },
+        );
+
+        write(&output, "a", "api");
+        write(&output, "b", "worker");
+        write(&output, "c", "api");
+        output.flush().unwrap();
+
+        let body = json(&requests.recv_timeout(Duration::from_secs(5)).unwrap().body);
+        let streams = body["streams"].as_array().unwrap();
+        assert_eq!(streams.len(), 2);
+        assert_eq!(
+            streams[0]["stream"],
+            serde_json::json!({ "logger": "web", "level": "info", "service": "api" })
+        );
+        let values = streams[0]["values"].as_array().unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0][0], "1757241000000000000");
+        assert_eq!(
+            json(values[1][1].as_str().unwrap().as_bytes())["message"],
+            "c"
+        );
+        assert_eq!(streams[1]["stream"]["service"], "worker");
+    }
+
+    #[test]
+    fn test_elasticsearch_bulk() {
+        let (url, requests) = stub_server(&[]);
+        let output = HttpOutput::new(
+            &url,
+            HttpFormat::ElasticsearchBulk {
+                index: "logs-app".to_string(),
+            },
+        );
+
+        write(&output, "indexed", "api");
+        output
+            .write_bytes(b"{\"message\":\"raw\",\"level\":\"ERROR\"}\n")
+            .unwrap();
+        output.flush().unwrap();
+
+        let request = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(request.headers["content-type"], "application/x-ndjson");
+        let body = String::from_utf8(request.body).unwrap();
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], r#"{"create":{"_index":"logs-app"}}"#);
+        assert_eq!(json(lines[1].as_bytes())["message"], "indexed");
+        assert_eq!(lines[3], r#"{"message":"raw","level":"ERROR"}"#);
+    }
+
+    #[test]
+    fn test_retries_server_errors() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let handler = {
+            let seen = Arc::clone(&seen);
+            ErrorHandler::new(move |name, e| seen.lock().unwrap().push(format!("{}: {}", name, e)))
+        };
+        let (url, requests) = stub_server(&[503, 429]);
+        let output = HttpOutput::new(&url, HttpFormat::JsonArray)
+            .with_retry(3, Duration::from_millis(10))
+            .with_error_handler(handler);
+
+        write(&output, "retried", "api");
+        output.flush().unwrap();
+
+        let bodies: Vec<Vec<u8>> = requests.try_iter().map(|r| r.body).collect();
+        assert_eq!(bodies.len(), 3);
+        assert!(bodies.iter().all(|b| b == &bodies[0]));
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert!(seen[0].starts_with("HttpOutput: request to"));
+        assert!(seen[1].ends_with("retry 2 of 3"));
+    }
+
+    #[test]
+    fn test_client_error_is_not_retried() {
+        let (url, requests) = stub_server(&[400]);
+        let output =
+            HttpOutput::new(&url, HttpFormat::JsonArray).with_retry(3, Duration::from_millis(10));
+
+        write(&output, "rejected", "api");
+        assert!(output.flush().is_err());
+        assert_eq!(requests.try_iter().count(), 1);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_body() {
+        let (url, requests) = stub_server(&[]);
+        let output = HttpOutput::new(&url, HttpFormat::JsonArray)
+            .with_gzip(true)
+            .with_header("Authorization", "Bearer token");
+
+        write(&output, "compressed", "api");
+        output.flush().unwrap();
+
+        let request = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(request.headers["content-encoding"], "gzip");
+        assert_eq!(request.headers["authorization"], "Bearer token");
+        let mut body = Vec::new();
+        flate2::read::GzDecoder::new(&request.body[..])
+            .read_to_end(&mut body)
+            .unwrap();
+        assert_eq!(json(&body)[0]["message"], "compressed");
+    }
+}
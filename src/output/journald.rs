@@ -0,0 +1,378 @@
+//! Native systemd-journald output (requires the `journald` feature, Linux only).
+//!
+//! [`JournaldOutput`] speaks the journal's native protocol: each record is one
+//! datagram of `FIELD=value` lines sent to `/run/systemd/journal/socket`.
+//! Values containing newlines use the protocol's length-prefixed binary form.
+//! Records too large for a single datagram are written to a sealed memfd whose
+//! descriptor is passed to journald instead, as `sd_journal_send` does.
+//!
+//! Besides `MESSAGE`, every entry carries `PRIORITY`, `SYSLOG_IDENTIFIER`,
+//! `TELELOG_LOGGER` and, when the call site is known, `CODE_FILE` and
+//! `CODE_LINE`. Context and data fields become upper-case journal fields, so
+//! `("user_id", "42")` can be queried with `journalctl USER_ID=42`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use telelog::output::JournaldOutput;
+//!
+//! let journald = JournaldOutput::new().unwrap().with_identifier("billing");
+//! ```
+
+use crate::output::{LogRecord, OutputDestination, OwnedRecord};
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+
+/// Path of journald's native protocol socket.
+pub const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Fields set by the output itself; user fields with these names are prefixed
+/// with `USER_` so they cannot override them.
+const RESERVED_FIELDS: &[&str] = &[
+    "MESSAGE",
+    "PRIORITY",
+    "SYSLOG_IDENTIFIER",
+    "TELELOG_LOGGER",
+    "CODE_FILE",
+    "CODE_LINE",
+];
+
+/// Sends log records to systemd-journald using the native journal protocol.
+pub struct JournaldOutput {
+    socket: UnixDatagram,
+    path: PathBuf,
+    identifier: Option<String>,
+}
+
+impl JournaldOutput {
+    /// Creates an output that sends to the system journal socket.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a Unix datagram socket cannot be created.
+    pub fn new() -> io::Result<Self> {
+        Self::with_socket(JOURNALD_SOCKET)
+    }
+
+    /// Creates an output that sends to the journal socket at `path`.
+    ///
+    /// The socket is addressed per send rather than connected, so a restart of
+    /// journald does not break the output.
+    pub fn with_socket<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self {
+            socket: UnixDatagram::unbound()?,
+            path: path.as_ref().to_path_buf(),
+            identifier: None,
+        })
+    }
+
+    /// Sets `SYSLOG_IDENTIFIER`, which defaults to the record's logger name.
+    pub fn with_identifier(mut self, identifier: &str) -> Self {
+        self.identifier = Some(identifier.to_string());
+        self
+    }
+
+    /// Encodes `record` as a native-protocol datagram payload.
+    pub(crate) fn encode(&self, record: &LogRecord<'_>) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(256);
+        append_field(&mut buf, "MESSAGE", record.message.as_bytes());
+        append_field(
+            &mut buf,
+            "PRIORITY",
+            record.level.syslog_severity().to_string().as_bytes(),
+        );
+        let identifier = self.identifier.as_deref().unwrap_or(record.logger);
+        append_field(&mut buf, "SYSLOG_IDENTIFIER", identifier.as_bytes());
+        append_field(&mut buf, "TELELOG_LOGGER", record.logger.as_bytes());
+        if let Some(location) = record.location {
+            append_field(&mut buf, "CODE_FILE", location.file().as_bytes());
+            append_field(
+                &mut buf,
+                "CODE_LINE",
+                location.line().to_string().as_bytes(),
+            );
+        }
+
+        let mut keys: Vec<&String> = record.context.keys().collect();
+        keys.sort_unstable();
+        for key in keys {
+            if let Some(name) = field_name(key) {
+                append_field(&mut buf, &name, record.context[key].as_bytes());
+            }
+        }
+        for (key, value) in record.data.unwrap_or_default() {
+            if let Some(name) = field_name(key) {
+                append_field(&mut buf, &name, value.as_bytes());
+            }
+        }
+        buf
+    }
+
+    fn send(&self, payload: &[u8]) -> io::Result<()> {
+        match self.socket.send_to(payload, &self.path) {
+            Ok(_) => Ok(()),
+            Err(e) if matches!(e.raw_os_error(), Some(libc::EMSGSIZE | libc::ENOBUFS)) => {
+                self.send_memfd(payload)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes `payload` to a sealed memfd and passes its descriptor to journald.
+    fn send_memfd(&self, payload: &[u8]) -> io::Result<()> {
+        // SAFETY: memfd_create takes a NUL-terminated name and flags; the result
+        // is checked before use and owned by `file` afterwards.
+        let fd = unsafe {
+            libc::memfd_create(
+                c"telelog-journal".as_ptr(),
+                libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.write_all(payload)?;
+
+        let seals =
+            libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+        // SAFETY: `fd` is a valid memfd owned by `file`.
+        if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        send_fd(&self.socket, &self.path, file.as_raw_fd())
+    }
+}
+
+impl OutputDestination for JournaldOutput {
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        self.send(&self.encode(record))
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Parses the pre-serialized JSON record so that its priority and fields
+    /// are kept.
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        OwnedRecord::from_json(bytes)?.with_record(|record| self.write(record))
+    }
+}
+
+/// Appends one field in the native protocol's text or binary form.
+fn append_field(buf: &mut Vec<u8>, name: &str, value: &[u8]) {
+    buf.extend_from_slice(name.as_bytes());
+    if value.contains(&b'\n') {
+        buf.push(b'\n');
+        buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        buf.push(b'=');
+    }
+    buf.extend_from_slice(value);
+    buf.push(b'\n');
+}
+
+/// Converts a context or data key into a valid journal field name.
+///
+/// Names are upper-cased, characters outside `[A-Z0-9_]` become `_`, leading
+/// underscores (reserved for trusted fields) are dropped and names starting
+/// with a digit get an `F` prefix. Returns `None` if nothing usable remains.
+fn field_name(key: &str) -> Option<String> {
+    let upper: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let mut name = upper.trim_start_matches('_').to_string();
+    if name.is_empty() {
+        return None;
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, 'F');
+    }
+    if RESERVED_FIELDS.contains(&name.as_str()) {
+        name.insert_str(0, "USER_");
+    }
+    name.truncate(64);
+    Some(name)
+}
+
+/// Sends an empty datagram to `path` carrying `fd` as `SCM_RIGHTS` ancillary data.
+fn send_fd(socket: &UnixDatagram, path: &Path, fd: RawFd) -> io::Result<()> {
+    // SAFETY: sockaddr_un and msghdr are plain C structs for which all-zero is valid.
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    let path_bytes = path.as_os_str().as_bytes();
+    if path_bytes.len() >= addr.sun_path.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "journal socket path too long",
+        ));
+    }
+    for (dst, src) in addr.sun_path.iter_mut().zip(path_bytes) {
+        *dst = *src as libc::c_char;
+    }
+    let addr_len = std::mem::offset_of!(libc::sockaddr_un, sun_path) + path_bytes.len();
+
+    let fd_size = std::mem::size_of::<RawFd>() as u32;
+    // SAFETY: CMSG_SPACE/CMSG_LEN only compute sizes.
+    let space = unsafe { libc::CMSG_SPACE(fd_size) } as usize;
+    let mut control = vec![0u64; space.div_ceil(8)];
+
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_name = &mut addr as *mut libc::sockaddr_un as *mut libc::c_void;
+    msg.msg_namelen = addr_len as libc::socklen_t;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = space as _;
+
+    // SAFETY: `control` is large enough and suitably aligned for one cmsghdr
+    // carrying a single descriptor, as sized by CMSG_SPACE above.
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(fd_size) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, fd);
+    }
+
+    // SAFETY: `msg` points at live buffers for the duration of the call.
+    let sent = unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) };
+    if sent < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::LogLevel;
+    use std::collections::HashMap;
+    use std::io::{Read, Seek, SeekFrom};
+
+    fn listener() -> (tempfile::TempDir, UnixDatagram, JournaldOutput) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.sock");
+        let socket = UnixDatagram::bind(&path).unwrap();
+        let output = JournaldOutput::with_socket(&path).unwrap();
+        (dir, socket, output)
+    }
+
+    #[test]
+    fn test_field_names() {
+        assert_eq!(field_name("user_id").as_deref(), Some("USER_ID"));
+        assert_eq!(field_name("http.method").as_deref(), Some("HTTP_METHOD"));
+        assert_eq!(field_name("_trusted").as_deref(), Some("TRUSTED"));
+        assert_eq!(field_name("2fa").as_deref(), Some("F2FA"));
+        assert_eq!(field_name("message").as_deref(), Some("USER_MESSAGE"));
+        assert_eq!(field_name("__"), None);
+    }
+
+    #[test]
+    fn test_native_datagram() {
+        let (_dir, socket, output) = listener();
+        let mut ctx = HashMap::new();
+        ctx.insert("request_id".to_string(), "abc".to_string());
+        let location = std::panic::Location::caller();
+
+        output
+            .write(&LogRecord {
+                timestamp: "2025-09-07T10:30:00Z",
+                level: LogLevel::Warning,
+                logger: "api",
+                message: "Slow query",
+                context: &ctx,
+                data: Some(&[("sql", "SELECT 1\nFROM t")]),
+                location: Some(location),
+            })
+            .unwrap();
+
+        let mut buf = vec![0u8; 4096];
+        let n = socket.recv(&mut buf).unwrap();
+        let mut expected = format!(
+            "MESSAGE=Slow query\nPRIORITY=4\nSYSLOG_IDENTIFIER=api\nTELELOG_LOGGER=api\n\
+             CODE_FILE={}\nCODE_LINE={}\nREQUEST_ID=abc\nSQL\n",
+            location.file(),
+            location.line(),
+        )
+        .into_bytes();
+        expected.extend_from_slice(&15u64.to_le_bytes());
+        expected.extend_from_slice(b"SELECT 1\nFROM t\n");
+        assert_eq!(&buf[..n], &expected[..]);
+    }
+
+    #[test]
+    fn test_write_bytes_keeps_priority_and_fields() {
+        let (_dir, socket, output) = listener();
+        output
+            .write_bytes(b"{\"timestamp\":\"2025-09-07T10:30:00Z\",\"level\":\"ERROR\",\"logger\":\"api\",\"message\":\"boom\",\"code\":500}\n")
+            .unwrap();
+
+        let mut buf = vec![0u8; 4096];
+        let n = socket.recv(&mut buf).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..n]).unwrap(),
+            "MESSAGE=boom\nPRIORITY=3\nSYSLOG_IDENTIFIER=api\nTELELOG_LOGGER=api\nCODE=500\n"
+        );
+    }
+
+    #[test]
+    fn test_large_record_uses_memfd() {
+        let (_dir, socket, output) = listener();
+        let message = "x".repeat(4 * 1024 * 1024);
+        let ctx = HashMap::new();
+
+        output
+            .write(&LogRecord {
+                timestamp: "2025-09-07T10:30:00Z",
+                level: LogLevel::Info,
+                logger: "api",
+                message: &message,
+                context: &ctx,
+                data: None,
+                location: None,
+            })
+            .unwrap();
+
+        // Receive the descriptor passed as SCM_RIGHTS.
+        let space = unsafe { libc::CMSG_SPACE(std::mem::size_of::<RawFd>() as u32) } as usize;
+        let mut control = vec![0u64; space.div_ceil(8)];
+        let mut data = [0u8; 16];
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = space as _;
+        let received = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, 0) };
+        assert_eq!(received, 0);
+        let fd = unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            assert_eq!((*cmsg).cmsg_type, libc::SCM_RIGHTS);
+            std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const RawFd)
+        };
+
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut payload = Vec::new();
+        file.read_to_end(&mut payload).unwrap();
+        assert!(payload.starts_with(b"MESSAGE=xxx"));
+        assert!(payload.ends_with(b"TELELOG_LOGGER=api\n"));
+        assert_eq!(payload.len(), message.len() + 61);
+    }
+}
@@ -0,0 +1,268 @@
+//! JSON encoding of log records without per-record allocation.
+//!
+//! [`encode_line`] writes a [`LogRecord`] as one newline-terminated JSON
+//! object, byte for byte the same as its `serde::Serialize` implementation but
+//! without going through serde. Records are encoded into a buffer that each
+//! thread reuses, so the steady state allocates nothing.
+//!
+//! [`EncodedRecord`] encodes a record lazily, at most once, and is handed to
+//! every destination of a [`MultiOutput`](crate::output::MultiOutput) through
+//! [`OutputDestination::write_encoded`](crate::output::OutputDestination::write_encoded).
+
+use crate::output::LogRecord;
+use std::cell::{Cell, OnceCell};
+
+thread_local! {
+    /// Buffer reused by [`with_buffer`] on this thread. Taken out while in
+    /// use, so nested calls get a fresh buffer instead of a borrow conflict.
+    static BUF: Cell<Vec<u8>> = Cell::new(Vec::with_capacity(256));
+    /// Buffer holding the line of the current [`EncodedRecord`]. Separate from
+    /// `BUF` so that destinations serializing records themselves inside a
+    /// fan-out still reuse their buffer.
+    static LINE: Cell<Vec<u8>> = Cell::new(Vec::with_capacity(256));
+}
+
+/// Calls `f` with this thread's cleared reusable buffer.
+pub(crate) fn with_buffer<R>(f: impl FnOnce(&mut Vec<u8>) -> R) -> R {
+    let mut buf = BUF.with(Cell::take);
+    buf.clear();
+    let result = f(&mut buf);
+    BUF.with(|slot| slot.set(buf));
+    result
+}
+
+/// Appends `record` to `out` as a JSON object followed by a newline.
+pub(crate) fn encode_line(out: &mut Vec<u8>, record: &LogRecord<'_>) {
+    out.push(b'{');
+    write_entry(out, "timestamp", record.timestamp, true);
+    write_entry(out, "level", record.level.as_str(), false);
+    write_entry(out, "logger", record.logger, false);
+    write_entry(out, "message", record.message, false);
+    for (key, value) in record.context {
+        write_entry(out, key, value, false);
+    }
+    for (key, value) in record.data.unwrap_or_default() {
+        write_entry(out, key, value, false);
+    }
+    out.extend_from_slice(b"}\n");
+}
+
+fn write_entry(out: &mut Vec<u8>, key: &str, value: &str, first: bool) {
+    if !first {
+        out.push(b',');
+    }
+    write_string(out, key);
+    out.push(b':');
+    write_string(out, value);
+}
+
+/// Writes `value` as a quoted JSON string, escaping like `serde_json`.
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    out.push(b'"');
+    let bytes = value.as_bytes();
+    let mut start = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        let escape: &[u8] = match byte {
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            0x08 => b"\\b",
+            0x0c => b"\\f",
+            0x00..=0x1f => &[
+                b'\\',
+                b'u',
+                b'0',
+                b'0',
+                HEX[(byte >> 4) as usize],
+                HEX[(byte & 0xf) as usize],
+            ],
+            _ => continue,
+        };
+        out.extend_from_slice(&bytes[start..i]);
+        out.extend_from_slice(escape);
+        start = i + 1;
+    }
+    out.extend_from_slice(&bytes[start..]);
+    out.push(b'"');
+}
+
+/// A record together with its JSON line, encoded on first use.
+///
+/// Destinations that write the JSON form of records use [`json`](Self::json)
+/// instead of serializing the record themselves, so a record sent to several
+/// such destinations is encoded only once.
+pub struct EncodedRecord<'r, 'a> {
+    record: &'r LogRecord<'a>,
+    line: OnceCell<Vec<u8>>,
+}
+
+impl<'r, 'a> EncodedRecord<'r, 'a> {
+    /// Calls `f` with a lazily encoded view of `record`. The line buffer is
+    /// taken from this thread only if a destination asks for the JSON form.
+    pub(crate) fn scope<R>(record: &'r LogRecord<'a>, f: impl FnOnce(&Self) -> R) -> R {
+        let encoded = Self {
+            record,
+            line: OnceCell::new(),
+        };
+        let result = f(&encoded);
+        if let Some(line) = encoded.line.into_inner() {
+            LINE.with(|slot| slot.set(line));
+        }
+        result
+    }
+
+    /// Returns the record.
+    pub fn record(&self) -> &'r LogRecord<'a> {
+        self.record
+    }
+
+    /// Returns the record as a newline-terminated JSON line, the format
+    /// expected by [`write_bytes`](crate::output::OutputDestination::write_bytes).
+    pub fn json(&self) -> &[u8] {
+        self.line.get_or_init(|| {
+            let mut line = LINE.with(Cell::take);
+            line.clear();
+            encode_line(&mut line, self.record);
+            line
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::LogLevel;
+    use std::collections::HashMap;
+
+    fn record<'a>(
+        message: &'a str,
+        context: &'a HashMap<String, String>,
+        data: &'a [(&'a str, &'a str)],
+    ) -> LogRecord<'a> {
+        LogRecord {
+            timestamp: "2025-09-07T10:30:00Z",
+            level: LogLevel::Error,
+            logger: "api",
+            message,
+            context,
+            data: Some(data),
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_matches_serde() {
+        let ctx: HashMap<String, String> = [
+            ("env".to_string(), "prod".to_string()),
+            ("q\"uote".to_string(), "back\\slash".to_string()),
+        ]
+        .into();
+        let message = "line\nbreak\ttab\r\u{8}\u{c}\u{0}\u{1f}\u{7f} é ✓ / </script>";
+        let record = record(message, &ctx, &[("code", "500"), ("", "")]);
+
+        let mut line = Vec::new();
+        encode_line(&mut line, &record);
+        let expected = serde_json::to_string(&record).unwrap() + "\n";
+        assert_eq!(String::from_utf8(line).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_encodes_once_and_reuses_buffer() {
+        let ctx = HashMap::new();
+        let record = record("hi", &ctx, &[]);
+
+        let first = EncodedRecord::scope(&record, |encoded| {
+            let json = encoded.json();
+            assert!(std::ptr::eq(json, encoded.json()));
+            assert_eq!(
+                json,
+                b"{\"timestamp\":\"2025-09-07T10:30:00Z\",\"level\":\"ERROR\",\"logger\":\"api\",\"message\":\"hi\"}\n"
+            );
+            json.as_ptr()
+        });
+        let second = EncodedRecord::scope(&record, |encoded| encoded.json().as_ptr());
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_nested_scopes() {
+        let ctx = HashMap::new();
+        let outer = record("outer", &ctx, &[]);
+        let inner = record("inner", &ctx, &[]);
+
+        EncodedRecord::scope(&outer, |a| {
+            let a = a.json();
+            EncodedRecord::scope(&inner, |b| {
+                assert!(b.json().windows(5).any(|w| w == b"inner"));
+            });
+            assert!(a.windows(5).any(|w| w == b"outer"));
+        });
+    }
+
+    mod counting {
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::cell::Cell;
+
+        thread_local! {
+            static COUNT: Cell<Option<usize>> = const { Cell::new(None) };
+        }
+
+        /// Counts allocations made by the current thread while enabled.
+        pub struct Counting;
+
+        unsafe impl GlobalAlloc for Counting {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                let _ = COUNT.try_with(|count| count.set(count.get().map(|n| n + 1)));
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        /// Returns how many allocations `f` made on this thread.
+        pub fn allocations(f: impl FnOnce()) -> usize {
+            COUNT.with(|count| count.set(Some(0)));
+            f();
+            COUNT.with(|count| count.take()).unwrap_or_default()
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: counting::Counting = counting::Counting;
+
+    #[test]
+    fn test_fan_out_reuses_buffers() {
+        use crate::output::{FileOutput, MultiOutput, OutputDestination};
+
+        let dir = tempfile::tempdir().unwrap();
+        let multi = MultiOutput::new()
+            .add_output(Box::new(
+                FileOutput::new(dir.path().join("a.log"), false).unwrap(),
+            ))
+            .add_output(Box::new(
+                FileOutput::new(dir.path().join("b.json"), true).unwrap(),
+            ))
+            .add_output(Box::new(
+                FileOutput::new(dir.path().join("c.log"), false).unwrap(),
+            ));
+        let outer = MultiOutput::new().add_output(Box::new(multi));
+        let ctx = HashMap::new();
+        let record = record("hello", &ctx, &[("user", "42")]);
+
+        for _ in 0..10 {
+            outer.write(&record).unwrap();
+        }
+        let count = counting::allocations(|| {
+            for _ in 0..100 {
+                outer.write(&record).unwrap();
+            }
+        });
+        assert_eq!(count, 0);
+    }
+}
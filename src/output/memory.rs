@@ -0,0 +1,347 @@
+//! In-memory ring buffer output with a query API.
+//!
+//! [`MemoryOutput`] keeps the most recent records as [`OwnedRecord`]s, bounded
+//! by a record count and optionally by total size. The oldest records are
+//! evicted first. Records are copied before the lock is taken and stored behind
+//! an [`Arc`], so writers hold the lock only to push a pointer and readers only
+//! to copy pointers out.
+//!
+//! Typical uses are serving recent logs from a debug endpoint and inspecting
+//! output in tests without parsing files.
+//!
+//! # Example
+//!
+//! ```
+//! use telelog::output::{MemoryOutput, MemoryQuery};
+//! use telelog::{Config, LogLevel, Logger};
+//! use std::sync::Arc;
+//!
+//! let memory = Arc::new(MemoryOutput::new(1000));
+//! let logger = Logger::with_config("api", Config::new().with_console_output(false));
+//! logger.add_output(Box::new(Arc::clone(&memory)));
+//!
+//! logger.info_with("request done", &[("status", "200")]);
+//! logger.error_with("request failed", &[("status", "500")]);
+//!
+//! let errors = memory.query(&MemoryQuery::new().with_min_level(LogLevel::Error));
+//! assert_eq!(errors.len(), 1);
+//! assert_eq!(errors[0].field("status"), Some("500"));
+//! ```
+
+use crate::level::LogLevel;
+use crate::output::{LogRecord, OutputDestination, OwnedRecord};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Arc;
+
+/// Filter for [`MemoryOutput::query`]. All set conditions must match.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryQuery {
+    min_level: Option<LogLevel>,
+    logger: Option<String>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    fields: Vec<(String, String)>,
+    limit: Option<usize>,
+}
+
+impl MemoryQuery {
+    /// Creates a query matching every record.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Matches records at `level` or above.
+    pub fn with_min_level(mut self, level: LogLevel) -> Self {
+        self.min_level = Some(level);
+        self
+    }
+
+    /// Matches records of the logger named `logger`.
+    pub fn with_logger(mut self, logger: &str) -> Self {
+        self.logger = Some(logger.to_string());
+        self
+    }
+
+    /// Matches records with `since <= timestamp < until`.
+    ///
+    /// Records whose timestamp is not RFC 3339 never match a time range.
+    pub fn with_time_range(mut self, since: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self.until = Some(until);
+        self
+    }
+
+    /// Matches records logged at or after `since`.
+    pub fn with_since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Matches records whose data field or context entry `key` equals `value`.
+    ///
+    /// May be called repeatedly; every field must match.
+    pub fn with_field(mut self, key: &str, value: &str) -> Self {
+        self.fields.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns at most the `limit` most recent matching records.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns whether `record` satisfies every condition of this query.
+    pub fn matches(&self, record: &OwnedRecord) -> bool {
+        if self.min_level.is_some_and(|min| record.level < min) {
+            return false;
+        }
+        if self.logger.as_ref().is_some_and(|l| *l != record.logger) {
+            return false;
+        }
+        if self.since.is_some() || self.until.is_some() {
+            let Ok(time) = DateTime::parse_from_rfc3339(&record.timestamp) else {
+                return false;
+            };
+            if self.since.is_some_and(|since| time < since)
+                || self.until.is_some_and(|until| time >= until)
+            {
+                return false;
+            }
+        }
+        self.fields
+            .iter()
+            .all(|(key, value)| record.field(key) == Some(value.as_str()))
+    }
+}
+
+struct Ring {
+    records: VecDeque<Arc<OwnedRecord>>,
+    bytes: usize,
+    evicted: u64,
+}
+
+/// Keeps the most recent log records in memory.
+pub struct MemoryOutput {
+    ring: Mutex<Ring>,
+    capacity: usize,
+    max_bytes: Option<usize>,
+}
+
+impl MemoryOutput {
+    /// Creates an output keeping the last `capacity` records.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            ring: Mutex::new(Ring {
+                records: VecDeque::with_capacity(capacity.min(4096)),
+                bytes: 0,
+                evicted: 0,
+            }),
+            capacity,
+            max_bytes: None,
+        }
+    }
+
+    /// Also limits the retained records to about `max_bytes` in total, as
+    /// measured by [`OwnedRecord::size`]. The newest record is always kept.
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Returns all retained records, oldest first.
+    pub fn snapshot(&self) -> Vec<Arc<OwnedRecord>> {
+        self.ring.lock().records.iter().cloned().collect()
+    }
+
+    /// Removes and returns all retained records, oldest first.
+    pub fn drain(&self) -> Vec<Arc<OwnedRecord>> {
+        let mut ring = self.ring.lock();
+        ring.bytes = 0;
+        ring.records.drain(..).collect()
+    }
+
+    /// Returns the retained records matching `query`, oldest first.
+    pub fn query(&self, query: &MemoryQuery) -> Vec<Arc<OwnedRecord>> {
+        let limit = query.limit.unwrap_or(usize::MAX);
+        let mut matches: Vec<Arc<OwnedRecord>> = {
+            let ring = self.ring.lock();
+            ring.records
+                .iter()
+                .rev()
+                .filter(|record| query.matches(record))
+                .take(limit)
+                .cloned()
+                .collect()
+        };
+        matches.reverse();
+        matches
+    }
+
+    /// Returns the number of retained records.
+    pub fn len(&self) -> usize {
+        self.ring.lock().records.len()
+    }
+
+    /// Returns whether no records are retained.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns how many records have been evicted to make room for newer ones.
+    pub fn evicted(&self) -> u64 {
+        self.ring.lock().evicted
+    }
+
+    /// Removes all retained records.
+    pub fn clear(&self) {
+        self.drain();
+    }
+
+    fn push(&self, record: OwnedRecord) {
+        let size = record.size();
+        let record = Arc::new(record);
+        let mut ring = self.ring.lock();
+        ring.records.push_back(record);
+        ring.bytes += size;
+        while ring.records.len() > 1
+            && (ring.records.len() > self.capacity
+                || self.max_bytes.is_some_and(|max| ring.bytes > max))
+        {
+            if let Some(old) = ring.records.pop_front() {
+                ring.bytes -= old.size();
+                ring.evicted += 1;
+            }
+        }
+    }
+}
+
+impl OutputDestination for MemoryOutput {
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        self.push(OwnedRecord::from_record(record));
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        self.push(OwnedRecord::from_json(bytes)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn write(output: &MemoryOutput, timestamp: &str, level: LogLevel, logger: &str, message: &str) {
+        let mut ctx = HashMap::new();
+        ctx.insert("request_id".to_string(), format!("req-{}", message));
+        output
+            .write(&LogRecord {
+                timestamp,
+                level,
+                logger,
+                message,
+                context: &ctx,
+                data: Some(&[("user_id", "42")]),
+                location: None,
+            })
+            .unwrap();
+    }
+
+    fn messages(records: &[Arc<OwnedRecord>]) -> Vec<&str> {
+        records.iter().map(|r| r.message.as_str()).collect()
+    }
+
+    #[test]
+    fn test_ring_evicts_oldest() {
+        let output = MemoryOutput::new(3);
+        for message in ["a", "b", "c", "d"] {
+            write(
+                &output,
+                "2025-09-07T10:30:00Z",
+                LogLevel::Info,
+                "app",
+                message,
+            );
+        }
+        assert_eq!(messages(&output.snapshot()), ["b", "c", "d"]);
+        assert_eq!(output.evicted(), 1);
+
+        assert_eq!(messages(&output.drain()), ["b", "c", "d"]);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_byte_limit() {
+        let output = MemoryOutput::new(100);
+        write(&output, "2025-09-07T10:30:00Z", LogLevel::Info, "app", "x");
+        let size = output.snapshot()[0].size();
+        let output = output.with_max_bytes(size * 2);
+
+        write(&output, "2025-09-07T10:30:00Z", LogLevel::Info, "app", "y");
+        write(&output, "2025-09-07T10:30:00Z", LogLevel::Info, "app", "z");
+        assert_eq!(messages(&output.snapshot()), ["y", "z"]);
+    }
+
+    #[test]
+    fn test_query() {
+        let output = MemoryOutput::new(100);
+        write(&output, "2025-09-07T10:00:00Z", LogLevel::Debug, "db", "a");
+        write(&output, "2025-09-07T10:10:00Z", LogLevel::Error, "api", "b");
+        write(
+            &output,
+            "2025-09-07T10:20:00Z",
+            LogLevel::Warning,
+            "api",
+            "c",
+        );
+        write(
+            &output,
+            "2025-09-07T10:30:00Z",
+            LogLevel::Critical,
+            "db",
+            "d",
+        );
+
+        let query = MemoryQuery::new().with_min_level(LogLevel::Warning);
+        assert_eq!(messages(&output.query(&query)), ["b", "c", "d"]);
+        let query = query.with_limit(2);
+        assert_eq!(messages(&output.query(&query)), ["c", "d"]);
+        let query = MemoryQuery::new().with_logger("db");
+        assert_eq!(messages(&output.query(&query)), ["a", "d"]);
+
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        let query = MemoryQuery::new()
+            .with_time_range(at("2025-09-07T10:10:00Z"), at("2025-09-07T10:30:00Z"));
+        assert_eq!(messages(&output.query(&query)), ["b", "c"]);
+
+        let query = MemoryQuery::new()
+            .with_field("request_id", "req-c")
+            .with_field("user_id", "42");
+        assert_eq!(messages(&output.query(&query)), ["c"]);
+    }
+
+    #[test]
+    fn test_write_bytes() {
+        let output = MemoryOutput::new(10);
+        output
+            .write_bytes(b"{\"timestamp\":\"2025-09-07T10:30:00Z\",\"level\":\"ERROR\",\"logger\":\"api\",\"message\":\"boom\",\"code\":500}\n")
+            .unwrap();
+
+        let records = output.snapshot();
+        assert_eq!(records[0].level, LogLevel::Error);
+        assert_eq!(records[0].logger, "api");
+        assert_eq!(records[0].field("code"), Some("500"));
+        let json = serde_json::to_value(&*records[0]).unwrap();
+        assert_eq!(json["message"], "boom");
+    }
+}
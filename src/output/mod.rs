@@ -1,7 +1,7 @@
 //! Output destination management for log messages.
 //!
-//! Provides various output destinations including console, file, rotating files,
-//! and multi-output routing. All destinations implement the [`OutputDestination`] trait.
+//! Provides various output destinations including console, file, size- and time-rotating files,
+//! syslog, GELF, Fluentd, network collectors, in-memory ring buffers, background writers, disk-backed spill queues, failover, and multi-output routing. All destinations implement the [`OutputDestination`] trait.
 //!
 //! # Examples
 //!
@@ -17,32 +17,88 @@
 //!     .add_output(file);
 //! ```
 
+pub mod background;
 pub mod buffered;
+mod chunk_queue;
 pub mod console;
+pub mod durability;
+pub mod failover;
 pub mod file;
+pub mod format;
+pub mod gelf;
+pub mod json;
+pub mod memory;
 pub mod multi;
+pub mod network;
+pub mod pretty;
+pub mod rotation;
+pub mod spill;
+pub mod syslog;
 
 #[cfg(feature = "async")]
 pub mod r#async;
 
+#[cfg(feature = "fluentd")]
+pub mod fluentd;
+
+#[cfg(feature = "http")]
+pub mod http;
+
+#[cfg(all(target_os = "linux", feature = "journald"))]
+pub mod journald;
+
+#[cfg(feature = "otlp")]
+pub mod otlp;
+
+pub use background::{BackgroundOutput, OverflowPolicy};
 pub use buffered::BufferedOutput;
-pub use console::ConsoleOutput;
+pub use console::{ColorMode, ColorTheme, ConsoleFormat, ConsoleOutput, ConsoleTarget};
+pub use durability::SyncPolicy;
+pub use failover::FailoverOutput;
 pub use file::{FileOutput, RotatingFileOutput};
-pub use multi::MultiOutput;
+pub use format::TextRecord;
+pub use gelf::{GelfCompression, GelfOutput, GelfTransport};
+pub use json::EncodedRecord;
+pub use memory::{MemoryOutput, MemoryQuery};
+pub use multi::{MultiOutput, Route};
+pub use network::{NetworkOutput, NetworkTransport};
+pub use pretty::{FieldLayout, PrettyStyle, TimestampStyle};
+pub use rotation::{Compression, RotationSchedule, RotationTimeZone};
+pub use spill::SpillOutput;
+pub use syslog::{Facility, SyslogFormat, SyslogOutput, SyslogTransport};
 
 #[cfg(feature = "async")]
 pub use r#async::AsyncOutput;
 
+#[cfg(feature = "fluentd")]
+pub use fluentd::{FluentdOutput, FluentdTransport};
+
+#[cfg(feature = "http")]
+pub use http::{HttpFormat, HttpOutput};
+
+#[cfg(all(target_os = "linux", feature = "journald"))]
+pub use journald::JournaldOutput;
+
+#[cfg(feature = "otlp")]
+pub use otlp::{OtlpOutput, OtlpProtocol, OtlpSpanExporter};
+
 use crate::level::LogLevel;
 use serde::ser::{SerializeMap, Serializer};
 use std::collections::HashMap;
 use std::io;
+use std::panic::Location;
+use std::sync::Arc;
 
 /// A zero-allocation log record passed by reference through the output pipeline.
 ///
 /// All fields are borrowed from the caller's stack frame, avoiding heap allocation
 /// for the record itself. Structured fields in `data` are also borrowed slices.
+///
+/// The struct is `#[non_exhaustive]` so that fields can be added without
+/// breaking custom outputs; outside this crate, create records with
+/// [`LogRecord::new`] and the `with_*` methods instead of a struct literal.
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct LogRecord<'a> {
     pub timestamp: &'a str,
     pub level: LogLevel,
@@ -50,6 +106,44 @@ pub struct LogRecord<'a> {
     pub message: &'a str,
     pub context: &'a HashMap<String, String>,
     pub data: Option<&'a [(&'a str, &'a str)]>,
+    /// Source location of the logging call, when known.
+    ///
+    /// Not part of the serialized JSON record; outputs with a native notion of
+    /// source location (such as journald) use it directly.
+    pub location: Option<&'static Location<'static>>,
+}
+
+impl<'a> LogRecord<'a> {
+    /// Creates a record without data fields or source location.
+    pub fn new(
+        timestamp: &'a str,
+        level: LogLevel,
+        logger: &'a str,
+        message: &'a str,
+        context: &'a HashMap<String, String>,
+    ) -> Self {
+        Self {
+            timestamp,
+            level,
+            logger,
+            message,
+            context,
+            data: None,
+            location: None,
+        }
+    }
+
+    /// Sets the structured data fields.
+    pub fn with_data(mut self, data: &'a [(&'a str, &'a str)]) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// Sets the source location of the logging call.
+    pub fn with_location(mut self, location: &'static Location<'static>) -> Self {
+        self.location = Some(location);
+        self
+    }
 }
 
 impl<'a> serde::Serialize for LogRecord<'a> {
@@ -74,6 +168,125 @@ impl<'a> serde::Serialize for LogRecord<'a> {
     }
 }
 
+/// An owned copy of a [`LogRecord`], for outputs that keep records after
+/// `write` returns.
+///
+/// Serializes to the same JSON object as the record it was copied from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OwnedRecord {
+    pub timestamp: String,
+    pub level: LogLevel,
+    pub logger: String,
+    pub message: String,
+    pub context: HashMap<String, String>,
+    pub data: Vec<(String, String)>,
+    pub location: Option<&'static Location<'static>>,
+}
+
+impl OwnedRecord {
+    /// Copies all fields of `record`.
+    pub fn from_record(record: &LogRecord<'_>) -> Self {
+        Self {
+            timestamp: record.timestamp.to_owned(),
+            level: record.level,
+            logger: record.logger.to_owned(),
+            message: record.message.to_owned(),
+            context: record.context.clone(),
+            data: record
+                .data
+                .unwrap_or_default()
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            location: record.location,
+        }
+    }
+
+    /// Parses a pre-serialized JSON record as passed to
+    /// [`OutputDestination::write_bytes`].
+    ///
+    /// Fields other than `timestamp`, `level`, `logger` and `message` become
+    /// data; non-string values are kept as their JSON text.
+    pub fn from_json(bytes: &[u8]) -> io::Result<Self> {
+        let fields: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_slice(bytes.trim_ascii_end())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut record = Self {
+            timestamp: String::new(),
+            level: LogLevel::Info,
+            logger: String::new(),
+            message: String::new(),
+            context: HashMap::new(),
+            data: Vec::new(),
+            location: None,
+        };
+        for (key, value) in fields {
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            match key.as_str() {
+                "timestamp" => record.timestamp = value,
+                "level" => record.level = value.parse().unwrap_or(LogLevel::Info),
+                "logger" => record.logger = value,
+                "message" => record.message = value,
+                _ => record.data.push((key, value)),
+            }
+        }
+        Ok(record)
+    }
+
+    /// Calls `f` with a borrowed [`LogRecord`] view of this record.
+    pub fn with_record<R>(&self, f: impl FnOnce(&LogRecord<'_>) -> R) -> R {
+        let data: Vec<(&str, &str)> = self
+            .data
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        f(&LogRecord {
+            timestamp: &self.timestamp,
+            level: self.level,
+            logger: &self.logger,
+            message: &self.message,
+            context: &self.context,
+            data: if data.is_empty() { None } else { Some(&data) },
+            location: self.location,
+        })
+    }
+
+    /// Returns the value of a data field, or else of a context entry.
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.data
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .or_else(|| self.context.get(key).map(String::as_str))
+    }
+
+    /// Approximate heap size of the record in bytes.
+    pub fn size(&self) -> usize {
+        self.timestamp.len()
+            + self.logger.len()
+            + self.message.len()
+            + self
+                .context
+                .iter()
+                .chain(self.data.iter().map(|(k, v)| (k, v)))
+                .map(|(k, v)| k.len() + v.len())
+                .sum::<usize>()
+    }
+}
+
+impl serde::Serialize for OwnedRecord {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.with_record(|record| record.serialize(serializer))
+    }
+}
+
 /// Trait for log message output destinations.
 ///
 /// Implement this trait to create custom output destinations.
@@ -94,4 +307,113 @@ pub trait OutputDestination: Send + Sync {
         let _ = bytes;
         Ok(())
     }
+
+    /// Writes a record that may already have been encoded as JSON.
+    ///
+    /// Routers such as [`MultiOutput`] call this instead of
+    /// [`write`](Self::write) so that every destination writing JSON lines
+    /// shares one encoding of the record. Such destinations should override it
+    /// and use [`EncodedRecord::json`]; the default calls `write`.
+    fn write_encoded(&self, encoded: &EncodedRecord<'_, '_>) -> io::Result<()> {
+        self.write(encoded.record())
+    }
+
+    /// Closes and reopens any files held by this destination.
+    ///
+    /// Called after external log rotation (e.g. logrotate) so that subsequent
+    /// records go to a fresh file at the configured path. The default
+    /// implementation does nothing.
+    fn reopen(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Short name identifying the destination in error reports.
+    ///
+    /// Defaults to the name of the implementing type.
+    fn name(&self) -> &str {
+        let name = std::any::type_name::<Self>();
+        let name = name.split('<').next().unwrap_or(name);
+        name.rsplit("::").next().unwrap_or(name)
+    }
+}
+
+/// Callback for errors that outputs cannot return to the logging call, such
+/// as a failing destination inside a [`MultiOutput`] or a write on a
+/// background thread.
+///
+/// It receives the [`name`](OutputDestination::name) of the destination and
+/// the error. Without a handler, errors are printed to stderr.
+#[derive(Clone)]
+pub struct ErrorHandler(Arc<ErrorCallback>);
+
+type ErrorCallback = dyn Fn(&str, &io::Error) + Send + Sync;
+
+impl ErrorHandler {
+    /// Creates a handler calling `f` with the destination name and the error.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&str, &io::Error) + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    /// Reports an error of the named destination.
+    pub fn report(&self, destination: &str, error: &io::Error) {
+        (self.0)(destination, error)
+    }
+}
+
+impl std::fmt::Debug for ErrorHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ErrorHandler(..)")
+    }
+}
+
+/// Reports an error to `handler`, or prints it to stderr if there is none.
+pub(crate) fn report_error(handler: Option<&ErrorHandler>, destination: &str, error: &io::Error) {
+    match handler {
+        Some(handler) => handler.report(destination, error),
+        None => eprintln!("[Telelog] {} error: {}", destination, error),
+    }
+}
+
+/// Shared outputs forward to the destination they point to, so one destination
+/// can be registered with several routers or loggers.
+impl<T: OutputDestination + ?Sized> OutputDestination for Arc<T> {
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        (**self).write(record)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        (**self).flush()
+    }
+
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        (**self).write_bytes(bytes)
+    }
+
+    fn write_encoded(&self, encoded: &EncodedRecord<'_, '_>) -> io::Result<()> {
+        (**self).write_encoded(encoded)
+    }
+
+    fn reopen(&self) -> io::Result<()> {
+        (**self).reopen()
+    }
+
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+}
+
+/// Returns the host name of this machine, or `None` if it cannot be determined.
+///
+/// Used by network outputs whose wire formats carry a host field.
+pub(crate) fn hostname() -> Option<String> {
+    let from_env = std::env::var("HOSTNAME").ok();
+    from_env
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
 }
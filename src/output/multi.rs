@@ -1,29 +1,256 @@
 //! Multi-output router that fans log records out to multiple destinations.
+//!
+//! Each destination can be given a [`Route`] deciding which records it
+//! receives, so that for example critical records also go to a pager while
+//! debug noise stays in a file:
+//!
+//! ```no_run
+//! use telelog::output::{ConsoleOutput, FileOutput, MultiOutput, Route};
+//! use telelog::LogLevel;
+//!
+//! let multi = MultiOutput::new()
+//!     .add_output(Box::new(FileOutput::new("app.log", true).unwrap()))
+//!     .add_output_with(
+//!         Box::new(ConsoleOutput::new(true)),
+//!         Route::min_level(LogLevel::Critical).or(Route::field("alert", "true")),
+//!     )
+//!     .add_output_with(
+//!         Box::new(FileOutput::new("db.log", true).unwrap()),
+//!         Route::logger("db.*") & !Route::min_level(LogLevel::Error),
+//!     );
+//! ```
 
-use crate::output::{LogRecord, OutputDestination};
+use crate::level::LogLevel;
+use crate::output::{
+    report_error, EncodedRecord, ErrorHandler, LogRecord, OutputDestination, OwnedRecord,
+};
+use arc_swap::ArcSwap;
 use std::io;
+use std::sync::Arc;
 
-/// Routes each log record to all registered output destinations.
+type Predicate = Arc<dyn Fn(&LogRecord<'_>) -> bool + Send + Sync>;
+
+/// Decides which records a destination of a [`MultiOutput`] receives.
+///
+/// Routes combine with [`and`](Route::and), [`or`](Route::or) and `!`, or the
+/// equivalent `&` and `|` operators.
+#[derive(Clone)]
+pub enum Route {
+    /// Every record.
+    All,
+    /// Records at this level or above.
+    MinLevel(LogLevel),
+    /// Records whose logger name matches a glob, where `*` matches any run of
+    /// characters and `?` any single character.
+    Logger(String),
+    /// Records whose data field, or else context entry, equals the value.
+    Field(String, String),
+    /// Records for which the function returns `true`.
+    Predicate(Predicate),
+    /// Records matching every route.
+    And(Vec<Route>),
+    /// Records matching at least one route.
+    Or(Vec<Route>),
+    /// Records not matching the route.
+    Not(Box<Route>),
+}
+
+impl Route {
+    /// Matches records at `level` or above.
+    pub fn min_level(level: LogLevel) -> Self {
+        Route::MinLevel(level)
+    }
+
+    /// Matches records whose logger name matches `pattern`.
+    pub fn logger(pattern: &str) -> Self {
+        Route::Logger(pattern.to_string())
+    }
+
+    /// Matches records whose data field or context entry `key` equals `value`.
+    pub fn field(key: &str, value: &str) -> Self {
+        Route::Field(key.to_string(), value.to_string())
+    }
+
+    /// Matches records for which `f` returns `true`.
+    pub fn predicate<F>(f: F) -> Self
+    where
+        F: Fn(&LogRecord<'_>) -> bool + Send + Sync + 'static,
+    {
+        Route::Predicate(Arc::new(f))
+    }
+
+    /// Matches records matching both routes.
+    pub fn and(self, other: Route) -> Self {
+        match self {
+            Route::And(mut routes) => {
+                routes.push(other);
+                Route::And(routes)
+            }
+            route => Route::And(vec![route, other]),
+        }
+    }
+
+    /// Matches records matching either route.
+    pub fn or(self, other: Route) -> Self {
+        match self {
+            Route::Or(mut routes) => {
+                routes.push(other);
+                Route::Or(routes)
+            }
+            route => Route::Or(vec![route, other]),
+        }
+    }
+
+    /// Returns whether `record` should be sent along this route.
+    pub fn matches(&self, record: &LogRecord<'_>) -> bool {
+        match self {
+            Route::All => true,
+            Route::MinLevel(level) => record.level >= *level,
+            Route::Logger(pattern) => glob_match(pattern, record.logger),
+            Route::Field(key, value) => field(record, key) == Some(value.as_str()),
+            Route::Predicate(f) => f(record),
+            Route::And(routes) => routes.iter().all(|route| route.matches(record)),
+            Route::Or(routes) => routes.iter().any(|route| route.matches(record)),
+            Route::Not(route) => !route.matches(record),
+        }
+    }
+}
+
+impl std::ops::BitAnd for Route {
+    type Output = Route;
+
+    fn bitand(self, other: Route) -> Route {
+        self.and(other)
+    }
+}
+
+impl std::ops::BitOr for Route {
+    type Output = Route;
+
+    fn bitor(self, other: Route) -> Route {
+        self.or(other)
+    }
+}
+
+impl std::ops::Not for Route {
+    type Output = Route;
+
+    fn not(self) -> Route {
+        match self {
+            Route::Not(route) => *route,
+            route => Route::Not(Box::new(route)),
+        }
+    }
+}
+
+impl std::fmt::Debug for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Route::All => f.write_str("All"),
+            Route::MinLevel(level) => f.debug_tuple("MinLevel").field(level).finish(),
+            Route::Logger(pattern) => f.debug_tuple("Logger").field(pattern).finish(),
+            Route::Field(key, value) => f.debug_tuple("Field").field(key).field(value).finish(),
+            Route::Predicate(_) => f.write_str("Predicate(..)"),
+            Route::And(routes) => f.debug_tuple("And").field(routes).finish(),
+            Route::Or(routes) => f.debug_tuple("Or").field(routes).finish(),
+            Route::Not(route) => f.debug_tuple("Not").field(route).finish(),
+        }
+    }
+}
+
+/// Returns the value of a data field, or else of a context entry.
+fn field<'a>(record: &LogRecord<'a>, key: &str) -> Option<&'a str> {
+    record
+        .data
+        .unwrap_or_default()
+        .iter()
+        .rev()
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| *v)
+        .or_else(|| record.context.get(key).map(String::as_str))
+}
+
+/// Matches `text` against a glob supporting `*` and `?`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it was tried at.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Routes each log record to the registered output destinations whose
+/// [`Route`] it matches.
 ///
-/// Errors from individual outputs are printed to stderr but do not prevent
-/// writing to the remaining destinations.
+/// Errors from individual outputs are passed to the error handler, or printed
+/// to stderr, but do not prevent writing to the remaining destinations.
 pub struct MultiOutput {
-    outputs: Vec<Box<dyn OutputDestination>>,
+    outputs: ArcSwap<Vec<(Arc<dyn OutputDestination>, Route)>>,
+    error_handler: Option<ErrorHandler>,
 }
 
 impl MultiOutput {
     /// Creates an empty multi-output router.
     pub fn new() -> Self {
         Self {
-            outputs: Vec::new(),
+            outputs: ArcSwap::from_pointee(Vec::new()),
+            error_handler: None,
         }
     }
 
-    /// Appends an output destination to the router.
-    pub fn add_output(mut self, output: Box<dyn OutputDestination>) -> Self {
-        self.outputs.push(output);
+    /// Appends an output destination receiving every record.
+    pub fn add_output(self, output: Box<dyn OutputDestination>) -> Self {
+        self.add_output_with(output, Route::All)
+    }
+
+    /// Appends an output destination receiving the records matching `route`.
+    pub fn add_output_with(self, output: Box<dyn OutputDestination>, route: Route) -> Self {
+        self.push_output(output, route);
+        self
+    }
+
+    /// Appends an output destination receiving the records matching `route`
+    /// while the router is in use; records already being written are not
+    /// affected.
+    pub fn push_output(&self, output: Box<dyn OutputDestination>, route: Route) {
+        let output: Arc<dyn OutputDestination> = Arc::from(output);
+        self.outputs.rcu(|outputs| {
+            let mut outputs = Vec::clone(outputs);
+            outputs.push((Arc::clone(&output), route.clone()));
+            outputs
+        });
+    }
+
+    /// Reports errors of individual outputs to `handler` instead of stderr.
+    pub fn with_error_handler(mut self, handler: ErrorHandler) -> Self {
+        self.error_handler = Some(handler);
         self
     }
+
+    fn report(&self, output: &dyn OutputDestination, error: &io::Error) {
+        report_error(self.error_handler.as_ref(), output.name(), error);
+    }
 }
 
 impl Default for MultiOutput {
@@ -33,28 +260,61 @@ impl Default for MultiOutput {
 }
 
 impl OutputDestination for MultiOutput {
+    /// Writes the record to every matching output, encoding it as JSON at
+    /// most once for all of them.
     fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
-        for output in &self.outputs {
-            if let Err(e) = output.write(record) {
-                eprintln!("Output error: {}", e);
+        EncodedRecord::scope(record, |encoded| self.write_encoded(encoded))
+    }
+
+    fn write_encoded(&self, encoded: &EncodedRecord<'_, '_>) -> io::Result<()> {
+        for (output, route) in self.outputs.load().iter() {
+            if !route.matches(encoded.record()) {
+                continue;
+            }
+            if let Err(e) = output.write_encoded(encoded) {
+                self.report(output.as_ref(), &e);
             }
         }
         Ok(())
     }
 
     fn flush(&self) -> io::Result<()> {
-        for output in &self.outputs {
+        for (output, _) in self.outputs.load().iter() {
             if let Err(e) = output.flush() {
-                eprintln!("Flush error: {}", e);
+                self.report(output.as_ref(), &e);
             }
         }
         Ok(())
     }
 
     fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
-        for output in &self.outputs {
+        // Routes need the record's fields; parse only if some output filters.
+        let outputs = self.outputs.load();
+        let record = if outputs
+            .iter()
+            .any(|(_, route)| !matches!(route, Route::All))
+        {
+            Some(OwnedRecord::from_json(bytes)?)
+        } else {
+            None
+        };
+        for (output, route) in outputs.iter() {
+            if let Some(record) = &record {
+                if !record.with_record(|r| route.matches(r)) {
+                    continue;
+                }
+            }
             if let Err(e) = output.write_bytes(bytes) {
-                eprintln!("Output error: {}", e);
+                self.report(output.as_ref(), &e);
+            }
+        }
+        Ok(())
+    }
+
+    fn reopen(&self) -> io::Result<()> {
+        for (output, _) in self.outputs.load().iter() {
+            if let Err(e) = output.reopen() {
+                self.report(output.as_ref(), &e);
             }
         }
         Ok(())
@@ -65,7 +325,7 @@ impl OutputDestination for MultiOutput {
 mod tests {
     use super::*;
     use crate::level::LogLevel;
-    use crate::output::{console::ConsoleOutput, file::FileOutput};
+    use crate::output::{console::ConsoleOutput, file::FileOutput, MemoryOutput};
     use std::collections::HashMap;
     use tempfile::NamedTempFile;
 
@@ -84,8 +344,132 @@ mod tests {
                 message: "Test message",
                 context: &HashMap::new(),
                 data: None,
+                location: None,
             })
             .is_ok());
         assert!(multi.flush().is_ok());
     }
+
+    fn record<'a>(
+        level: LogLevel,
+        logger: &'a str,
+        context: &'a HashMap<String, String>,
+        data: &'a [(&'a str, &'a str)],
+    ) -> LogRecord<'a> {
+        LogRecord {
+            timestamp: "2025-09-07T10:30:00Z",
+            level,
+            logger,
+            message: "routed",
+            context,
+            data: Some(data),
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_routes() {
+        let ctx: HashMap<String, String> = [("tenant".to_string(), "acme".to_string())].into();
+        let debug_db = record(LogLevel::Debug, "db.pool", &ctx, &[("alert", "true")]);
+        let error_api = record(LogLevel::Error, "api", &ctx, &[]);
+
+        assert!(Route::min_level(LogLevel::Error).matches(&error_api));
+        assert!(!Route::min_level(LogLevel::Error).matches(&debug_db));
+        assert!(Route::logger("db.*").matches(&debug_db));
+        assert!(!Route::logger("db.*").matches(&error_api));
+        assert!(Route::field("alert", "true").matches(&debug_db));
+        assert!(Route::field("tenant", "acme").matches(&error_api));
+
+        let route = Route::logger("db.*") & !Route::min_level(LogLevel::Error);
+        assert!(route.matches(&debug_db));
+        let route = Route::min_level(LogLevel::Critical) | Route::predicate(|r| r.logger == "api");
+        assert!(route.matches(&error_api));
+        assert!(!route.matches(&debug_db));
+    }
+
+    #[test]
+    fn test_add_output_with_routes_records() {
+        let all = Arc::new(MemoryOutput::new(10));
+        let errors = Arc::new(MemoryOutput::new(10));
+        let multi = MultiOutput::new()
+            .add_output(Box::new(Arc::clone(&all)))
+            .add_output_with(
+                Box::new(Arc::clone(&errors)),
+                Route::min_level(LogLevel::Error),
+            );
+
+        let ctx = HashMap::new();
+        multi
+            .write(&record(LogLevel::Debug, "app", &ctx, &[]))
+            .unwrap();
+        multi
+            .write(&record(LogLevel::Error, "app", &ctx, &[]))
+            .unwrap();
+        multi
+            .write_bytes(b"{\"level\":\"CRITICAL\",\"logger\":\"app\",\"message\":\"m\"}\n")
+            .unwrap();
+        multi
+            .write_bytes(b"{\"level\":\"INFO\",\"logger\":\"app\",\"message\":\"m\"}\n")
+            .unwrap();
+
+        assert_eq!(all.len(), 4);
+        let levels: Vec<LogLevel> = errors.snapshot().iter().map(|r| r.level).collect();
+        assert_eq!(levels, [LogLevel::Error, LogLevel::Critical]);
+    }
+
+    #[test]
+    fn test_json_encoded_once() {
+        struct Recorder(Arc<parking_lot::Mutex<Vec<usize>>>);
+
+        impl OutputDestination for Recorder {
+            fn write(&self, _: &LogRecord<'_>) -> io::Result<()> {
+                unreachable!("routers pass the shared encoding")
+            }
+
+            fn flush(&self) -> io::Result<()> {
+                Ok(())
+            }
+
+            fn write_encoded(&self, encoded: &EncodedRecord<'_, '_>) -> io::Result<()> {
+                self.0.lock().push(encoded.json().as_ptr() as usize);
+                Ok(())
+            }
+        }
+
+        let seen = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let temp_file = NamedTempFile::new().unwrap();
+        let multi = MultiOutput::new()
+            .add_output(Box::new(Recorder(Arc::clone(&seen))))
+            .add_output(Box::new(FileOutput::new(temp_file.path(), true).unwrap()))
+            .add_output(Box::new(Recorder(Arc::clone(&seen))));
+        let record = LogRecord {
+            timestamp: "2025-09-07T10:30:00Z",
+            level: LogLevel::Info,
+            logger: "app",
+            message: "shared",
+            context: &HashMap::new(),
+            data: Some(&[("k", "v")]),
+            location: None,
+        };
+        multi.write(&record).unwrap();
+        multi.flush().unwrap();
+
+        let seen = seen.lock();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0], seen[1]);
+        assert_eq!(
+            std::fs::read_to_string(temp_file.path()).unwrap(),
+            serde_json::to_string(&record).unwrap() + "\n"
+        );
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("db.*", "db.pool"));
+        assert!(glob_match("*.pool", "db.pool"));
+        assert!(glob_match("a?c*z", "abcxyz"));
+        assert!(!glob_match("db.*", "api"));
+        assert!(!glob_match("a?c", "ac"));
+    }
 }
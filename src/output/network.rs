@@ -0,0 +1,528 @@
+//! Network output streaming newline-delimited JSON to a remote collector.
+//!
+//! [`NetworkOutput`] serializes each record as one JSON line and sends it over
+//! TCP, UDP (one datagram per record), a Unix stream socket or, with the `tls`
+//! feature, TLS over TCP. It is meant to sit next to file outputs behind a
+//! [`MultiOutput`](crate::output::MultiOutput), so a logger can write to disk
+//! and to a collector such as Vector, Fluent Bit or Logstash at the same time.
+//!
+//! While the collector is unreachable, records are kept in a bounded in-memory
+//! queue and reconnection is attempted with exponential backoff. When the queue
+//! is full the oldest records are dropped; the number dropped is reported on
+//! stderr once the connection is re-established. A UDP record too large to
+//! fit in a datagram is dropped and reported as a write error.
+//!
+//! Connecting and sending happen on the logging thread. Wrap the output in a
+//! [`BufferedOutput`](crate::output::BufferedOutput) or an `AsyncOutput` if a
+//! slow collector must not delay the application.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use telelog::output::{FileOutput, MultiOutput, NetworkOutput, NetworkTransport};
+//! use std::time::Duration;
+//!
+//! let collector = NetworkOutput::new(NetworkTransport::Tcp("10.0.0.5:5170".parse().unwrap()))
+//!     .with_queue_capacity(10_000)
+//!     .with_backoff(Duration::from_millis(200), Duration::from_secs(60));
+//!
+//! let multi = MultiOutput::new()
+//!     .add_output(Box::new(FileOutput::new("app.log", true).unwrap()))
+//!     .add_output(Box::new(collector));
+//! ```
+
+use crate::output::{report_error, EncodedRecord, ErrorHandler, LogRecord, OutputDestination};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::PathBuf;
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const DEFAULT_QUEUE_CAPACITY: usize = 1000;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where a [`NetworkOutput`] sends records.
+#[derive(Debug, Clone)]
+pub enum NetworkTransport {
+    /// A TCP stream; records are newline-delimited.
+    Tcp(SocketAddr),
+    /// UDP; each record is sent as one datagram including its trailing newline.
+    Udp(SocketAddr),
+    /// A Unix stream socket; records are newline-delimited.
+    #[cfg(unix)]
+    Unix(PathBuf),
+    /// TLS over TCP (requires `tls` feature). The server certificate is
+    /// verified against `server_name`.
+    #[cfg(feature = "tls")]
+    Tls {
+        addr: SocketAddr,
+        server_name: String,
+    },
+}
+
+enum Connection {
+    Tcp(TcpStream),
+    Udp(UdpSocket),
+    #[cfg(unix)]
+    Unix(UnixStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+}
+
+impl Connection {
+    fn open(output: &NetworkOutput) -> io::Result<Self> {
+        let timeout = output.timeout;
+        match &output.transport {
+            NetworkTransport::Tcp(addr) => Ok(Connection::Tcp(connect_tcp(addr, timeout)?)),
+            NetworkTransport::Udp(addr) => {
+                let local: SocketAddr = if addr.is_ipv4() {
+                    ([0, 0, 0, 0], 0).into()
+                } else {
+                    ([0u16; 8], 0).into()
+                };
+                let socket = UdpSocket::bind(local)?;
+                socket.connect(addr)?;
+                Ok(Connection::Udp(socket))
+            }
+            #[cfg(unix)]
+            NetworkTransport::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_write_timeout(Some(timeout))?;
+                Ok(Connection::Unix(stream))
+            }
+            #[cfg(feature = "tls")]
+            NetworkTransport::Tls { addr, server_name } => {
+                let mut tcp = connect_tcp(addr, timeout)?;
+                // Bound the handshake so an unresponsive server cannot block
+                // the logging thread indefinitely.
+                tcp.set_read_timeout(Some(timeout))?;
+                let name = rustls::pki_types::ServerName::try_from(server_name.clone())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                let config = output.tls_config.clone().unwrap_or_else(default_tls_config);
+                let mut conn = rustls::ClientConnection::new(config, name)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                // Complete the handshake now so certificate errors surface as
+                // connection failures rather than on the first record.
+                while conn.is_handshaking() {
+                    conn.complete_io(&mut tcp)?;
+                }
+                Ok(Connection::Tls(Box::new(rustls::StreamOwned::new(
+                    conn, tcp,
+                ))))
+            }
+        }
+    }
+
+    fn send(&mut self, line: &[u8]) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.write_all(line),
+            Connection::Udp(socket) => socket.send(line).map(|_| ()),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.write_all(line),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => {
+                stream.write_all(line)?;
+                stream.flush()
+            }
+        }
+    }
+}
+
+fn connect_tcp(addr: &SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect_timeout(addr, timeout)?;
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(timeout))?;
+    Ok(stream)
+}
+
+/// Returns true if `e` means a datagram was larger than the transport allows.
+fn is_oversized(e: &io::Error) -> bool {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const EMSGSIZE: i32 = 90;
+    #[cfg(windows)]
+    const EMSGSIZE: i32 = 10040;
+    #[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+    const EMSGSIZE: i32 = 40;
+    e.raw_os_error() == Some(EMSGSIZE)
+}
+
+#[cfg(feature = "tls")]
+fn default_tls_config() -> Arc<rustls::ClientConfig> {
+    let roots = rustls::RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    Arc::new(
+        rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .expect("ring provider supports the default protocol versions")
+        .with_root_certificates(roots)
+        .with_no_client_auth(),
+    )
+}
+
+struct State {
+    connection: Option<Connection>,
+    queue: VecDeque<Vec<u8>>,
+    backoff: Duration,
+    next_attempt: Instant,
+    dropped: u64,
+}
+
+/// Streams log records as newline-delimited JSON to a TCP, UDP or Unix-socket
+/// collector, reconnecting with exponential backoff.
+///
+/// The connection is opened lazily by the first record. A failed connection
+/// attempt is reported as an error from `write`; the record itself stays
+/// queued and is sent once a later attempt succeeds.
+pub struct NetworkOutput {
+    transport: NetworkTransport,
+    state: Mutex<State>,
+    queue_capacity: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    timeout: Duration,
+    error_handler: Option<ErrorHandler>,
+    #[cfg(feature = "tls")]
+    tls_config: Option<Arc<rustls::ClientConfig>>,
+}
+
+impl NetworkOutput {
+    /// Creates a network output for `transport`.
+    pub fn new(transport: NetworkTransport) -> Self {
+        Self {
+            transport,
+            state: Mutex::new(State {
+                connection: None,
+                queue: VecDeque::new(),
+                backoff: DEFAULT_INITIAL_BACKOFF,
+                next_attempt: Instant::now(),
+                dropped: 0,
+            }),
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            timeout: DEFAULT_TIMEOUT,
+            error_handler: None,
+            #[cfg(feature = "tls")]
+            tls_config: None,
+        }
+    }
+
+    /// Sets how many records are kept while disconnected (1000 by default).
+    ///
+    /// When the queue is full the oldest record is dropped.
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets the delay before the first reconnection attempt and the cap it
+    /// doubles up to (100ms and 30s by default).
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self.state.get_mut().backoff = initial;
+        self
+    }
+
+    /// Sets the connect and write timeout for stream transports (5s by default).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Reports records dropped while disconnected to `handler` instead of
+    /// stderr.
+    pub fn with_error_handler(mut self, handler: ErrorHandler) -> Self {
+        self.error_handler = Some(handler);
+        self
+    }
+
+    /// Uses a custom TLS client configuration, e.g. with a private CA or a
+    /// client certificate (requires `tls` feature).
+    ///
+    /// By default server certificates are verified against the Mozilla root
+    /// store bundled by `webpki-roots`.
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(mut self, config: Arc<rustls::ClientConfig>) -> Self {
+        self.tls_config = Some(config);
+        self
+    }
+
+    /// Returns the number of records waiting to be sent.
+    pub fn queued(&self) -> usize {
+        self.state.lock().queue.len()
+    }
+
+    fn enqueue(&self, line: Vec<u8>) -> io::Result<()> {
+        let mut state = self.state.lock();
+        if state.queue.len() >= self.queue_capacity {
+            state.queue.pop_front();
+            state.dropped += 1;
+        }
+        state.queue.push_back(line);
+        self.drain(&mut state)
+    }
+
+    /// Sends queued records, connecting first if the backoff delay has passed.
+    fn drain(&self, state: &mut State) -> io::Result<()> {
+        if state.connection.is_none() {
+            if Instant::now() < state.next_attempt {
+                return Ok(());
+            }
+            match Connection::open(self) {
+                Ok(connection) => {
+                    state.connection = Some(connection);
+                    state.backoff = self.initial_backoff;
+                    if state.dropped > 0 {
+                        let notice = io::Error::other(format!(
+                            "dropped {} records while disconnected",
+                            state.dropped
+                        ));
+                        report_error(self.error_handler.as_ref(), "NetworkOutput", &notice);
+                        state.dropped = 0;
+                    }
+                }
+                Err(e) => {
+                    self.schedule_retry(state);
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut result = Ok(());
+        while let Some(line) = state.queue.front() {
+            let sent = match state.connection.as_mut() {
+                Some(connection) => connection.send(line),
+                None => return result,
+            };
+            match sent {
+                Ok(()) => {}
+                // Retrying cannot help a datagram that is too large, so drop
+                // it rather than blocking the records behind it.
+                Err(e) if is_oversized(&e) => {
+                    result = Err(io::Error::new(
+                        e.kind(),
+                        format!("dropped record of {} bytes: {}", line.len(), e),
+                    ));
+                }
+                Err(e) => {
+                    state.connection = None;
+                    self.schedule_retry(state);
+                    return Err(e);
+                }
+            }
+            state.queue.pop_front();
+        }
+        result
+    }
+
+    fn schedule_retry(&self, state: &mut State) {
+        state.next_attempt = Instant::now() + state.backoff;
+        state.backoff = (state.backoff * 2).min(self.max_backoff);
+    }
+}
+
+impl OutputDestination for NetworkOutput {
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        EncodedRecord::scope(record, |encoded| self.write_encoded(encoded))
+    }
+
+    fn write_encoded(&self, encoded: &EncodedRecord<'_, '_>) -> io::Result<()> {
+        self.enqueue(encoded.json().to_vec())
+    }
+
+    /// Attempts to send any queued records.
+    fn flush(&self) -> io::Result<()> {
+        let mut state = self.state.lock();
+        self.drain(&mut state)
+    }
+
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        let mut line = bytes.to_vec();
+        if !line.ends_with(b"\n") {
+            line.push(b'\n');
+        }
+        self.enqueue(line)
+    }
+
+    /// Drops the current connection and reconnects on the next record without
+    /// waiting for the backoff delay.
+    fn reopen(&self) -> io::Result<()> {
+        let mut state = self.state.lock();
+        state.connection = None;
+        state.backoff = self.initial_backoff;
+        state.next_attempt = Instant::now();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::LogLevel;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    fn write(output: &NetworkOutput, message: &str) -> io::Result<()> {
+        let ctx = HashMap::new();
+        output.write(&LogRecord {
+            timestamp: "2025-09-07T10:30:00Z",
+            level: LogLevel::Info,
+            logger: "net",
+            message,
+            context: &ctx,
+            data: Some(&[("user_id", "42")]),
+            location: None,
+        })
+    }
+
+    fn read_messages(reader: &mut impl BufRead, n: usize) -> Vec<String> {
+        (0..n)
+            .map(|_| {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+                value["message"].as_str().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    /// Returns an address on which nothing is listening.
+    fn unused_addr() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_tcp_ndjson() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let output = NetworkOutput::new(NetworkTransport::Tcp(listener.local_addr().unwrap()));
+
+        write(&output, "first").unwrap();
+        write(&output, "second").unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["message"], "first");
+        assert_eq!(value["user_id"], "42");
+        assert_eq!(read_messages(&mut reader, 1), ["second"]);
+    }
+
+    #[test]
+    fn test_queues_and_reconnects() {
+        let addr = unused_addr();
+        let output = NetworkOutput::new(NetworkTransport::Tcp(addr))
+            .with_backoff(Duration::from_millis(20), Duration::from_millis(40));
+
+        assert!(write(&output, "queued-1").is_err());
+        // Within the backoff delay no connection is attempted.
+        write(&output, "queued-2").unwrap();
+        assert_eq!(output.queued(), 2);
+
+        let listener = TcpListener::bind(addr).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        write(&output, "live").unwrap();
+        assert_eq!(output.queued(), 0);
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        assert_eq!(
+            read_messages(&mut reader, 3),
+            ["queued-1", "queued-2", "live"]
+        );
+    }
+
+    #[test]
+    fn test_queue_drops_oldest_when_full() {
+        let seen = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let handler = {
+            let seen = std::sync::Arc::clone(&seen);
+            ErrorHandler::new(move |name, e| seen.lock().push(format!("{}: {}", name, e)))
+        };
+        let addr = unused_addr();
+        let output = NetworkOutput::new(NetworkTransport::Tcp(addr))
+            .with_queue_capacity(2)
+            .with_backoff(Duration::from_millis(10), Duration::from_millis(10))
+            .with_error_handler(handler);
+
+        for message in ["a", "b", "c"] {
+            let _ = write(&output, message);
+        }
+        assert_eq!(output.queued(), 2);
+
+        let listener = TcpListener::bind(addr).unwrap();
+        output.reopen().unwrap();
+        output.flush().unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        assert_eq!(read_messages(&mut BufReader::new(stream), 2), ["b", "c"]);
+        assert_eq!(
+            *seen.lock(),
+            ["NetworkOutput: dropped 1 records while disconnected"]
+        );
+    }
+
+    #[test]
+    fn test_udp_datagram_per_record() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let output = NetworkOutput::new(NetworkTransport::Udp(socket.local_addr().unwrap()));
+
+        write(&output, "over udp").unwrap();
+
+        let mut buf = [0u8; 1024];
+        let n = socket.recv(&mut buf).unwrap();
+        assert!(buf[..n].ends_with(b"\n"));
+        let value: serde_json::Value = serde_json::from_slice(&buf[..n]).unwrap();
+        assert_eq!(value["message"], "over udp");
+    }
+
+    #[test]
+    fn test_udp_drops_oversized_record() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let output = NetworkOutput::new(NetworkTransport::Udp(socket.local_addr().unwrap()));
+
+        assert!(write(&output, &"x".repeat(70_000)).is_err());
+        assert_eq!(output.queued(), 0);
+        write(&output, "after").unwrap();
+
+        let mut buf = [0u8; 1024];
+        let n = socket.recv(&mut buf).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&buf[..n]).unwrap();
+        assert_eq!(value["message"], "after");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("collector.sock");
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let output = NetworkOutput::new(NetworkTransport::Unix(path));
+
+        write(&output, "over unix").unwrap();
+        output
+            .write_bytes(b"{\"message\":\"pre-serialized\"}\n")
+            .unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        assert_eq!(
+            read_messages(&mut BufReader::new(stream), 2),
+            ["over unix", "pre-serialized"]
+        );
+    }
+}
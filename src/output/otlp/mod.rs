@@ -0,0 +1,484 @@
+//! OpenTelemetry OTLP log exporter (requires the `otlp` feature).
+//!
+//! [`OtlpOutput`] converts each [`LogRecord`] into an OTLP `LogRecord` and
+//! exports batches to an OpenTelemetry collector over OTLP/HTTP, as protobuf
+//! or JSON. Batching, retries and gzip compression work as for
+//! [`HttpOutput`](crate::output::HttpOutput).
+//!
+//! The mapping is:
+//!
+//! - the level becomes `SeverityNumber` (see [`LogLevel::otel_severity_number`])
+//!   and `SeverityText`, and the message becomes the body;
+//! - context and data fields become string attributes, and the call site
+//!   becomes `code.file.path` and `code.line.number`;
+//! - a `trace_id` (32 hex digits) or `span_id` (16 hex digits) field sets the
+//!   record's trace context instead of becoming an attribute;
+//! - the logger name becomes the instrumentation scope name;
+//! - the resource carries `service.name` (from [`OtlpOutput::with_service_name`]
+//!   or `OTEL_SERVICE_NAME`), `host.name` and the `telemetry.sdk.*` attributes.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use telelog::output::{OtlpOutput, OtlpProtocol};
+//!
+//! let otlp = OtlpOutput::new("http://localhost:4318/v1/logs")
+//!     .with_protocol(OtlpProtocol::HttpJson)
+//!     .with_service_name("billing")
+//!     .with_resource_attribute("deployment.environment", "prod");
+//! ```
+
+pub(crate) mod proto;
+mod trace;
+
+pub use trace::{OtlpSpanExporter, DEFAULT_TRACES_ENDPOINT};
+
+use crate::level::LogLevel;
+use crate::output::http::{timestamp_ns, BatchFormat, Entry, HttpOutput};
+use crate::output::{ErrorHandler, LogRecord, OutputDestination};
+use prost::Message;
+use std::collections::BTreeMap;
+use std::io;
+use std::panic::Location;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default OTLP/HTTP logs endpoint of a local collector.
+pub const DEFAULT_LOGS_ENDPOINT: &str = "http://localhost:4318/v1/logs";
+
+/// Encoding used for OTLP/HTTP requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OtlpProtocol {
+    /// Binary protobuf (`application/x-protobuf`), the OTLP default.
+    #[default]
+    HttpProtobuf,
+    /// The OTLP JSON mapping (`application/json`).
+    HttpJson,
+}
+
+/// Builds the resource shared by all exported records.
+pub(crate) fn default_resource(service_name: Option<&str>) -> Vec<proto::KeyValue> {
+    let service_name = service_name
+        .map(str::to_string)
+        .or_else(|| std::env::var("OTEL_SERVICE_NAME").ok())
+        .unwrap_or_else(|| "unknown_service".to_string());
+    let mut attributes = vec![
+        proto::KeyValue::string("service.name", &service_name),
+        proto::KeyValue::string("telemetry.sdk.name", "telelog"),
+        proto::KeyValue::string("telemetry.sdk.language", "rust"),
+        proto::KeyValue::string("telemetry.sdk.version", env!("CARGO_PKG_VERSION")),
+    ];
+    if let Some(host) = super::hostname() {
+        attributes.push(proto::KeyValue::string("host.name", &host));
+    }
+    attributes
+}
+
+/// Sets `key` to `value` in `attributes`, replacing an existing entry.
+pub(crate) fn set_attribute(attributes: &mut Vec<proto::KeyValue>, attribute: proto::KeyValue) {
+    match attributes.iter_mut().find(|kv| kv.key == attribute.key) {
+        Some(existing) => *existing = attribute,
+        None => attributes.push(attribute),
+    }
+}
+
+/// Encodes records and batches for the OTLP logs endpoint.
+#[derive(Clone)]
+struct OtlpFormat {
+    protocol: OtlpProtocol,
+    resource: proto::Resource,
+}
+
+impl OtlpFormat {
+    fn log_record<'f>(
+        timestamp: &str,
+        level: LogLevel,
+        message: &str,
+        fields: impl Iterator<Item = (&'f str, &'f str)>,
+        location: Option<&'static Location<'static>>,
+    ) -> proto::LogRecord {
+        let mut record = proto::LogRecord {
+            time_unix_nano: timestamp_ns(timestamp).max(0) as u64,
+            observed_time_unix_nano: chrono::Utc::now()
+                .timestamp_nanos_opt()
+                .unwrap_or_default()
+                .max(0) as u64,
+            severity_number: level.otel_severity_number(),
+            severity_text: level.as_str().to_string(),
+            body: Some(proto::AnyValue {
+                value: Some(proto::any_value::Value::StringValue(message.to_string())),
+            }),
+            ..Default::default()
+        };
+        for (key, value) in fields {
+            let id = match key {
+                "trace_id" => proto::parse_id(value, 16).map(|id| record.trace_id = id),
+                "span_id" => proto::parse_id(value, 8).map(|id| record.span_id = id),
+                _ => None,
+            };
+            if id.is_none() {
+                record.attributes.push(proto::KeyValue::string(key, value));
+            }
+        }
+        if let Some(location) = location {
+            record
+                .attributes
+                .push(proto::KeyValue::string("code.file.path", location.file()));
+            record.attributes.push(proto::KeyValue::int(
+                "code.line.number",
+                location.line() as i64,
+            ));
+        }
+        record
+    }
+
+    fn to_entry(&self, scope: &str, record: &proto::LogRecord) -> Entry {
+        let doc = match self.protocol {
+            OtlpProtocol::HttpProtobuf => record.encode_to_vec(),
+            OtlpProtocol::HttpJson => proto::log_record_json(record).to_string().into_bytes(),
+        };
+        let mut labels = BTreeMap::new();
+        labels.insert("scope".to_string(), scope.to_string());
+        Entry {
+            doc,
+            timestamp_ns: record.time_unix_nano as i64,
+            labels,
+        }
+    }
+
+    /// Groups a batch by instrumentation scope, keeping first-seen order.
+    fn scopes(batch: &[Entry]) -> Vec<(proto::InstrumentationScope, Vec<&[u8]>)> {
+        let mut scopes: Vec<(proto::InstrumentationScope, Vec<&[u8]>)> = Vec::new();
+        for entry in batch {
+            let name = entry.labels.get("scope").map(String::as_str).unwrap_or("");
+            match scopes.iter_mut().find(|(scope, _)| scope.name == name) {
+                Some((_, docs)) => docs.push(&entry.doc),
+                None => scopes.push((
+                    proto::InstrumentationScope {
+                        name: name.to_string(),
+                        version: String::new(),
+                    },
+                    vec![&entry.doc],
+                )),
+            }
+        }
+        scopes
+    }
+}
+
+impl BatchFormat for OtlpFormat {
+    fn content_type(&self) -> &'static str {
+        match self.protocol {
+            OtlpProtocol::HttpProtobuf => "application/x-protobuf",
+            OtlpProtocol::HttpJson => "application/json",
+        }
+    }
+
+    fn entry(&self, record: &LogRecord<'_>) -> io::Result<Entry> {
+        let mut keys: Vec<&String> = record.context.keys().collect();
+        keys.sort_unstable();
+        let context = keys
+            .into_iter()
+            .map(|k| (k.as_str(), record.context[k].as_str()));
+        let data = record.data.unwrap_or_default().iter().copied();
+        let otlp = Self::log_record(
+            record.timestamp,
+            record.level,
+            record.message,
+            context.chain(data),
+            record.location,
+        );
+        Ok(self.to_entry(record.logger, &otlp))
+    }
+
+    fn entry_from_json(&self, bytes: &[u8]) -> io::Result<Entry> {
+        let value: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let field = |key: &str| value.get(key).and_then(|v| v.as_str()).unwrap_or("");
+        let level = field("level").parse().unwrap_or(LogLevel::Info);
+        let fields: Vec<(&str, String)> = value
+            .iter()
+            .filter(|(k, _)| !matches!(k.as_str(), "timestamp" | "level" | "logger" | "message"))
+            .map(|(k, v)| match v {
+                serde_json::Value::String(s) => (k.as_str(), s.clone()),
+                other => (k.as_str(), other.to_string()),
+            })
+            .collect();
+        let otlp = Self::log_record(
+            field("timestamp"),
+            level,
+            field("message"),
+            fields.iter().map(|(k, v)| (*k, v.as_str())),
+            None,
+        );
+        Ok(self.to_entry(field("logger"), &otlp))
+    }
+
+    fn encode(&self, batch: &[Entry]) -> Vec<u8> {
+        let scopes = Self::scopes(batch);
+        match self.protocol {
+            OtlpProtocol::HttpProtobuf => {
+                let mut resource_logs = Vec::new();
+                proto::put_message(&mut resource_logs, 1, &self.resource.encode_to_vec());
+                for (scope, docs) in scopes {
+                    let mut scope_logs = Vec::new();
+                    proto::put_message(&mut scope_logs, 1, &scope.encode_to_vec());
+                    for doc in docs {
+                        proto::put_message(&mut scope_logs, 2, doc);
+                    }
+                    proto::put_message(&mut resource_logs, 2, &scope_logs);
+                }
+                let mut request = Vec::with_capacity(resource_logs.len() + 8);
+                proto::put_message(&mut request, 1, &resource_logs);
+                request
+            }
+            OtlpProtocol::HttpJson => {
+                let mut body = format!(
+                    "{{\"resourceLogs\":[{{\"resource\":{},\"scopeLogs\":[",
+                    proto::resource_json(&self.resource)
+                )
+                .into_bytes();
+                for (i, (scope, docs)) in scopes.into_iter().enumerate() {
+                    if i > 0 {
+                        body.push(b',');
+                    }
+                    body.extend_from_slice(
+                        format!("{{\"scope\":{},\"logRecords\":[", proto::scope_json(&scope))
+                            .as_bytes(),
+                    );
+                    body.extend_from_slice(&docs.join(&b','));
+                    body.extend_from_slice(b"]}");
+                }
+                body.extend_from_slice(b"]}]}");
+                body
+            }
+        }
+    }
+}
+
+/// Exports log records to an OpenTelemetry collector over OTLP/HTTP.
+pub struct OtlpOutput {
+    http: HttpOutput,
+    format: OtlpFormat,
+}
+
+impl OtlpOutput {
+    /// Creates an exporter for the full logs URL, e.g.
+    /// [`DEFAULT_LOGS_ENDPOINT`] (`http://localhost:4318/v1/logs`).
+    pub fn new(endpoint: &str) -> Self {
+        let format = OtlpFormat {
+            protocol: OtlpProtocol::default(),
+            resource: proto::Resource {
+                attributes: default_resource(None),
+            },
+        };
+        Self {
+            http: HttpOutput::with_batch_format(endpoint, Arc::new(format.clone())),
+            format,
+        }
+    }
+
+    /// Sets the request encoding (protobuf by default).
+    pub fn with_protocol(mut self, protocol: OtlpProtocol) -> Self {
+        self.format.protocol = protocol;
+        self.update_format()
+    }
+
+    /// Sets the `service.name` resource attribute.
+    pub fn with_service_name(self, name: &str) -> Self {
+        self.with_resource_attribute("service.name", name)
+    }
+
+    /// Adds or replaces a resource attribute.
+    pub fn with_resource_attribute(mut self, key: &str, value: &str) -> Self {
+        set_attribute(
+            &mut self.format.resource.attributes,
+            proto::KeyValue::string(key, value),
+        );
+        self.update_format()
+    }
+
+    /// Sends a batch once it holds this many records (100 by default).
+    pub fn with_batch_size(mut self, records: usize) -> Self {
+        self.http = self.http.with_batch_size(records);
+        self
+    }
+
+    /// Sends a batch once its encoded records total this many bytes (1 MiB by default).
+    pub fn with_max_batch_bytes(mut self, bytes: usize) -> Self {
+        self.http = self.http.with_max_batch_bytes(bytes);
+        self
+    }
+
+    /// Sends a batch at the latest this long after its first record (1s by default).
+    pub fn with_max_batch_age(mut self, age: Duration) -> Self {
+        self.http = self.http.with_max_batch_age(age);
+        self
+    }
+
+    /// Sets how often a failed export is retried and the initial backoff.
+    pub fn with_retry(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.http = self.http.with_retry(max_retries, backoff);
+        self
+    }
+
+    /// Adds a request header, e.g. for collector authentication.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.http = self.http.with_header(name, value);
+        self
+    }
+
+    /// Sets the timeout for a whole export request (10s by default).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http = self.http.with_timeout(timeout);
+        self
+    }
+
+    /// Compresses requests with gzip (requires `gzip` feature).
+    #[cfg(feature = "gzip")]
+    pub fn with_gzip(mut self, enabled: bool) -> Self {
+        self.http = self.http.with_gzip(enabled);
+        self
+    }
+
+    /// Reports failed exports and retries to `handler` instead of stderr.
+    pub fn with_error_handler(mut self, handler: ErrorHandler) -> Self {
+        self.http = self.http.with_error_handler(handler);
+        self
+    }
+
+    fn update_format(mut self) -> Self {
+        self.http.set_batch_format(Arc::new(self.format.clone()));
+        self
+    }
+}
+
+impl OutputDestination for OtlpOutput {
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        self.http.write(record)
+    }
+
+    /// Exports the pending batch and waits for the request to complete.
+    fn flush(&self) -> io::Result<()> {
+        self.http.flush()
+    }
+
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        self.http.write_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::http::tests::stub_server;
+    use std::collections::HashMap;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const SPAN_ID: &str = "00f067aa0ba902b7";
+
+    fn write(output: &OtlpOutput, logger: &str, message: &str) {
+        let mut ctx = HashMap::new();
+        ctx.insert("trace_id".to_string(), TRACE_ID.to_string());
+        ctx.insert("request_id".to_string(), "abc".to_string());
+        output
+            .write(&LogRecord {
+                timestamp: "2025-09-07T10:30:00Z",
+                level: LogLevel::Warning,
+                logger,
+                message,
+                context: &ctx,
+                data: Some(&[("span_id", SPAN_ID), ("user_id", "42")]),
+                location: None,
+            })
+            .unwrap();
+    }
+
+    fn string_attr<'a>(attributes: &'a [proto::KeyValue], key: &str) -> Option<&'a str> {
+        attributes.iter().find(|kv| kv.key == key).and_then(|kv| {
+            match kv.value.as_ref()?.value.as_ref()? {
+                proto::any_value::Value::StringValue(s) => Some(s.as_str()),
+                _ => None,
+            }
+        })
+    }
+
+    #[test]
+    fn test_protobuf_export() {
+        let (url, requests) = stub_server(&[]);
+        let output = OtlpOutput::new(&url).with_service_name("billing");
+
+        write(&output, "api", "first");
+        write(&output, "db", "second");
+        write(&output, "api", "third");
+        output.flush().unwrap();
+
+        let request = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(request.headers["content-type"], "application/x-protobuf");
+        let export = proto::ExportLogsServiceRequest::decode(&request.body[..]).unwrap();
+        assert_eq!(export.resource_logs.len(), 1);
+        let resource_logs = &export.resource_logs[0];
+        let resource = resource_logs.resource.as_ref().unwrap();
+        assert_eq!(
+            string_attr(&resource.attributes, "service.name"),
+            Some("billing")
+        );
+
+        let scopes = &resource_logs.scope_logs;
+        assert_eq!(scopes.len(), 2);
+        assert_eq!(scopes[0].scope.as_ref().unwrap().name, "api");
+        assert_eq!(scopes[0].log_records.len(), 2);
+        assert_eq!(scopes[1].scope.as_ref().unwrap().name, "db");
+
+        let record = &scopes[0].log_records[0];
+        assert_eq!(record.time_unix_nano, 1_757_241_000_000_000_000);
+        assert_eq!(record.severity_number, 13);
+        assert_eq!(record.severity_text, "WARNING");
+        assert_eq!(
+            record.body.as_ref().unwrap().value,
+            Some(proto::any_value::Value::StringValue("first".to_string()))
+        );
+        assert_eq!(proto::hex(&record.trace_id), TRACE_ID);
+        assert_eq!(proto::hex(&record.span_id), SPAN_ID);
+        assert_eq!(string_attr(&record.attributes, "request_id"), Some("abc"));
+        assert_eq!(string_attr(&record.attributes, "user_id"), Some("42"));
+        assert_eq!(string_attr(&record.attributes, "trace_id"), None);
+    }
+
+    #[test]
+    fn test_json_export() {
+        let (url, requests) = stub_server(&[]);
+        let output = OtlpOutput::new(&url)
+            .with_protocol(OtlpProtocol::HttpJson)
+            .with_resource_attribute("deployment.environment", "test");
+
+        write(&output, "api", "hello");
+        output
+            .write_bytes(b"{\"timestamp\":\"2025-09-07T10:30:01Z\",\"level\":\"ERROR\",\"logger\":\"api\",\"message\":\"raw\",\"code\":7}\n")
+            .unwrap();
+        output.flush().unwrap();
+
+        let request = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(request.headers["content-type"], "application/json");
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        let resource_logs = &body["resourceLogs"][0];
+        assert!(resource_logs["resource"]["attributes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|a| a["key"] == "deployment.environment" && a["value"]["stringValue"] == "test"));
+
+        let scope_logs = &resource_logs["scopeLogs"][0];
+        assert_eq!(scope_logs["scope"]["name"], "api");
+        let records = scope_logs["logRecords"].as_array().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["timeUnixNano"], "1757241000000000000");
+        assert_eq!(records[0]["severityNumber"], 13);
+        assert_eq!(records[0]["body"]["stringValue"], "hello");
+        assert_eq!(records[0]["traceId"], TRACE_ID);
+        assert_eq!(records[0]["spanId"], SPAN_ID);
+        assert_eq!(records[1]["severityNumber"], 17);
+        assert_eq!(records[1]["attributes"][0]["key"], "code");
+        assert_eq!(records[1]["attributes"][0]["value"]["stringValue"], "7");
+    }
+}
@@ -0,0 +1,307 @@
+//! The subset of the OpenTelemetry protocol messages used by the exporters.
+//!
+//! Field numbers follow `opentelemetry/proto` v1. Messages are declared by
+//! hand with `prost` derives so no `protoc` is needed at build time; only the
+//! `AnyValue` variants telelog produces are included.
+
+use serde_json::{json, Value};
+
+/// `opentelemetry.proto.common.v1.AnyValue`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AnyValue {
+    #[prost(oneof = "any_value::Value", tags = "1, 2, 3, 4")]
+    pub value: Option<any_value::Value>,
+}
+
+pub mod any_value {
+    // Variant names mirror the protobuf field names.
+    #[allow(clippy::enum_variant_names)]
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Value {
+        #[prost(string, tag = "1")]
+        StringValue(String),
+        #[prost(bool, tag = "2")]
+        BoolValue(bool),
+        #[prost(int64, tag = "3")]
+        IntValue(i64),
+        #[prost(double, tag = "4")]
+        DoubleValue(f64),
+    }
+}
+
+/// `opentelemetry.proto.common.v1.KeyValue`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct KeyValue {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(message, optional, tag = "2")]
+    pub value: Option<AnyValue>,
+}
+
+impl KeyValue {
+    pub fn string(key: &str, value: &str) -> Self {
+        Self::new(key, any_value::Value::StringValue(value.to_string()))
+    }
+
+    pub fn int(key: &str, value: i64) -> Self {
+        Self::new(key, any_value::Value::IntValue(value))
+    }
+
+    fn new(key: &str, value: any_value::Value) -> Self {
+        Self {
+            key: key.to_string(),
+            value: Some(AnyValue { value: Some(value) }),
+        }
+    }
+}
+
+/// `opentelemetry.proto.common.v1.InstrumentationScope`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct InstrumentationScope {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub version: String,
+}
+
+/// `opentelemetry.proto.resource.v1.Resource`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Resource {
+    #[prost(message, repeated, tag = "1")]
+    pub attributes: Vec<KeyValue>,
+}
+
+/// `opentelemetry.proto.logs.v1.LogRecord`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LogRecord {
+    #[prost(fixed64, tag = "1")]
+    pub time_unix_nano: u64,
+    #[prost(fixed64, tag = "11")]
+    pub observed_time_unix_nano: u64,
+    #[prost(int32, tag = "2")]
+    pub severity_number: i32,
+    #[prost(string, tag = "3")]
+    pub severity_text: String,
+    #[prost(message, optional, tag = "5")]
+    pub body: Option<AnyValue>,
+    #[prost(message, repeated, tag = "6")]
+    pub attributes: Vec<KeyValue>,
+    #[prost(bytes = "vec", tag = "9")]
+    pub trace_id: Vec<u8>,
+    #[prost(bytes = "vec", tag = "10")]
+    pub span_id: Vec<u8>,
+}
+
+// Requests are assembled from encoded records with `put_message`; the
+// container messages below are only needed to decode them in tests.
+
+/// `opentelemetry.proto.logs.v1.ScopeLogs`
+#[cfg(test)]
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ScopeLogs {
+    #[prost(message, optional, tag = "1")]
+    pub scope: Option<InstrumentationScope>,
+    #[prost(message, repeated, tag = "2")]
+    pub log_records: Vec<LogRecord>,
+}
+
+/// `opentelemetry.proto.logs.v1.ResourceLogs`
+#[cfg(test)]
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ResourceLogs {
+    #[prost(message, optional, tag = "1")]
+    pub resource: Option<Resource>,
+    #[prost(message, repeated, tag = "2")]
+    pub scope_logs: Vec<ScopeLogs>,
+}
+
+/// `opentelemetry.proto.collector.logs.v1.ExportLogsServiceRequest`
+#[cfg(test)]
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ExportLogsServiceRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub resource_logs: Vec<ResourceLogs>,
+}
+
+/// `opentelemetry.proto.trace.v1.Status`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Status {
+    #[prost(string, tag = "2")]
+    pub message: String,
+    #[prost(int32, tag = "3")]
+    pub code: i32,
+}
+
+/// `Status.StatusCode` values.
+pub const STATUS_CODE_UNSET: i32 = 0;
+pub const STATUS_CODE_OK: i32 = 1;
+pub const STATUS_CODE_ERROR: i32 = 2;
+
+/// `Span.SpanKind.SPAN_KIND_INTERNAL`
+pub const SPAN_KIND_INTERNAL: i32 = 1;
+
+/// `opentelemetry.proto.trace.v1.Span`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Span {
+    #[prost(bytes = "vec", tag = "1")]
+    pub trace_id: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub span_id: Vec<u8>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub parent_span_id: Vec<u8>,
+    #[prost(string, tag = "5")]
+    pub name: String,
+    #[prost(int32, tag = "6")]
+    pub kind: i32,
+    #[prost(fixed64, tag = "7")]
+    pub start_time_unix_nano: u64,
+    #[prost(fixed64, tag = "8")]
+    pub end_time_unix_nano: u64,
+    #[prost(message, repeated, tag = "9")]
+    pub attributes: Vec<KeyValue>,
+    #[prost(message, optional, tag = "15")]
+    pub status: Option<Status>,
+}
+
+/// `opentelemetry.proto.trace.v1.ScopeSpans`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ScopeSpans {
+    #[prost(message, optional, tag = "1")]
+    pub scope: Option<InstrumentationScope>,
+    #[prost(message, repeated, tag = "2")]
+    pub spans: Vec<Span>,
+}
+
+/// `opentelemetry.proto.trace.v1.ResourceSpans`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ResourceSpans {
+    #[prost(message, optional, tag = "1")]
+    pub resource: Option<Resource>,
+    #[prost(message, repeated, tag = "2")]
+    pub scope_spans: Vec<ScopeSpans>,
+}
+
+/// `opentelemetry.proto.collector.trace.v1.ExportTraceServiceRequest`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ExportTraceServiceRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub resource_spans: Vec<ResourceSpans>,
+}
+
+/// Appends a length-delimited field holding an already encoded message.
+///
+/// Lets batches be assembled from records encoded when they were logged.
+pub fn put_message(buf: &mut Vec<u8>, tag: u32, message: &[u8]) {
+    prost::encoding::encode_key(tag, prost::encoding::WireType::LengthDelimited, buf);
+    prost::encoding::encode_varint(message.len() as u64, buf);
+    buf.extend_from_slice(message);
+}
+
+// OTLP/JSON mapping: camelCase names, 64-bit integers as strings, enums as
+// numbers and trace/span ids as lowercase hex.
+
+pub fn any_value_json(value: &AnyValue) -> Value {
+    match &value.value {
+        Some(any_value::Value::StringValue(s)) => json!({ "stringValue": s }),
+        Some(any_value::Value::BoolValue(b)) => json!({ "boolValue": b }),
+        Some(any_value::Value::IntValue(i)) => json!({ "intValue": i.to_string() }),
+        Some(any_value::Value::DoubleValue(d)) => json!({ "doubleValue": d }),
+        None => json!({}),
+    }
+}
+
+pub fn attributes_json(attributes: &[KeyValue]) -> Value {
+    attributes
+        .iter()
+        .map(|kv| {
+            json!({
+                "key": kv.key,
+                "value": kv.value.as_ref().map(any_value_json).unwrap_or_else(|| json!({})),
+            })
+        })
+        .collect()
+}
+
+pub fn resource_json(resource: &Resource) -> Value {
+    json!({ "attributes": attributes_json(&resource.attributes) })
+}
+
+pub fn scope_json(scope: &InstrumentationScope) -> Value {
+    json!({ "name": scope.name, "version": scope.version })
+}
+
+pub fn log_record_json(record: &LogRecord) -> Value {
+    let mut value = json!({
+        "timeUnixNano": record.time_unix_nano.to_string(),
+        "observedTimeUnixNano": record.observed_time_unix_nano.to_string(),
+        "severityNumber": record.severity_number,
+        "severityText": record.severity_text,
+        "body": record.body.as_ref().map(any_value_json).unwrap_or_else(|| json!({})),
+        "attributes": attributes_json(&record.attributes),
+    });
+    if !record.trace_id.is_empty() {
+        value["traceId"] = json!(hex(&record.trace_id));
+    }
+    if !record.span_id.is_empty() {
+        value["spanId"] = json!(hex(&record.span_id));
+    }
+    value
+}
+
+pub fn span_json(span: &Span) -> Value {
+    let mut value = json!({
+        "traceId": hex(&span.trace_id),
+        "spanId": hex(&span.span_id),
+        "name": span.name,
+        "kind": span.kind,
+        "startTimeUnixNano": span.start_time_unix_nano.to_string(),
+        "endTimeUnixNano": span.end_time_unix_nano.to_string(),
+        "attributes": attributes_json(&span.attributes),
+    });
+    if !span.parent_span_id.is_empty() {
+        value["parentSpanId"] = json!(hex(&span.parent_span_id));
+    }
+    if let Some(status) = &span.status {
+        value["status"] = json!({ "code": status.code, "message": status.message });
+    }
+    value
+}
+
+pub fn export_trace_json(request: &ExportTraceServiceRequest) -> Value {
+    let resource_spans: Vec<Value> = request
+        .resource_spans
+        .iter()
+        .map(|rs| {
+            let scope_spans: Vec<Value> = rs
+                .scope_spans
+                .iter()
+                .map(|ss| {
+                    json!({
+                        "scope": ss.scope.as_ref().map(scope_json),
+                        "spans": ss.spans.iter().map(span_json).collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            json!({
+                "resource": rs.resource.as_ref().map(resource_json),
+                "scopeSpans": scope_spans,
+            })
+        })
+        .collect();
+    json!({ "resourceSpans": resource_spans })
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parses a hex trace or span id of exactly `len` bytes; all-zero ids are invalid.
+pub fn parse_id(hex: &str, len: usize) -> Option<Vec<u8>> {
+    if hex.len() != len * 2 || !hex.is_ascii() {
+        return None;
+    }
+    let bytes = (0..len)
+        .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    bytes.iter().any(|&b| b != 0).then_some(bytes)
+}
@@ -0,0 +1,390 @@
+//! Export of [`ComponentTracker`] trees as OpenTelemetry spans.
+//!
+//! Each completed [`Component`] becomes one span: its name is the component
+//! name, `parent_id` links it to its parent span, and start and end times are
+//! converted with [`ComponentTracker::system_time`]. [`ComponentStatus`] maps
+//! to the span status (`Success` to `OK`, `Failed` to `ERROR` with the error
+//! as message, `Cancelled` to `UNSET`), and is also recorded in the
+//! `telelog.status` attribute. `ComponentMetadata::custom` entries become
+//! string attributes and `memory_bytes` becomes `telelog.memory_bytes`.
+//!
+//! Trace and span ids are derived from the component ids and the root
+//! component's start time, so exporting the same tree again, or a parent after
+//! its children, yields consistent ids.
+
+use super::proto::{self, KeyValue};
+use super::{default_resource, set_attribute, OtlpProtocol};
+use crate::component::{Component, ComponentStatus, ComponentTracker};
+use crate::output::http::HttpClient;
+use parking_lot::Mutex;
+use prost::Message;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Default OTLP/HTTP traces endpoint of a local collector.
+pub const DEFAULT_TRACES_ENDPOINT: &str = "http://localhost:4318/v1/traces";
+
+/// Exports completed components of a [`ComponentTracker`] as OTLP spans.
+///
+/// # Example
+///
+/// ```no_run
+/// use telelog::output::OtlpSpanExporter;
+/// use telelog::Logger;
+///
+/// let logger = Logger::new("app");
+/// let exporter = OtlpSpanExporter::new("http://localhost:4318/v1/traces")
+///     .with_service_name("billing");
+/// {
+///     let _request = logger.track_component("handle_request");
+///     let _query = logger.track_component("db_query");
+/// }
+/// exporter.export(logger.component_tracker()).unwrap();
+/// ```
+pub struct OtlpSpanExporter {
+    client: HttpClient,
+    protocol: OtlpProtocol,
+    resource: proto::Resource,
+    exported: Mutex<HashSet<Vec<u8>>>,
+}
+
+impl OtlpSpanExporter {
+    /// Creates an exporter for the full traces URL, e.g.
+    /// [`DEFAULT_TRACES_ENDPOINT`] (`http://localhost:4318/v1/traces`).
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            client: HttpClient::new(endpoint),
+            protocol: OtlpProtocol::default(),
+            resource: proto::Resource {
+                attributes: default_resource(None),
+            },
+            exported: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Sets the request encoding (protobuf by default).
+    pub fn with_protocol(mut self, protocol: OtlpProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Sets the `service.name` resource attribute.
+    pub fn with_service_name(self, name: &str) -> Self {
+        self.with_resource_attribute("service.name", name)
+    }
+
+    /// Adds or replaces a resource attribute.
+    pub fn with_resource_attribute(mut self, key: &str, value: &str) -> Self {
+        set_attribute(&mut self.resource.attributes, KeyValue::string(key, value));
+        self
+    }
+
+    /// Adds a request header, e.g. for collector authentication.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.client
+            .headers
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the timeout for a whole export request (10s by default).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client.set_timeout(timeout);
+        self
+    }
+
+    /// Sets how often a failed export is retried and the initial backoff.
+    pub fn with_retry(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.client.max_retries = max_retries;
+        self.client.retry_backoff = backoff;
+        self
+    }
+
+    /// Compresses requests with gzip (requires `gzip` feature).
+    #[cfg(feature = "gzip")]
+    pub fn with_gzip(mut self, enabled: bool) -> Self {
+        self.client.gzip = enabled;
+        self
+    }
+
+    /// Exports components completed since the previous call and returns how
+    /// many spans were sent.
+    ///
+    /// Running components are skipped and exported by a later call once they
+    /// finish. If the request fails, the spans are retried by the next call.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the collector cannot be reached or rejects the request.
+    pub fn export(&self, tracker: &ComponentTracker) -> io::Result<usize> {
+        let components = tracker.get_components();
+        let mut exported = self.exported.lock();
+
+        let spans = spans(tracker, &components);
+        // Forget spans of components that have been cleared from the tracker.
+        let current: HashSet<&Vec<u8>> = spans.iter().map(|s| &s.span_id).collect();
+        exported.retain(|id| current.contains(id));
+
+        let pending: Vec<proto::Span> = spans
+            .iter()
+            .filter(|s| !exported.contains(&s.span_id))
+            .cloned()
+            .collect();
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let request = proto::ExportTraceServiceRequest {
+            resource_spans: vec![proto::ResourceSpans {
+                resource: Some(self.resource.clone()),
+                scope_spans: vec![proto::ScopeSpans {
+                    scope: Some(proto::InstrumentationScope {
+                        name: "telelog".to_string(),
+                        version: env!("CARGO_PKG_VERSION").to_string(),
+                    }),
+                    spans: pending,
+                }],
+            }],
+        };
+        let (body, content_type) = match self.protocol {
+            OtlpProtocol::HttpProtobuf => (request.encode_to_vec(), "application/x-protobuf"),
+            OtlpProtocol::HttpJson => (
+                proto::export_trace_json(&request).to_string().into_bytes(),
+                "application/json",
+            ),
+        };
+        self.client.post(&body, content_type)?;
+
+        let spans = &request.resource_spans[0].scope_spans[0].spans;
+        exported.extend(spans.iter().map(|s| s.span_id.clone()));
+        Ok(spans.len())
+    }
+}
+
+/// Converts every completed component into a span.
+fn spans(tracker: &ComponentTracker, components: &HashMap<String, Component>) -> Vec<proto::Span> {
+    let mut ids: Vec<&String> = components.keys().collect();
+    ids.sort_unstable();
+    ids.into_iter()
+        .map(|id| &components[id])
+        .filter(|c| !c.is_running())
+        .map(|component| {
+            let trace_id = trace_id(tracker, components, component);
+            let parent_span_id = component
+                .parent_id
+                .as_ref()
+                .filter(|parent| components.contains_key(*parent))
+                .map(|parent| span_id(&trace_id, parent))
+                .unwrap_or_default();
+            let end = component.end_time.unwrap_or(component.start_time);
+            proto::Span {
+                span_id: span_id(&trace_id, &component.id),
+                trace_id,
+                parent_span_id,
+                name: component.name.clone(),
+                kind: proto::SPAN_KIND_INTERNAL,
+                start_time_unix_nano: unix_nanos(tracker.system_time(component.start_time)),
+                end_time_unix_nano: unix_nanos(tracker.system_time(end)),
+                attributes: attributes(component),
+                status: Some(status(&component.status)),
+            }
+        })
+        .collect()
+}
+
+fn attributes(component: &Component) -> Vec<KeyValue> {
+    let metadata = &component.metadata;
+    let mut keys: Vec<&String> = metadata.custom.keys().collect();
+    keys.sort_unstable();
+    let mut attributes: Vec<KeyValue> = keys
+        .into_iter()
+        .map(|key| KeyValue::string(key, &metadata.custom[key]))
+        .collect();
+    if let Some(bytes) = metadata.memory_bytes {
+        attributes.push(KeyValue::int(
+            "telelog.memory_bytes",
+            i64::try_from(bytes).unwrap_or(i64::MAX),
+        ));
+    }
+    if let Some(message) = &metadata.message {
+        attributes.push(KeyValue::string("telelog.message", message));
+    }
+    attributes.push(KeyValue::string("telelog.level", metadata.level.as_str()));
+    attributes.push(KeyValue::string("telelog.component_id", &component.id));
+    let status = match component.status {
+        ComponentStatus::Running => "running",
+        ComponentStatus::Success => "success",
+        ComponentStatus::Failed(_) => "failed",
+        ComponentStatus::Cancelled => "cancelled",
+    };
+    attributes.push(KeyValue::string("telelog.status", status));
+    attributes
+}
+
+fn status(status: &ComponentStatus) -> proto::Status {
+    match status {
+        ComponentStatus::Success => proto::Status {
+            code: proto::STATUS_CODE_OK,
+            message: String::new(),
+        },
+        ComponentStatus::Failed(error) => proto::Status {
+            code: proto::STATUS_CODE_ERROR,
+            message: error.clone(),
+        },
+        ComponentStatus::Running | ComponentStatus::Cancelled => proto::Status {
+            code: proto::STATUS_CODE_UNSET,
+            message: String::new(),
+        },
+    }
+}
+
+/// Derives the trace id from the component's root and its start time.
+fn trace_id(
+    tracker: &ComponentTracker,
+    components: &HashMap<String, Component>,
+    component: &Component,
+) -> Vec<u8> {
+    let mut root = component;
+    // Bounded walk in case of a malformed parent chain.
+    for _ in 0..components.len() {
+        match root.parent_id.as_ref().and_then(|p| components.get(p)) {
+            Some(parent) => root = parent,
+            None => break,
+        }
+    }
+    let start = unix_nanos(tracker.system_time(root.start_time));
+    let seed = (std::process::id(), root.id.as_str(), start);
+    let mut id = hash(&(seed, 0u8)).to_be_bytes().to_vec();
+    id.extend_from_slice(&hash(&(seed, 1u8)).to_be_bytes());
+    id
+}
+
+fn span_id(trace_id: &[u8], component_id: &str) -> Vec<u8> {
+    hash(&(trace_id, component_id)).to_be_bytes().to_vec()
+}
+
+/// Hashes `value` to a non-zero id (all-zero ids are invalid in OTLP).
+fn hash<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish().max(1)
+}
+
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::ComponentMetadata;
+    use crate::output::http::tests::stub_server;
+
+    fn tracker() -> ComponentTracker {
+        let tracker = ComponentTracker::new();
+        let request = tracker.start_component("handle_request");
+        let query = tracker.start_component("db_query");
+        tracker
+            .update_metadata(
+                &query,
+                ComponentMetadata::new()
+                    .with_custom("table", "users")
+                    .with_memory(4096),
+            )
+            .unwrap();
+        tracker
+            .end_component(&query, ComponentStatus::Failed("timeout".to_string()))
+            .unwrap();
+        tracker
+            .end_component(&request, ComponentStatus::Success)
+            .unwrap();
+        tracker
+    }
+
+    fn attr<'a>(span: &'a proto::Span, key: &str) -> Option<&'a proto::any_value::Value> {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key == key)
+            .and_then(|kv| kv.value.as_ref()?.value.as_ref())
+    }
+
+    #[test]
+    fn test_components_become_span_tree() {
+        let tracker = tracker();
+        let spans = spans(&tracker, &tracker.get_components());
+        assert_eq!(spans.len(), 2);
+        let (request, query) = (&spans[0], &spans[1]);
+
+        assert_eq!(request.name, "handle_request");
+        assert!(request.parent_span_id.is_empty());
+        assert_eq!(request.status.as_ref().unwrap().code, proto::STATUS_CODE_OK);
+        assert!(request.end_time_unix_nano >= request.start_time_unix_nano);
+
+        assert_eq!(query.trace_id, request.trace_id);
+        assert_eq!(query.trace_id.len(), 16);
+        assert_eq!(query.span_id.len(), 8);
+        assert_eq!(query.parent_span_id, request.span_id);
+        let status = query.status.as_ref().unwrap();
+        assert_eq!(status.code, proto::STATUS_CODE_ERROR);
+        assert_eq!(status.message, "timeout");
+        assert_eq!(
+            attr(query, "table"),
+            Some(&proto::any_value::Value::StringValue("users".to_string()))
+        );
+        assert_eq!(
+            attr(query, "telelog.memory_bytes"),
+            Some(&proto::any_value::Value::IntValue(4096))
+        );
+
+        // Ids are stable across conversions.
+        assert_eq!(spans, super::spans(&tracker, &tracker.get_components()));
+    }
+
+    #[test]
+    fn test_export_sends_each_span_once() {
+        let (url, requests) = stub_server(&[]);
+        let exporter = OtlpSpanExporter::new(&url).with_service_name("billing");
+        let tracker = tracker();
+        let running = tracker.start_component("background");
+
+        assert_eq!(exporter.export(&tracker).unwrap(), 2);
+        let request = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(request.headers["content-type"], "application/x-protobuf");
+        let export = proto::ExportTraceServiceRequest::decode(&request.body[..]).unwrap();
+        let resource_spans = &export.resource_spans[0];
+        assert!(resource_spans
+            .resource
+            .as_ref()
+            .unwrap()
+            .attributes
+            .contains(&KeyValue::string("service.name", "billing")));
+        assert_eq!(resource_spans.scope_spans[0].spans.len(), 2);
+
+        assert_eq!(exporter.export(&tracker).unwrap(), 0);
+        tracker
+            .end_component(&running, ComponentStatus::Cancelled)
+            .unwrap();
+        assert_eq!(exporter.export(&tracker).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_json_export() {
+        let (url, requests) = stub_server(&[]);
+        let exporter = OtlpSpanExporter::new(&url).with_protocol(OtlpProtocol::HttpJson);
+
+        exporter.export(&tracker()).unwrap();
+
+        let request = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        let spans = &body["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(spans[0]["name"], "handle_request");
+        assert_eq!(spans[1]["parentSpanId"], spans[0]["spanId"]);
+        assert_eq!(spans[1]["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(spans[1]["status"]["code"], proto::STATUS_CODE_ERROR);
+    }
+}
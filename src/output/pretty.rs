@@ -0,0 +1,308 @@
+//! Human-friendly console rendering for local development.
+//!
+//! [`PrettyStyle`] configures the `Pretty` [`ConsoleFormat`](crate::output::ConsoleFormat):
+//!
+//! ```text
+//! 10:30:00.123 INFO  api request done  status=200 user=42
+//! 10:30:00.131 ERROR db   query failed
+//!                         syntax error at line 3
+//! ```
+//!
+//! Records get a short or relative timestamp, a padded level badge colored by
+//! the theme and a dimmed logger name. Messages are indented two spaces per
+//! open component of the logger's [`ComponentTracker`](crate::component::ComponentTracker),
+//! and continuation lines of multi-line messages line up under the first.
+//! Context entries and data fields follow on the same line or, with
+//! [`FieldLayout::Below`], one per line with aligned keys.
+
+use crate::level::LogLevel;
+use crate::output::console::ColorTheme;
+use crate::output::format::write_value;
+use crate::output::LogRecord;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// How timestamps are shown by the pretty console format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TimestampStyle {
+    /// Local time of day with milliseconds, e.g. `10:30:00.123`.
+    #[default]
+    Short,
+    /// Seconds since the console output was created, e.g. `+12.345s`.
+    Relative,
+    /// The record's RFC 3339 timestamp.
+    Full,
+    /// No timestamp.
+    Hidden,
+}
+
+/// Where the pretty console format puts context entries and data fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FieldLayout {
+    /// After the message, as `key=value` pairs.
+    #[default]
+    Inline,
+    /// One per line below the message, with keys padded to the same width.
+    Below,
+}
+
+/// Options of the pretty console format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PrettyStyle {
+    pub timestamps: TimestampStyle,
+    pub fields: FieldLayout,
+}
+
+impl PrettyStyle {
+    /// Creates the default style: short timestamps and inline fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how timestamps are shown.
+    pub fn with_timestamps(mut self, timestamps: TimestampStyle) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// Sets where fields are shown.
+    pub fn with_fields(mut self, fields: FieldLayout) -> Self {
+        self.fields = fields;
+        self
+    }
+}
+
+/// Display adapter rendering a record in the pretty format, without the
+/// trailing newline.
+pub(crate) struct PrettyRecord<'r, 'a> {
+    pub record: &'r LogRecord<'a>,
+    pub style: PrettyStyle,
+    /// Reference point for relative timestamps.
+    pub start: DateTime<Utc>,
+    /// Number of open components; each indents the message by two spaces.
+    pub depth: usize,
+    /// Colors to use, or `None` for plain text.
+    pub theme: Option<&'r ColorTheme>,
+}
+
+fn badge(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Debug => "DEBUG",
+        LogLevel::Info => "INFO",
+        LogLevel::Warning => "WARN",
+        LogLevel::Error => "ERROR",
+        LogLevel::Critical => "CRIT",
+    }
+}
+
+impl PrettyRecord<'_, '_> {
+    fn write_timestamp(&self, out: &mut String) -> fmt::Result {
+        let time = DateTime::parse_from_rfc3339(self.record.timestamp);
+        match (self.style.timestamps, time) {
+            (TimestampStyle::Hidden, _) => return Ok(()),
+            (TimestampStyle::Short, Ok(time)) => write!(
+                out,
+                "{}",
+                time.with_timezone(&chrono::Local).format("%H:%M:%S%.3f")
+            )?,
+            (TimestampStyle::Relative, Ok(time)) => {
+                let millis = (time.with_timezone(&Utc) - self.start)
+                    .num_milliseconds()
+                    .max(0);
+                write!(out, "+{}.{:03}s", millis / 1000, millis % 1000)?
+            }
+            _ => out.write_str(self.record.timestamp)?,
+        }
+        out.write_char(' ')
+    }
+
+    fn paint(&self, out: &mut String, color: &str, text: &str) -> fmt::Result {
+        match self.theme {
+            Some(_) => write!(out, "{}{}{}", color, text, RESET),
+            None => out.write_str(text),
+        }
+    }
+
+    /// Returns the context entries, sorted by key, followed by data fields.
+    fn fields(&self) -> Vec<(&str, &str)> {
+        let mut context: Vec<(&str, &str)> = self
+            .record
+            .context
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        context.sort_unstable();
+        context.extend(self.record.data.unwrap_or_default().iter().copied());
+        context
+    }
+}
+
+impl fmt::Display for PrettyRecord<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let record = self.record;
+        let mut out = String::with_capacity(128);
+
+        self.write_timestamp(&mut out)?;
+        let level_color = self.theme.map_or("", |t| t.color(record.level));
+        self.paint(
+            &mut out,
+            level_color,
+            &format!("{:<5}", badge(record.level)),
+        )?;
+        out.write_char(' ')?;
+        self.paint(&mut out, DIM, record.logger)?;
+        out.write_char(' ')?;
+        for _ in 0..self.depth {
+            out.write_str("  ")?;
+        }
+
+        // Width of everything before the message, for continuation lines.
+        let indent = console_width(&out);
+        let mut lines = record.message.split('\n');
+        out.write_str(lines.next().unwrap_or_default().trim_end_matches('\r'))?;
+        for line in lines {
+            out.write_char('\n')?;
+            out.extend(std::iter::repeat_n(' ', indent));
+            out.write_str(line.trim_end_matches('\r'))?;
+        }
+
+        let fields = self.fields();
+        match self.style.fields {
+            FieldLayout::Inline => {
+                if !fields.is_empty() {
+                    out.write_char(' ')?;
+                }
+                for (key, value) in fields {
+                    out.write_char(' ')?;
+                    self.paint(&mut out, DIM, &format!("{}=", key))?;
+                    write_value(&mut out, value)?;
+                }
+            }
+            FieldLayout::Below => {
+                let width = fields.iter().map(|(k, _)| k.chars().count()).max();
+                for (key, value) in &fields {
+                    out.write_char('\n')?;
+                    out.extend(std::iter::repeat_n(' ', indent + 2));
+                    let key = format!("{:<width$} =", key, width = width.unwrap_or(0));
+                    self.paint(&mut out, DIM, &key)?;
+                    out.write_char(' ')?;
+                    write_value(&mut out, value)?;
+                }
+            }
+        }
+        f.write_str(&out)
+    }
+}
+
+/// Returns the number of characters in `text`, ignoring ANSI escape sequences.
+fn console_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn render(record: &LogRecord<'_>, style: PrettyStyle, depth: usize) -> String {
+        PrettyRecord {
+            record,
+            style,
+            start: "2025-09-07T10:29:58Z".parse().unwrap(),
+            depth,
+            theme: None,
+        }
+        .to_string()
+    }
+
+    fn record<'a>(
+        message: &'a str,
+        context: &'a HashMap<String, String>,
+        data: &'a [(&'a str, &'a str)],
+    ) -> LogRecord<'a> {
+        LogRecord {
+            timestamp: "2025-09-07T10:30:00.250Z",
+            level: LogLevel::Warning,
+            logger: "api",
+            message,
+            context,
+            data: Some(data),
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_inline_fields_and_depth() {
+        let ctx: HashMap<String, String> = [("env".to_string(), "dev".to_string())].into();
+        let record = record("slow request", &ctx, &[("ms", "812"), ("path", "/a b")]);
+        let style = PrettyStyle::new().with_timestamps(TimestampStyle::Relative);
+
+        assert_eq!(
+            render(&record, style, 0),
+            "+2.250s WARN  api slow request  env=dev ms=812 path=\"/a b\""
+        );
+        assert_eq!(
+            render(&record, style, 2),
+            "+2.250s WARN  api     slow request  env=dev ms=812 path=\"/a b\""
+        );
+    }
+
+    #[test]
+    fn test_multiline_message_and_fields_below() {
+        let ctx = HashMap::new();
+        let record = record(
+            "query failed\nsyntax error",
+            &ctx,
+            &[("db", "main"), ("attempt", "2")],
+        );
+        let style = PrettyStyle::new()
+            .with_timestamps(TimestampStyle::Hidden)
+            .with_fields(FieldLayout::Below);
+
+        assert_eq!(
+            render(&record, style, 1),
+            "WARN  api   query failed\n            syntax error\n              db      = main\n              attempt = 2"
+        );
+    }
+
+    #[test]
+    fn test_colors() {
+        let ctx = HashMap::new();
+        let record = record("hi", &ctx, &[]);
+        let theme = ColorTheme::default();
+        let text = PrettyRecord {
+            record: &record,
+            style: PrettyStyle::new().with_timestamps(TimestampStyle::Full),
+            start: Utc::now(),
+            depth: 0,
+            theme: Some(&theme),
+        }
+        .to_string();
+
+        assert_eq!(
+            text,
+            "2025-09-07T10:30:00.250Z \x1b[33mWARN \x1b[0m \x1b[2mapi\x1b[0m hi"
+        );
+        assert_eq!(
+            console_width(&text),
+            "2025-09-07T10:30:00.250Z WARN  api hi".len()
+        );
+    }
+}
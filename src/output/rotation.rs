@@ -0,0 +1,359 @@
+//! Rotation schedules, time-zone handling and segment compression for
+//! [`RotatingFileOutput`].
+//!
+//! A [`RotationSchedule`] splits time into fixed periods (minutes, hours, days
+//! or ISO weeks). Period boundaries are computed in a [`RotationTimeZone`], so a
+//! daily schedule in `Local` rotates at local midnight rather than UTC midnight.
+//!
+//! Rotated segments can be compressed on a background thread with
+//! [`Compression`] (requires the `gzip` or `zstd` feature).
+//!
+//! [`RotatingFileOutput`]: crate::output::RotatingFileOutput
+
+use crate::output::{report_error, ErrorHandler};
+use chrono::{
+    DateTime, Datelike, FixedOffset, Local, NaiveDateTime, NaiveTime, TimeDelta, TimeZone,
+    Timelike, Utc,
+};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+/// How often a rotating file is rolled over regardless of its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RotationSchedule {
+    /// Rotate on size only; rotated files are numbered (`app.1.log`).
+    #[default]
+    Never,
+    /// Rotate at the start of every minute. Mostly useful for tests.
+    Minutely,
+    /// Rotate at the start of every hour.
+    Hourly,
+    /// Rotate at midnight.
+    Daily,
+    /// Rotate at midnight between Sunday and Monday.
+    Weekly,
+}
+
+impl RotationSchedule {
+    /// Returns `true` if this schedule rotates on time boundaries.
+    pub fn is_timed(&self) -> bool {
+        !matches!(self, RotationSchedule::Never)
+    }
+
+    /// Returns the start of the period containing `local`.
+    pub(crate) fn period_start(&self, local: NaiveDateTime) -> NaiveDateTime {
+        let date = local.date();
+        match self {
+            RotationSchedule::Never => local,
+            RotationSchedule::Minutely => local
+                .with_second(0)
+                .and_then(|t| t.with_nanosecond(0))
+                .unwrap_or(local),
+            RotationSchedule::Hourly => date.and_hms_opt(local.hour(), 0, 0).unwrap_or(local),
+            RotationSchedule::Daily => date.and_time(NaiveTime::MIN),
+            RotationSchedule::Weekly => {
+                let back = TimeDelta::days(date.weekday().num_days_from_monday() as i64);
+                (date - back).and_time(NaiveTime::MIN)
+            }
+        }
+    }
+
+    fn period_len(&self) -> TimeDelta {
+        match self {
+            RotationSchedule::Never => TimeDelta::MAX,
+            RotationSchedule::Minutely => TimeDelta::minutes(1),
+            RotationSchedule::Hourly => TimeDelta::hours(1),
+            RotationSchedule::Daily => TimeDelta::days(1),
+            RotationSchedule::Weekly => TimeDelta::weeks(1),
+        }
+    }
+
+    /// Returns the start of the period following the one starting at `start`.
+    pub(crate) fn next_period(&self, start: NaiveDateTime) -> NaiveDateTime {
+        start
+            .checked_add_signed(self.period_len())
+            .unwrap_or(NaiveDateTime::MAX)
+    }
+
+    /// Formats the period starting at `start` for use in a file name.
+    pub(crate) fn label(&self, start: NaiveDateTime) -> String {
+        let fmt = match self {
+            RotationSchedule::Minutely => "%Y-%m-%dT%H-%M",
+            RotationSchedule::Hourly => "%Y-%m-%dT%H",
+            _ => "%Y-%m-%d",
+        };
+        start.format(fmt).to_string()
+    }
+}
+
+/// Time zone in which rotation boundaries and file-name dates are computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RotationTimeZone {
+    /// Coordinated Universal Time.
+    #[default]
+    Utc,
+    /// The system's local time zone, including daylight-saving transitions.
+    Local,
+    /// A fixed offset from UTC, in seconds east of Greenwich.
+    FixedOffset(i32),
+}
+
+impl RotationTimeZone {
+    /// Converts a UTC instant to wall-clock time in this zone.
+    pub(crate) fn to_local(self, t: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            RotationTimeZone::Utc => t.naive_utc(),
+            RotationTimeZone::Local => t.with_timezone(&Local).naive_local(),
+            RotationTimeZone::FixedOffset(secs) => match FixedOffset::east_opt(secs) {
+                Some(offset) => t.with_timezone(&offset).naive_local(),
+                None => t.naive_utc(),
+            },
+        }
+    }
+
+    /// Converts wall-clock time in this zone back to a UTC instant.
+    ///
+    /// Ambiguous local times resolve to the earlier instant; times skipped by a
+    /// daylight-saving jump resolve to the first valid instant after the gap.
+    pub(crate) fn to_utc(self, local: NaiveDateTime) -> DateTime<Utc> {
+        match self {
+            RotationTimeZone::Utc => local.and_utc(),
+            RotationTimeZone::Local => Local
+                .from_local_datetime(&local)
+                .earliest()
+                .or_else(|| {
+                    Local
+                        .from_local_datetime(&(local + TimeDelta::hours(1)))
+                        .earliest()
+                })
+                .map(|t| t.with_timezone(&Utc))
+                .unwrap_or_else(|| local.and_utc()),
+            RotationTimeZone::FixedOffset(secs) => match FixedOffset::east_opt(secs) {
+                Some(offset) => offset
+                    .from_local_datetime(&local)
+                    .single()
+                    .map(|t| t.with_timezone(&Utc))
+                    .unwrap_or_else(|| local.and_utc()),
+                None => local.and_utc(),
+            },
+        }
+    }
+}
+
+/// Compression applied to rotated segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Compression {
+    /// Rotated segments are left as plain files.
+    #[default]
+    None,
+    /// Gzip-compress segments to `*.gz` (requires `gzip` feature).
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// Zstandard-compress segments to `*.zst` (requires `zstd` feature).
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// Returns the file extension appended to compressed segments.
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Some("gz"),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Some("zst"),
+        }
+    }
+
+    /// Compresses `path` into a sibling file with [`extension`](Self::extension)
+    /// appended, then removes the original.
+    ///
+    /// The segment is compressed into a temporary file without holding
+    /// `lock`; the lock is taken only to move the result into place, so
+    /// rotation never waits for compression. If `path` was renamed or deleted
+    /// in the meantime, the temporary file is discarded and `None` returned.
+    ///
+    /// The compressed file keeps the original's modification time so that
+    /// age-based retention treats it as the same segment.
+    pub(crate) fn compress_file(
+        &self,
+        path: &Path,
+        lock: &parking_lot::Mutex<()>,
+    ) -> io::Result<Option<PathBuf>> {
+        let Some(ext) = self.extension() else {
+            return Ok(Some(path.to_path_buf()));
+        };
+        let mut target = path.as_os_str().to_owned();
+        target.push(".");
+        target.push(ext);
+        let target = PathBuf::from(target);
+        let mut tmp = target.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let input = match File::open(path) {
+            Ok(input) => input,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let metadata = input.metadata()?;
+        let output = File::create(&tmp)?;
+        let output = match self {
+            Compression::None => output,
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(output, flate2::Compression::default());
+                io::copy(&mut &input, &mut encoder)?;
+                encoder.finish()?
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let mut encoder = zstd::Encoder::new(output, 0)?;
+                io::copy(&mut &input, &mut encoder)?;
+                encoder.finish()?
+            }
+        };
+        output.set_modified(metadata.modified()?)?;
+        drop(output);
+
+        let _guard = lock.lock();
+        let unchanged = std::fs::metadata(path).is_ok_and(|current| same_file(&metadata, &current));
+        if !unchanged {
+            std::fs::remove_file(&tmp)?;
+            return Ok(None);
+        }
+        std::fs::rename(&tmp, &target)?;
+        std::fs::remove_file(path)?;
+        Ok(Some(target))
+    }
+}
+
+/// Returns whether two metadata snapshots describe the same, unmodified file.
+fn same_file(a: &std::fs::Metadata, b: &std::fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if (a.dev(), a.ino()) != (b.dev(), b.ino()) {
+            return false;
+        }
+    }
+    a.len() == b.len() && a.modified().ok() == b.modified().ok()
+}
+
+/// Background thread that compresses rotated segments off the write path.
+pub(crate) struct Compressor {
+    sender: Option<mpsc::Sender<PathBuf>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Compressor {
+    /// Spawns the compression thread.
+    ///
+    /// `lock` is held while a compressed segment is moved into place so that
+    /// renames performed by the rotating output never race with the
+    /// compressor. Segments moved by a rotation while being compressed are
+    /// queued again by the rotation's cleanup.
+    pub(crate) fn spawn(
+        compression: Compression,
+        lock: std::sync::Arc<parking_lot::Mutex<()>>,
+        handler: Option<ErrorHandler>,
+    ) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::channel::<PathBuf>();
+        let handle = std::thread::Builder::new()
+            .name("telelog-compress".to_string())
+            .spawn(move || {
+                for path in receiver {
+                    if let Err(e) = compression.compress_file(&path, &lock) {
+                        let error = io::Error::new(
+                            e.kind(),
+                            format!("cannot compress {}: {}", path.display(), e),
+                        );
+                        report_error(handler.as_ref(), "RotatingFileOutput", &error);
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            sender: Some(sender),
+            handle: Some(handle),
+        })
+    }
+
+    /// Queues a segment for compression.
+    pub(crate) fn submit(&self, path: PathBuf) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(path);
+        }
+    }
+}
+
+impl Drop for Compressor {
+    fn drop(&mut self) {
+        // Closing the channel lets the thread finish queued segments and exit.
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, s)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_period_start() {
+        let t = at(2026, 10, 16, 13, 45, 12);
+        assert_eq!(
+            RotationSchedule::Minutely.period_start(t),
+            at(2026, 10, 16, 13, 45, 0)
+        );
+        assert_eq!(
+            RotationSchedule::Hourly.period_start(t),
+            at(2026, 10, 16, 13, 0, 0)
+        );
+        assert_eq!(
+            RotationSchedule::Daily.period_start(t),
+            at(2026, 10, 16, 0, 0, 0)
+        );
+        // 2026-10-16 is a Friday; the week starts on Monday the 12th.
+        assert_eq!(
+            RotationSchedule::Weekly.period_start(t),
+            at(2026, 10, 12, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        let t = at(2026, 10, 16, 13, 45, 0);
+        assert_eq!(RotationSchedule::Daily.label(t), "2026-10-16");
+        assert_eq!(RotationSchedule::Hourly.label(t), "2026-10-16T13");
+        assert_eq!(RotationSchedule::Minutely.label(t), "2026-10-16T13-45");
+    }
+
+    #[test]
+    fn test_fixed_offset_boundary() {
+        // 23:30 UTC is already the next day at UTC+02:00.
+        let tz = RotationTimeZone::FixedOffset(2 * 3600);
+        let now = at(2026, 10, 16, 23, 30, 0).and_utc();
+        let start = RotationSchedule::Daily.period_start(tz.to_local(now));
+        assert_eq!(start, at(2026, 10, 17, 0, 0, 0));
+
+        let next = tz.to_utc(RotationSchedule::Daily.next_period(start));
+        assert_eq!(next, at(2026, 10, 17, 22, 0, 0).and_utc());
+    }
+}
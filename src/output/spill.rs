@@ -0,0 +1,812 @@
+//! Disk-backed spill queue in front of a slow or unreliable destination.
+//!
+//! [`SpillOutput`] wraps any [`OutputDestination`]. While the destination keeps
+//! up, records are written to it directly. When a write fails, or takes longer
+//! than the slow threshold, records are appended to segment files in a spill
+//! directory instead, and a `telelog-spill` thread replays them to the
+//! destination in order, retrying until it recovers. Once the backlog is empty,
+//! records go directly to the destination again.
+//!
+//! Segments left behind by a previous process are replayed when the output is
+//! next used, so spilled records survive restarts. Each entry is stored as a
+//! length, a CRC-32 of the payload and the record's JSON; an entry that fails
+//! the check, such as a tail torn by a crash, ends replay of its segment
+//! instead of blocking the queue. Replay progress is saved periodically, so
+//! after a restart at most the last unsaved batch is delivered twice.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use telelog::output::{NetworkOutput, NetworkTransport, SpillOutput};
+//! use std::sync::Arc;
+//!
+//! let collector = NetworkOutput::new(NetworkTransport::Tcp("10.0.0.5:5170".parse().unwrap()));
+//! let spill = SpillOutput::new(Arc::new(collector), "/var/spool/app/logs")
+//!     .unwrap()
+//!     .with_max_bytes(512 * 1024 * 1024);
+//! ```
+
+use crate::output::{
+    report_error, EncodedRecord, ErrorHandler, LogRecord, OutputDestination, OwnedRecord,
+};
+use parking_lot::{Condvar, Mutex};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const DEFAULT_SEGMENT_SIZE: u64 = 8 * 1024 * 1024;
+const DEFAULT_MAX_BYTES: u64 = 256 * 1024 * 1024;
+const DEFAULT_SLOW_THRESHOLD: Duration = Duration::from_millis(100);
+const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+/// Entries larger than this are treated as corrupt.
+const MAX_ENTRY_SIZE: u32 = 64 * 1024 * 1024;
+/// Replayed records between saves of the replay position.
+const CURSOR_INTERVAL: usize = 64;
+const HEADER_SIZE: u64 = 8;
+const SEGMENT_EXTENSION: &str = "seg";
+const CURSOR_FILE: &str = "cursor";
+
+/// CRC-32 (IEEE) lookup table.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &b| {
+        CRC_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// The segment being appended to.
+struct SegmentWriter {
+    seq: u64,
+    file: File,
+    len: u64,
+}
+
+struct State {
+    /// Whether records currently go to disk instead of the destination.
+    spilling: bool,
+    /// Sequence numbers of the segments on disk, oldest first.
+    segments: VecDeque<u64>,
+    writer: Option<SegmentWriter>,
+    next_seq: u64,
+    /// Total size of the segments on disk.
+    bytes: u64,
+    shutdown: bool,
+}
+
+struct Shared {
+    dir: PathBuf,
+    state: Mutex<State>,
+    wake: Condvar,
+    dropped: AtomicU64,
+    corrupted: AtomicU64,
+    skipped: AtomicU64,
+}
+
+impl Shared {
+    fn segment_path(&self, seq: u64) -> PathBuf {
+        self.dir.join(format!("{:020}.{}", seq, SEGMENT_EXTENSION))
+    }
+}
+
+struct Settings {
+    segment_size: u64,
+    max_bytes: u64,
+    slow_threshold: Duration,
+    retry_interval: Duration,
+    error_handler: Option<ErrorHandler>,
+}
+
+/// Spills records to disk while its destination is failing or slow, and
+/// replays them in order once it recovers.
+pub struct SpillOutput {
+    destination: Arc<dyn OutputDestination>,
+    shared: Arc<Shared>,
+    settings: Settings,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl SpillOutput {
+    /// Creates a spill queue for `destination` keeping its segments in `dir`.
+    ///
+    /// The directory is created if needed. Segments already in it are replayed
+    /// ahead of new records, starting with the first write or flush.
+    pub fn new(destination: Arc<dyn OutputDestination>, dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut segments = Vec::new();
+        let mut bytes = 0;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            let seq = path
+                .file_stem()
+                .and_then(|s| s.to_str()?.parse::<u64>().ok());
+            if let Some(seq) = seq {
+                bytes += fs::metadata(&path)?.len();
+                segments.push(seq);
+            }
+        }
+        segments.sort_unstable();
+        let next_seq = segments.last().map_or(0, |seq| seq + 1);
+
+        Ok(Self {
+            destination,
+            shared: Arc::new(Shared {
+                dir,
+                state: Mutex::new(State {
+                    spilling: !segments.is_empty(),
+                    segments: segments.into(),
+                    writer: None,
+                    next_seq,
+                    bytes,
+                    shutdown: false,
+                }),
+                wake: Condvar::new(),
+                dropped: AtomicU64::new(0),
+                corrupted: AtomicU64::new(0),
+                skipped: AtomicU64::new(0),
+            }),
+            settings: Settings {
+                segment_size: DEFAULT_SEGMENT_SIZE,
+                max_bytes: DEFAULT_MAX_BYTES,
+                slow_threshold: DEFAULT_SLOW_THRESHOLD,
+                retry_interval: DEFAULT_RETRY_INTERVAL,
+                error_handler: None,
+            },
+            worker: Mutex::new(None),
+        })
+    }
+
+    /// Sets the size at which a new segment file is started (8 MiB by default).
+    pub fn with_segment_size(mut self, bytes: u64) -> Self {
+        self.settings.segment_size = bytes.max(1);
+        self
+    }
+
+    /// Sets the maximum total size of the spill directory (256 MiB by
+    /// default). Records that would exceed it are dropped.
+    pub fn with_max_bytes(mut self, bytes: u64) -> Self {
+        self.settings.max_bytes = bytes;
+        self
+    }
+
+    /// Sets how long a direct write may take before following records are
+    /// spilled (100ms by default).
+    pub fn with_slow_threshold(mut self, threshold: Duration) -> Self {
+        self.settings.slow_threshold = threshold;
+        self
+    }
+
+    /// Sets how long replay waits after a failed write before retrying (1s by
+    /// default).
+    pub fn with_retry_interval(mut self, interval: Duration) -> Self {
+        self.settings.retry_interval = interval;
+        self
+    }
+
+    /// Reports write, replay and segment errors to `handler` instead of
+    /// stderr.
+    pub fn with_error_handler(mut self, handler: ErrorHandler) -> Self {
+        self.settings.error_handler = Some(handler);
+        self
+    }
+
+    /// Returns whether records are currently being spilled to disk.
+    pub fn is_spilling(&self) -> bool {
+        self.shared.state.lock().spilling
+    }
+
+    /// Returns the total size of the segments waiting to be replayed.
+    pub fn backlog_bytes(&self) -> u64 {
+        self.shared.state.lock().bytes
+    }
+
+    /// Returns how many records were dropped because the spill directory was
+    /// full or could not be written.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many corrupt entries ended replay of a segment early.
+    pub fn corrupted(&self) -> u64 {
+        self.shared.corrupted.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many spilled entries replay skipped without delivering
+    /// them: records that could not be parsed, corrupt entries (also counted
+    /// by [`corrupted`](Self::corrupted)) and segments that could not be
+    /// opened, each counted once.
+    pub fn skipped(&self) -> u64 {
+        self.shared.skipped.load(Ordering::Relaxed)
+    }
+
+    fn report(&self, error: &io::Error) {
+        report_error(self.settings.error_handler.as_ref(), "SpillOutput", error);
+    }
+
+    fn start(&self) -> io::Result<()> {
+        let mut worker = self.worker.lock();
+        if worker.is_none() {
+            let shared = Arc::clone(&self.shared);
+            let destination = Arc::clone(&self.destination);
+            let retry_interval = self.settings.retry_interval;
+            let handler = self.settings.error_handler.clone();
+            let handle = std::thread::Builder::new()
+                .name("telelog-spill".to_string())
+                .spawn(move || {
+                    Replay::new(&shared, &*destination, retry_interval, handler.as_ref()).run()
+                })?;
+            *worker = Some(handle);
+        }
+        Ok(())
+    }
+
+    /// Writes directly unless spilling; on failure or a slow write, spills.
+    fn deliver(
+        &self,
+        direct: impl FnOnce() -> io::Result<()>,
+        payload: impl FnOnce() -> io::Result<Vec<u8>>,
+    ) -> io::Result<()> {
+        self.start()?;
+        if !self.shared.state.lock().spilling {
+            let started = Instant::now();
+            match direct() {
+                Ok(()) => {
+                    if started.elapsed() > self.settings.slow_threshold {
+                        self.shared.state.lock().spilling = true;
+                    }
+                    return Ok(());
+                }
+                Err(e) => self.report(&io::Error::new(
+                    e.kind(),
+                    format!("spilling after write error: {}", e),
+                )),
+            }
+        }
+        self.spill(&payload()?)
+    }
+
+    fn spill(&self, payload: &[u8]) -> io::Result<()> {
+        let entry_len = HEADER_SIZE + payload.len() as u64;
+        let mut state = self.shared.state.lock();
+        if state.bytes + entry_len > self.settings.max_bytes {
+            self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+            return Err(io::Error::other("spill directory is full"));
+        }
+        if state
+            .writer
+            .as_ref()
+            .is_some_and(|w| w.len >= self.settings.segment_size)
+        {
+            state.writer = None;
+        }
+        if state.writer.is_none() {
+            let seq = state.next_seq;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.shared.segment_path(seq))?;
+            state.next_seq += 1;
+            state.segments.push_back(seq);
+            state.writer = Some(SegmentWriter { seq, file, len: 0 });
+        }
+
+        let mut entry = Vec::with_capacity(entry_len as usize);
+        entry.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        entry.extend_from_slice(&crc32(payload).to_le_bytes());
+        entry.extend_from_slice(payload);
+        let writer = state
+            .writer
+            .as_mut()
+            .expect("segment writer was just opened");
+        if let Err(e) = writer.file.write_all(&entry) {
+            self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+            // The segment may now end in a partial entry; start a new one.
+            state.writer = None;
+            return Err(e);
+        }
+        writer.len += entry_len;
+        state.bytes += entry_len;
+        state.spilling = true;
+        drop(state);
+        self.shared.wake.notify_one();
+        Ok(())
+    }
+}
+
+/// Replay position within the oldest segment.
+struct Reader {
+    seq: u64,
+    file: BufReader<File>,
+    offset: u64,
+}
+
+enum Entry {
+    Record(Vec<u8>),
+    /// The readable part of the segment has been consumed.
+    End,
+    Corrupt(String),
+}
+
+/// State of the replay thread.
+struct Replay<'a> {
+    shared: &'a Shared,
+    destination: &'a dyn OutputDestination,
+    retry_interval: Duration,
+    handler: Option<&'a ErrorHandler>,
+    reader: Option<Reader>,
+    unsaved: usize,
+}
+
+impl<'a> Replay<'a> {
+    fn new(
+        shared: &'a Shared,
+        destination: &'a dyn OutputDestination,
+        retry_interval: Duration,
+        handler: Option<&'a ErrorHandler>,
+    ) -> Self {
+        Self {
+            shared,
+            destination,
+            retry_interval,
+            handler,
+            reader: None,
+            unsaved: 0,
+        }
+    }
+
+    fn report(&self, message: String) {
+        report_error(self.handler, "SpillOutput", &io::Error::other(message));
+    }
+
+    fn run(mut self) {
+        loop {
+            // Wait for a segment; note how far it may be read if it is still
+            // being appended to.
+            let (seq, limit) = {
+                let mut state = self.shared.state.lock();
+                loop {
+                    if state.shutdown {
+                        drop(state);
+                        self.save_cursor();
+                        return;
+                    }
+                    if let Some(&seq) = state.segments.front() {
+                        let limit = state
+                            .writer
+                            .as_ref()
+                            .filter(|w| w.seq == seq)
+                            .map(|w| w.len);
+                        break (seq, limit);
+                    }
+                    self.shared.wake.wait(&mut state);
+                }
+            };
+
+            if self.reader.as_ref().map(|r| r.seq) != Some(seq) {
+                if let Err(e) = self.open(seq) {
+                    self.shared.skipped.fetch_add(1, Ordering::Relaxed);
+                    self.report(format!("cannot open segment {}: {}", seq, e));
+                    self.finish_segment(seq, None);
+                    continue;
+                }
+            }
+
+            match self.next_entry(limit) {
+                Entry::Record(payload) => {
+                    if !self.replay(&payload) {
+                        continue;
+                    }
+                    if let Some(reader) = self.reader.as_mut() {
+                        reader.offset += HEADER_SIZE + payload.len() as u64;
+                    }
+                    self.unsaved += 1;
+                    if self.unsaved >= CURSOR_INTERVAL {
+                        self.save_cursor();
+                    }
+                }
+                Entry::End => self.finish_segment(seq, limit),
+                Entry::Corrupt(reason) => {
+                    self.shared.corrupted.fetch_add(1, Ordering::Relaxed);
+                    self.shared.skipped.fetch_add(1, Ordering::Relaxed);
+                    self.report(format!("skipping rest of segment {}: {}", seq, reason));
+                    self.finish_segment(seq, None);
+                }
+            }
+        }
+    }
+
+    fn open(&mut self, seq: u64) -> io::Result<()> {
+        let mut file = File::open(self.shared.segment_path(seq))?;
+        let offset = match self.read_cursor() {
+            Some((cursor_seq, offset)) if cursor_seq == seq => offset,
+            _ => 0,
+        };
+        file.seek(SeekFrom::Start(offset))?;
+        self.reader = Some(Reader {
+            seq,
+            file: BufReader::new(file),
+            offset,
+        });
+        Ok(())
+    }
+
+    fn next_entry(&mut self, limit: Option<u64>) -> Entry {
+        let Some(reader) = self.reader.as_mut() else {
+            return Entry::End;
+        };
+        if limit.is_some_and(|limit| reader.offset >= limit) {
+            return Entry::End;
+        }
+        let mut header = [0u8; HEADER_SIZE as usize];
+        match read_full(&mut reader.file, &mut header) {
+            Ok(0) => return Entry::End,
+            Ok(n) if n < header.len() => return Entry::Corrupt("truncated entry header".into()),
+            Ok(_) => {}
+            Err(e) => return Entry::Corrupt(e.to_string()),
+        }
+        let len = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let crc = u32::from_le_bytes(header[4..].try_into().unwrap());
+        if len > MAX_ENTRY_SIZE {
+            return Entry::Corrupt(format!("entry length {} is too large", len));
+        }
+        let mut payload = vec![0; len as usize];
+        match read_full(&mut reader.file, &mut payload) {
+            Ok(n) if n < payload.len() => return Entry::Corrupt("truncated entry".into()),
+            Ok(_) => {}
+            Err(e) => return Entry::Corrupt(e.to_string()),
+        }
+        if crc32(&payload) != crc {
+            return Entry::Corrupt("checksum mismatch".into());
+        }
+        Entry::Record(payload)
+    }
+
+    /// Writes one spilled record, retrying until it succeeds. Returns `false`
+    /// if shutdown was requested first.
+    fn replay(&mut self, payload: &[u8]) -> bool {
+        let record = match OwnedRecord::from_json(payload) {
+            Ok(record) => record,
+            Err(e) => {
+                self.shared.skipped.fetch_add(1, Ordering::Relaxed);
+                self.report(format!("skipping unreadable record: {}", e));
+                return true;
+            }
+        };
+        let mut reported = false;
+        loop {
+            match record.with_record(|r| self.destination.write(r)) {
+                Ok(()) => return true,
+                Err(e) => {
+                    if !reported {
+                        self.report(format!("replay failed, retrying: {}", e));
+                        reported = true;
+                    }
+                    self.save_cursor();
+                    let mut state = self.shared.state.lock();
+                    if !state.shutdown {
+                        self.shared.wake.wait_for(&mut state, self.retry_interval);
+                    }
+                    if state.shutdown {
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes a consumed segment. If it is the segment being appended to and
+    /// everything written has been replayed, spilling ends.
+    fn finish_segment(&mut self, seq: u64, limit: Option<u64>) {
+        let mut state = self.shared.state.lock();
+        if let Some(writer) = state.writer.as_ref().filter(|w| w.seq == seq) {
+            if limit.is_some_and(|limit| limit < writer.len) {
+                // More was appended since `limit` was taken.
+                return;
+            }
+            state.writer = None;
+        }
+        let size = fs::metadata(self.shared.segment_path(seq)).map_or(0, |m| m.len());
+        if let Err(e) = fs::remove_file(self.shared.segment_path(seq)) {
+            if e.kind() != io::ErrorKind::NotFound {
+                self.report(format!("cannot remove segment {}: {}", seq, e));
+            }
+        }
+        state.segments.retain(|&s| s != seq);
+        state.bytes = state.bytes.saturating_sub(size);
+        let empty = state.segments.is_empty();
+        if empty {
+            state.spilling = false;
+        }
+        drop(state);
+
+        self.reader = None;
+        if let Err(e) = self.destination.flush() {
+            report_error(self.handler, self.destination.name(), &e);
+        }
+        self.unsaved = 0;
+        let _ = fs::remove_file(self.shared.dir.join(CURSOR_FILE));
+    }
+
+    fn read_cursor(&self) -> Option<(u64, u64)> {
+        let text = fs::read_to_string(self.shared.dir.join(CURSOR_FILE)).ok()?;
+        let (seq, offset) = text.trim().split_once(' ')?;
+        Some((seq.parse().ok()?, offset.parse().ok()?))
+    }
+
+    /// Flushes the destination and records how far replay has got.
+    fn save_cursor(&mut self) {
+        if self.unsaved == 0 {
+            return;
+        }
+        let Some(reader) = self.reader.as_ref() else {
+            return;
+        };
+        if let Err(e) = self.destination.flush() {
+            report_error(self.handler, self.destination.name(), &e);
+            return;
+        }
+        let tmp = self.shared.dir.join(format!("{}.tmp", CURSOR_FILE));
+        let result = fs::write(&tmp, format!("{} {}\n", reader.seq, reader.offset))
+            .and_then(|()| fs::rename(&tmp, self.shared.dir.join(CURSOR_FILE)));
+        match result {
+            Ok(()) => self.unsaved = 0,
+            Err(e) => self.report(format!("cannot save replay position: {}", e)),
+        }
+    }
+}
+
+/// Reads until `buf` is full or the end of the file; returns the bytes read.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+impl OutputDestination for SpillOutput {
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        EncodedRecord::scope(record, |encoded| self.write_encoded(encoded))
+    }
+
+    fn write_encoded(&self, encoded: &EncodedRecord<'_, '_>) -> io::Result<()> {
+        self.deliver(
+            || self.destination.write_encoded(encoded),
+            || Ok(encoded.json().trim_ascii_end().to_vec()),
+        )
+    }
+
+    /// Syncs the current segment to disk and flushes the destination.
+    fn flush(&self) -> io::Result<()> {
+        self.start()?;
+        if let Some(writer) = self.shared.state.lock().writer.as_ref() {
+            writer.file.sync_data()?;
+        }
+        self.destination.flush()
+    }
+
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        self.deliver(
+            || self.destination.write_bytes(bytes),
+            || Ok(bytes.trim_ascii_end().to_vec()),
+        )
+    }
+
+    fn reopen(&self) -> io::Result<()> {
+        self.destination.reopen()
+    }
+}
+
+impl Drop for SpillOutput {
+    /// Stops replay; segments not yet replayed stay on disk for the next run.
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.get_mut().take() {
+            self.shared.state.lock().shutdown = true;
+            self.shared.wake.notify_all();
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::LogLevel;
+    use crate::output::MemoryOutput;
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicBool;
+
+    /// A destination that fails while `failing` is set.
+    struct Flaky {
+        failing: AtomicBool,
+        memory: MemoryOutput,
+    }
+
+    impl Flaky {
+        fn new(failing: bool) -> Arc<Self> {
+            Arc::new(Self {
+                failing: AtomicBool::new(failing),
+                memory: MemoryOutput::new(100),
+            })
+        }
+
+        fn messages(&self) -> Vec<String> {
+            self.memory
+                .snapshot()
+                .iter()
+                .map(|r| r.message.clone())
+                .collect()
+        }
+    }
+
+    impl OutputDestination for Flaky {
+        fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(io::Error::new(io::ErrorKind::ConnectionRefused, "down"));
+            }
+            self.memory.write(record)
+        }
+
+        fn flush(&self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn write(output: &SpillOutput, message: &str) -> io::Result<()> {
+        let ctx = HashMap::new();
+        output.write(&LogRecord {
+            timestamp: "2025-09-07T10:30:00Z",
+            level: LogLevel::Info,
+            logger: "spill",
+            message,
+            context: &ctx,
+            data: Some(&[("n", message)]),
+            location: None,
+        })
+    }
+
+    fn wait_until_drained(output: &SpillOutput) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while output.is_spilling() {
+            assert!(Instant::now() < deadline, "backlog was not replayed");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_spills_while_failing_and_replays_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let flaky = Flaky::new(false);
+        let output = SpillOutput::new(flaky.clone(), dir.path())
+            .unwrap()
+            .with_retry_interval(Duration::from_millis(10));
+
+        write(&output, "a").unwrap();
+        assert!(!output.is_spilling());
+
+        flaky.failing.store(true, Ordering::SeqCst);
+        write(&output, "b").unwrap();
+        write(&output, "c").unwrap();
+        assert!(output.is_spilling());
+        assert!(output.backlog_bytes() > 0);
+
+        flaky.failing.store(false, Ordering::SeqCst);
+        wait_until_drained(&output);
+        write(&output, "d").unwrap();
+
+        assert_eq!(flaky.messages(), ["a", "b", "c", "d"]);
+        assert_eq!(flaky.memory.snapshot()[1].field("n"), Some("b"));
+        assert_eq!(output.backlog_bytes(), 0);
+    }
+
+    #[test]
+    fn test_backlog_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = SpillOutput::new(Flaky::new(true), dir.path())
+            .unwrap()
+            .with_segment_size(1);
+        for message in ["a", "b", "c"] {
+            write(&output, message).unwrap();
+        }
+        drop(output);
+
+        let flaky = Flaky::new(false);
+        let output = SpillOutput::new(flaky.clone(), dir.path()).unwrap();
+        assert!(output.is_spilling());
+        write(&output, "d").unwrap();
+        wait_until_drained(&output);
+        assert_eq!(flaky.messages(), ["a", "b", "c", "d"]);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_corrupt_tail_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = SpillOutput::new(Flaky::new(true), dir.path())
+            .unwrap()
+            .with_segment_size(1);
+        for message in ["a", "b"] {
+            write(&output, message).unwrap();
+        }
+        drop(output);
+        // Simulate a crash in the middle of appending to the first segment.
+        let mut first = OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(format!("{:020}.seg", 0)))
+            .unwrap();
+        first.write_all(&[200, 0, 0, 0, 1, 2]).unwrap();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let handler = {
+            let seen = Arc::clone(&seen);
+            ErrorHandler::new(move |name, e| seen.lock().push(format!("{}: {}", name, e)))
+        };
+        let flaky = Flaky::new(false);
+        let output = SpillOutput::new(flaky.clone(), dir.path())
+            .unwrap()
+            .with_error_handler(handler);
+        output.flush().unwrap();
+        wait_until_drained(&output);
+        assert_eq!(flaky.messages(), ["a", "b"]);
+        assert_eq!(output.corrupted(), 1);
+        assert_eq!(output.skipped(), 1);
+        let seen = seen.lock();
+        assert_eq!(seen.len(), 1);
+        assert!(seen[0].starts_with("SpillOutput: skipping rest of segment 0"));
+    }
+
+    #[test]
+    fn test_size_cap_drops_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = SpillOutput::new(Flaky::new(true), dir.path())
+            .unwrap()
+            .with_max_bytes(200);
+        let mut accepted = 0;
+        for _ in 0..10 {
+            if write(&output, "record").is_ok() {
+                accepted += 1;
+            }
+        }
+        assert!(accepted > 0 && accepted < 10);
+        assert_eq!(output.dropped(), 10 - accepted);
+        assert!(output.backlog_bytes() <= 200);
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+}
@@ -0,0 +1,524 @@
+//! Syslog output destination (RFC 5424 and RFC 3164).
+//!
+//! [`SyslogOutput`] sends each record to a syslog daemon such as rsyslog or
+//! syslog-ng over a Unix datagram socket (usually `/dev/log`), UDP, or TCP with
+//! octet-counting framing (RFC 6587 §3.4.1).
+//!
+//! In RFC 5424 format, context and structured data are carried as two
+//! structured-data elements, `context@<enterprise-id>` and
+//! `data@<enterprise-id>`. In RFC 3164 format they are appended to the message
+//! as `key=value` pairs, like plain-text file output.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use telelog::output::{Facility, SyslogOutput, SyslogTransport};
+//!
+//! let syslog = SyslogOutput::new(SyslogTransport::Unix("/dev/log".into()))
+//!     .unwrap()
+//!     .with_facility(Facility::Local0)
+//!     .with_app_name("billing");
+//! ```
+
+use crate::level::LogLevel;
+use crate::output::format::write_fields;
+use crate::output::{LogRecord, OutputDestination, OwnedRecord};
+use parking_lot::Mutex;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Enterprise number reserved for documentation (RFC 5612), used in SD-IDs by default.
+const DEFAULT_ENTERPRISE_ID: u32 = 32473;
+
+/// Syslog facility codes (RFC 5424 §6.2.1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Facility {
+    Kern = 0,
+    #[default]
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    AuthPriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+/// Syslog message format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyslogFormat {
+    /// The structured IETF format with structured-data elements.
+    #[default]
+    Rfc5424,
+    /// The legacy BSD format understood by every syslog daemon.
+    Rfc3164,
+}
+
+/// Where syslog messages are sent.
+#[derive(Debug, Clone)]
+pub enum SyslogTransport {
+    /// A local Unix datagram socket, e.g. `/dev/log`.
+    #[cfg(unix)]
+    Unix(PathBuf),
+    /// A remote collector over UDP; one datagram per record.
+    Udp(SocketAddr),
+    /// A remote collector over TCP with octet-counting framing.
+    Tcp(SocketAddr),
+}
+
+enum Connection {
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+impl Connection {
+    fn open(transport: &SyslogTransport) -> io::Result<Self> {
+        match transport {
+            #[cfg(unix)]
+            SyslogTransport::Unix(path) => {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(path)?;
+                Ok(Connection::Unix(socket))
+            }
+            SyslogTransport::Udp(addr) => {
+                let local: SocketAddr = if addr.is_ipv4() {
+                    ([0, 0, 0, 0], 0).into()
+                } else {
+                    ([0u16; 8], 0).into()
+                };
+                let socket = UdpSocket::bind(local)?;
+                socket.connect(addr)?;
+                Ok(Connection::Udp(socket))
+            }
+            SyslogTransport::Tcp(addr) => {
+                let stream = TcpStream::connect_timeout(addr, Duration::from_secs(5))?;
+                stream.set_nodelay(true)?;
+                Ok(Connection::Tcp(stream))
+            }
+        }
+    }
+
+    fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Connection::Unix(socket) => socket.send(message).map(|_| ()),
+            Connection::Udp(socket) => socket.send(message).map(|_| ()),
+            Connection::Tcp(stream) => {
+                let mut frame = Vec::with_capacity(message.len() + 8);
+                write!(frame, "{} ", message.len())?;
+                frame.extend_from_slice(message);
+                stream.write_all(&frame)
+            }
+        }
+    }
+}
+
+/// Sends log records to a syslog daemon.
+///
+/// Log levels map to syslog severities via [`LogLevel::syslog_severity`].
+/// The connection is opened on construction and transparently re-established
+/// once if a send fails.
+pub struct SyslogOutput {
+    transport: SyslogTransport,
+    connection: Mutex<Option<Connection>>,
+    format: SyslogFormat,
+    facility: Facility,
+    app_name: Option<String>,
+    hostname: String,
+    enterprise_id: u32,
+}
+
+impl SyslogOutput {
+    /// Creates a syslog output and connects to `transport`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket cannot be opened or connected.
+    pub fn new(transport: SyslogTransport) -> io::Result<Self> {
+        let connection = Connection::open(&transport)?;
+        Ok(Self {
+            transport,
+            connection: Mutex::new(Some(connection)),
+            format: SyslogFormat::Rfc5424,
+            facility: Facility::User,
+            app_name: None,
+            hostname: super::hostname().unwrap_or_else(|| "-".to_string()),
+            enterprise_id: DEFAULT_ENTERPRISE_ID,
+        })
+    }
+
+    /// Sets the message format (RFC 5424 by default).
+    pub fn with_format(mut self, format: SyslogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the facility (`user` by default).
+    pub fn with_facility(mut self, facility: Facility) -> Self {
+        self.facility = facility;
+        self
+    }
+
+    /// Sets the APP-NAME / TAG field. Defaults to the record's logger name.
+    pub fn with_app_name(mut self, app_name: &str) -> Self {
+        self.app_name = Some(app_name.to_string());
+        self
+    }
+
+    /// Overrides the HOSTNAME field, which defaults to this machine's host name.
+    pub fn with_hostname(mut self, hostname: &str) -> Self {
+        self.hostname = hostname.to_string();
+        self
+    }
+
+    /// Sets the private enterprise number used in structured-data IDs.
+    pub fn with_enterprise_id(mut self, enterprise_id: u32) -> Self {
+        self.enterprise_id = enterprise_id;
+        self
+    }
+
+    fn priority(&self, level: LogLevel) -> u8 {
+        (self.facility as u8) * 8 + level.syslog_severity()
+    }
+
+    /// Formats `record` as a single syslog message.
+    pub(crate) fn format_record(&self, record: &LogRecord<'_>) -> String {
+        let app_name = sanitize_header(self.app_name.as_deref().unwrap_or(record.logger), 48);
+        let mut out = String::with_capacity(256);
+
+        match self.format {
+            SyslogFormat::Rfc5424 => {
+                let _ = write!(
+                    out,
+                    "<{}>1 {} {} {} {} - ",
+                    self.priority(record.level),
+                    rfc5424_timestamp(record.timestamp),
+                    sanitize_header(&self.hostname, 255),
+                    app_name,
+                    std::process::id(),
+                );
+                self.write_structured_data(&mut out, record);
+                out.push(' ');
+                out.push_str(record.message);
+            }
+            SyslogFormat::Rfc3164 => {
+                let timestamp = chrono::DateTime::parse_from_rfc3339(record.timestamp)
+                    .map(|t| t.format("%b %e %H:%M:%S").to_string())
+                    .unwrap_or_else(|_| chrono::Local::now().format("%b %e %H:%M:%S").to_string());
+                let tag: String = app_name
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+                    .take(32)
+                    .collect();
+                let _ = write!(
+                    out,
+                    "<{}>{} {} {}[{}]: {}",
+                    self.priority(record.level),
+                    timestamp,
+                    sanitize_header(&self.hostname, 255),
+                    tag,
+                    std::process::id(),
+                    record.message,
+                );
+                let _ = write_fields(&mut out, record);
+            }
+        }
+        out
+    }
+
+    fn write_structured_data(&self, out: &mut String, record: &LogRecord<'_>) {
+        let data = record.data.unwrap_or_default();
+        if record.context.is_empty() && data.is_empty() {
+            out.push('-');
+            return;
+        }
+
+        if !record.context.is_empty() {
+            let mut keys: Vec<&String> = record.context.keys().collect();
+            keys.sort_unstable();
+            let _ = write!(out, "[context@{}", self.enterprise_id);
+            for key in keys {
+                write_sd_param(out, key, &record.context[key]);
+            }
+            out.push(']');
+        }
+
+        if !data.is_empty() {
+            let _ = write!(out, "[data@{}", self.enterprise_id);
+            for (key, value) in data {
+                write_sd_param(out, key, value);
+            }
+            out.push(']');
+        }
+    }
+
+    fn send(&self, message: &[u8]) -> io::Result<()> {
+        let mut connection = self.connection.lock();
+        if let Some(conn) = connection.as_mut() {
+            if conn.send(message).is_ok() {
+                return Ok(());
+            }
+        }
+
+        // Reconnect once; the daemon may have restarted or the peer closed TCP.
+        *connection = None;
+        let mut conn = Connection::open(&self.transport)?;
+        conn.send(message)?;
+        *connection = Some(conn);
+        Ok(())
+    }
+}
+
+impl OutputDestination for SyslogOutput {
+    fn write(&self, record: &LogRecord<'_>) -> io::Result<()> {
+        let message = self.format_record(record);
+        self.send(message.as_bytes())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        if let Some(Connection::Tcp(stream)) = self.connection.lock().as_mut() {
+            stream.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Parses the pre-serialized JSON record so that its level and fields
+    /// are kept.
+    fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        OwnedRecord::from_json(bytes)?.with_record(|record| self.write(record))
+    }
+}
+
+/// Reformats an RFC 3339 timestamp with at most six fractional digits, as
+/// RFC 5424 §6.2.3 requires, or returns the nil value if it cannot be parsed.
+fn rfc5424_timestamp(timestamp: &str) -> String {
+    use chrono::Timelike;
+
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .and_then(|t| t.with_nanosecond(t.nanosecond() / 1000 * 1000))
+        .map_or_else(
+            || "-".to_string(),
+            |t| t.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, false),
+        )
+}
+
+/// Replaces characters not allowed in header fields and applies the length limit.
+fn sanitize_header(value: &str, max_len: usize) -> String {
+    let cleaned: String = value
+        .chars()
+        .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+        .take(max_len)
+        .collect();
+    if cleaned.is_empty() {
+        "-".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// Writes ` name="value"`, sanitizing the name and escaping the value per RFC 5424 §6.3.3.
+fn write_sd_param(out: &mut String, name: &str, value: &str) {
+    out.push(' ');
+    let mut written = 0;
+    for c in name.chars().take(32) {
+        let valid = c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"');
+        out.push(if valid { c } else { '_' });
+        written += 1;
+    }
+    if written == 0 {
+        out.push('_');
+    }
+    out.push_str("=\"");
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+
+    fn record<'a>(
+        context: &'a HashMap<String, String>,
+        data: Option<&'a [(&'a str, &'a str)]>,
+    ) -> LogRecord<'a> {
+        LogRecord {
+            timestamp: "2025-09-07T10:30:00+00:00",
+            level: LogLevel::Error,
+            logger: "api",
+            message: "Payment failed",
+            context,
+            data,
+            location: None,
+        }
+    }
+
+    fn udp_pair() -> (UdpSocket, SyslogOutput) {
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let output = SyslogOutput::new(SyslogTransport::Udp(listener.local_addr().unwrap()))
+            .unwrap()
+            .with_hostname("web-1");
+        (listener, output)
+    }
+
+    #[test]
+    fn test_rfc5424_structured_data() {
+        let (listener, output) = udp_pair();
+        let output = output.with_facility(Facility::Local0);
+        let mut ctx = HashMap::new();
+        ctx.insert("request_id".to_string(), "abc".to_string());
+        let data = [("reason", "card \"declined\" [x]")];
+
+        output.write(&record(&ctx, Some(&data))).unwrap();
+
+        let mut buf = [0u8; 1024];
+        let n = listener.recv(&mut buf).unwrap();
+        let message = std::str::from_utf8(&buf[..n]).unwrap();
+        // local0 (16) * 8 + err (3) = 131
+        let expected = format!(
+            "<131>1 2025-09-07T10:30:00+00:00 web-1 api {} - \
+             [context@32473 request_id=\"abc\"]\
+             [data@32473 reason=\"card \\\"declined\\\" [x\\]\"] Payment failed",
+            std::process::id()
+        );
+        assert_eq!(message, expected);
+    }
+
+    #[test]
+    fn test_rfc5424_logger_timestamp() {
+        use crate::output::MemoryOutput;
+        use crate::{Config, Logger};
+        use std::sync::Arc;
+
+        let memory = Arc::new(MemoryOutput::new(1));
+        let logger = Logger::with_config("api", Config::new().with_console_output(false));
+        logger.add_output(Box::new(Arc::clone(&memory)));
+        logger.info("hello");
+        let record = memory.drain().pop().unwrap();
+
+        let (_listener, output) = udp_pair();
+        let message = record.with_record(|r| output.format_record(r));
+        let timestamp = message.split(' ').nth(1).unwrap();
+        let fraction = timestamp
+            .split_once('.')
+            .map_or("", |(_, rest)| rest.trim_end_matches("+00:00"));
+        assert!(fraction.len() <= 6, "{}", timestamp);
+        assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+        assert_eq!(rfc5424_timestamp("not a time"), "-");
+        assert_eq!(
+            rfc5424_timestamp("2025-09-07T10:30:00.123456789+02:00"),
+            "2025-09-07T10:30:00.123456+02:00"
+        );
+    }
+
+    #[test]
+    fn test_write_bytes_keeps_level_and_fields() {
+        let (listener, output) = udp_pair();
+        output
+            .write_bytes(b"{\"timestamp\":\"2025-09-07T10:30:00+00:00\",\"level\":\"ERROR\",\"logger\":\"api\",\"message\":\"boom\",\"code\":500}\n")
+            .unwrap();
+
+        let mut buf = [0u8; 1024];
+        let n = listener.recv(&mut buf).unwrap();
+        let expected = format!(
+            "<11>1 2025-09-07T10:30:00+00:00 web-1 api {} - [data@32473 code=\"500\"] boom",
+            std::process::id()
+        );
+        assert_eq!(std::str::from_utf8(&buf[..n]).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_rfc3164_format() {
+        let (listener, output) = udp_pair();
+        let output = output
+            .with_format(SyslogFormat::Rfc3164)
+            .with_app_name("billing");
+        let ctx = HashMap::new();
+
+        output
+            .write(&record(&ctx, Some(&[("user_id", "42")])))
+            .unwrap();
+
+        let mut buf = [0u8; 1024];
+        let n = listener.recv(&mut buf).unwrap();
+        let message = std::str::from_utf8(&buf[..n]).unwrap();
+        assert_eq!(
+            message,
+            format!(
+                "<11>Sep  7 10:30:00 web-1 billing[{}]: Payment failed user_id=42",
+                std::process::id()
+            )
+        );
+    }
+
+    #[test]
+    fn test_tcp_octet_counting() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let output =
+            SyslogOutput::new(SyslogTransport::Tcp(listener.local_addr().unwrap())).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let ctx = HashMap::new();
+
+        output.write(&record(&ctx, None)).unwrap();
+        output.flush().unwrap();
+
+        let mut reader = BufReader::new(stream);
+        let mut len = Vec::new();
+        reader.read_until(b' ', &mut len).unwrap();
+        let len: usize = std::str::from_utf8(&len).unwrap().trim().parse().unwrap();
+        let mut message = vec![0u8; len];
+        reader.read_exact(&mut message).unwrap();
+        let message = String::from_utf8(message).unwrap();
+        assert!(message.starts_with("<11>1 "));
+        assert!(message.ends_with(" - - Payment failed"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_datagram() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.sock");
+        let listener = UnixDatagram::bind(&path).unwrap();
+        let output = SyslogOutput::new(SyslogTransport::Unix(path)).unwrap();
+        let ctx = HashMap::new();
+
+        output.write(&record(&ctx, None)).unwrap();
+
+        let mut buf = [0u8; 1024];
+        let n = listener.recv(&mut buf).unwrap();
+        assert!(std::str::from_utf8(&buf[..n])
+            .unwrap()
+            .ends_with("Payment failed"));
+    }
+}
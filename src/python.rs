@@ -113,17 +113,11 @@ impl Config {
         slf
     }
 
-    #[cfg(feature = "async")]
     fn with_async<'py>(slf: pyo3::Bound<'py, Self>, enabled: bool) -> pyo3::Bound<'py, Self> {
         slf.borrow_mut().inner = slf.borrow().inner.clone().with_async(enabled);
         slf
     }
 
-    #[cfg(not(feature = "async"))]
-    fn with_async<'py>(slf: pyo3::Bound<'py, Self>, _enabled: bool) -> pyo3::Bound<'py, Self> {
-        slf // No-op if async feature is missing
-    }
-
     fn with_buffering<'py>(slf: pyo3::Bound<'py, Self>, enabled: bool) -> pyo3::Bound<'py, Self> {
         slf.borrow_mut().inner = slf.borrow().inner.clone().with_buffering(enabled);
         slf
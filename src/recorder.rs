@@ -0,0 +1,421 @@
+//! Flight recorder keeping filtered-out records for replay ahead of errors.
+//!
+//! When a [`FlightRecorderConfig`] is set, records below the logger's minimum
+//! level are not discarded but copied into a small ring buffer per thread, or
+//! per value of a context key such as `request_id`. When a record at the
+//! trigger level is logged, the buffered records of the same thread or request
+//! are written first, each marked with `backfilled=true`.
+//!
+//! Capturing copies the record's strings into a ring slot whose allocation is
+//! reused, and defers building records and formatting timestamps until they
+//! are replayed, so nothing is allocated or serialized in the steady state
+//! unless an error occurs. Thread buffers are thread-local and request buffers
+//! are spread over independently locked shards, so concurrent threads do not
+//! contend on a single lock.
+//!
+//! # Example
+//!
+//! ```
+//! use telelog::config::FlightRecorderConfig;
+//! use telelog::output::MemoryOutput;
+//! use telelog::{Config, LogLevel, Logger};
+//! use std::sync::Arc;
+//!
+//! let config = Config::new()
+//!     .with_console_output(false)
+//!     .with_min_level(LogLevel::Info)
+//!     .with_flight_recorder(FlightRecorderConfig::new().with_capacity(50));
+//! let logger = Logger::with_config("api", config);
+//! let memory = Arc::new(MemoryOutput::new(100));
+//! logger.add_output(Box::new(Arc::clone(&memory)));
+//!
+//! logger.debug("cache miss");
+//! assert!(memory.is_empty());
+//!
+//! logger.error("query failed");
+//! let records = memory.snapshot();
+//! assert_eq!(records[0].message, "cache miss");
+//! assert_eq!(records[0].field("backfilled"), Some("true"));
+//! assert_eq!(records[1].message, "query failed");
+//! ```
+
+use crate::config::FlightRecorderConfig;
+use crate::level::LogLevel;
+use crate::output::OwnedRecord;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::panic::Location;
+use std::sync::{Arc, Weak};
+
+/// Aim for at least this many request buffers per shard, so that evicting
+/// the least recently used buffer of a shard stays close to a global LRU.
+const BUFFERS_PER_SHARD: usize = 64;
+const MAX_SHARDS: usize = 16;
+
+thread_local! {
+    /// This thread's buffers, one per live recorder, identified by the
+    /// recorder's token.
+    static THREAD_RINGS: RefCell<Vec<(Weak<()>, Ring)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A captured record. The strings are copied into `text`, whose allocation is
+/// reused once the ring wraps, and the timestamp is formatted only on replay.
+#[derive(Default)]
+struct Slot {
+    time: DateTime<Utc>,
+    level: Option<LogLevel>,
+    location: Option<&'static Location<'static>>,
+    /// Logger name, message, context keys and values, then data keys and
+    /// values, back to back.
+    text: String,
+    /// End offset in `text` of each string.
+    ends: Vec<usize>,
+    context_len: usize,
+}
+
+impl Slot {
+    fn push_str(&mut self, value: &str) {
+        self.text.push_str(value);
+        self.ends.push(self.text.len());
+    }
+
+    fn to_record(&self) -> OwnedRecord {
+        let mut start = 0;
+        let mut strings = self.ends.iter().map(|&end| {
+            let value = &self.text[start..end];
+            start = end;
+            value.to_owned()
+        });
+        let logger = strings.next().unwrap_or_default();
+        let message = strings.next().unwrap_or_default();
+        let mut pairs = std::iter::from_fn(|| Some((strings.next()?, strings.next()?)));
+        let context = pairs.by_ref().take(self.context_len).collect();
+        let mut data: Vec<_> = pairs.collect();
+        data.push(("backfilled".to_string(), "true".to_string()));
+        OwnedRecord {
+            timestamp: self.time.to_rfc3339(),
+            level: self.level.unwrap_or(LogLevel::Debug),
+            logger,
+            message,
+            context,
+            data,
+            location: self.location,
+        }
+    }
+}
+
+/// Fixed-capacity ring of captured records that overwrites the oldest.
+#[derive(Default)]
+struct Ring {
+    slots: Vec<Slot>,
+    /// Index of the oldest record.
+    head: usize,
+    len: usize,
+}
+
+impl Ring {
+    /// Returns the slot for a new record, reusing the oldest once full.
+    fn next_slot(&mut self, capacity: usize) -> &mut Slot {
+        let index = (self.head + self.len) % capacity;
+        if self.len == capacity {
+            self.head = (self.head + 1) % capacity;
+        } else {
+            self.len += 1;
+        }
+        // Until the ring first wraps, `head` is 0 and `index` is the next
+        // unallocated slot.
+        if index == self.slots.len() {
+            self.slots.push(Slot::default());
+        }
+        &mut self.slots[index]
+    }
+
+    /// Returns the records oldest first and empties the ring, keeping its
+    /// allocations.
+    fn take(&mut self) -> Vec<OwnedRecord> {
+        let capacity = self.slots.len().max(1);
+        let records = (0..self.len)
+            .map(|i| self.slots[(self.head + i) % capacity].to_record())
+            .collect();
+        self.head = 0;
+        self.len = 0;
+        records
+    }
+}
+
+struct Keyed {
+    ring: Ring,
+    last_used: u64,
+}
+
+/// Request buffers whose key hashes to the same shard.
+#[derive(Default)]
+struct Shard {
+    rings: HashMap<String, Keyed>,
+    clock: u64,
+}
+
+pub(crate) struct FlightRecorder {
+    config: FlightRecorderConfig,
+    /// Identifies this recorder's buffers in each thread's `THREAD_RINGS`.
+    token: Arc<()>,
+    shards: Box<[Mutex<Shard>]>,
+    /// Maximum request buffers per shard.
+    shard_limit: usize,
+    hasher: RandomState,
+}
+
+impl FlightRecorder {
+    pub(crate) fn new(config: FlightRecorderConfig) -> Self {
+        let shard_count = (config.max_buffers / BUFFERS_PER_SHARD).clamp(1, MAX_SHARDS);
+        Self {
+            shard_limit: config.max_buffers.div_ceil(shard_count),
+            shards: (0..shard_count).map(|_| Mutex::default()).collect(),
+            token: Arc::new(()),
+            hasher: RandomState::new(),
+            config,
+        }
+    }
+
+    /// Returns whether records at `level` are captured instead of discarded.
+    #[inline]
+    pub(crate) fn captures(&self, level: LogLevel) -> bool {
+        level >= self.config.capture_level
+    }
+
+    /// Returns whether records at `level` replay the captured records.
+    #[inline]
+    pub(crate) fn triggers(&self, level: LogLevel) -> bool {
+        level >= self.config.trigger_level
+    }
+
+    /// Returns the request a record belongs to, or `None` if it is grouped by
+    /// thread.
+    fn request<'a>(
+        &self,
+        context: &'a HashMap<String, String>,
+        data: Option<&[(&'a str, &'a str)]>,
+    ) -> Option<&'a str> {
+        let key = self.config.key.as_deref()?;
+        data.unwrap_or_default()
+            .iter()
+            .rev()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+            .or_else(|| context.get(key).map(String::as_str))
+    }
+
+    fn shard(&self, request: &str) -> &Mutex<Shard> {
+        let index = self.hasher.hash_one(request) as usize % self.shards.len();
+        &self.shards[index]
+    }
+
+    /// Calls `f` with this thread's buffer, or returns `None` during thread
+    /// teardown.
+    fn with_thread_ring<R>(&self, f: impl FnOnce(&mut Ring) -> R) -> Option<R> {
+        THREAD_RINGS
+            .try_with(|rings| {
+                let mut rings = rings.borrow_mut();
+                let token = Arc::as_ptr(&self.token);
+                let index = match rings.iter().position(|(t, _)| t.as_ptr() == token) {
+                    Some(index) => index,
+                    None => {
+                        // Drop the buffers of recorders that no longer exist.
+                        rings.retain(|(t, _)| t.strong_count() > 0);
+                        rings.push((Arc::downgrade(&self.token), Ring::default()));
+                        rings.len() - 1
+                    }
+                };
+                f(&mut rings[index].1)
+            })
+            .ok()
+    }
+
+    /// Keeps a copy of a record that is below the logger's minimum level.
+    ///
+    /// Records grouped by thread go into a thread-local ring without locking;
+    /// records grouped by request lock only the shard of their key.
+    pub(crate) fn capture(
+        &self,
+        logger: &str,
+        level: LogLevel,
+        message: &str,
+        context: &HashMap<String, String>,
+        data: Option<&[(&str, &str)]>,
+        location: &'static Location<'static>,
+    ) {
+        let time = Utc::now();
+        let capacity = self.config.capacity.max(1);
+        let fill = |slot: &mut Slot| {
+            slot.time = time;
+            slot.level = Some(level);
+            slot.location = Some(location);
+            slot.text.clear();
+            slot.ends.clear();
+            slot.push_str(logger);
+            slot.push_str(message);
+            for (key, value) in context {
+                slot.push_str(key);
+                slot.push_str(value);
+            }
+            slot.context_len = context.len();
+            for (key, value) in data.unwrap_or_default() {
+                slot.push_str(key);
+                slot.push_str(value);
+            }
+        };
+
+        let Some(request) = self.request(context, data) else {
+            self.with_thread_ring(|ring| fill(ring.next_slot(capacity)));
+            return;
+        };
+
+        let mut shard = self.shard(request).lock();
+        shard.clock += 1;
+        let clock = shard.clock;
+        if !shard.rings.contains_key(request) && shard.rings.len() >= self.shard_limit {
+            let oldest = shard
+                .rings
+                .iter()
+                .min_by_key(|(_, keyed)| keyed.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                shard.rings.remove(&oldest);
+            }
+        }
+        let keyed = match shard.rings.get_mut(request) {
+            Some(keyed) => keyed,
+            None => shard.rings.entry(request.to_owned()).or_insert(Keyed {
+                ring: Ring::default(),
+                last_used: clock,
+            }),
+        };
+        keyed.last_used = clock;
+        fill(keyed.ring.next_slot(capacity));
+    }
+
+    /// Removes and returns the records captured for the thread or request of
+    /// a triggering record, oldest first and marked as backfilled.
+    pub(crate) fn take(
+        &self,
+        context: &HashMap<String, String>,
+        data: Option<&[(&str, &str)]>,
+    ) -> Vec<OwnedRecord> {
+        match self.request(context, data) {
+            Some(request) => match self.shard(request).lock().rings.remove(request) {
+                Some(mut keyed) => keyed.ring.take(),
+                None => Vec::new(),
+            },
+            None => self.with_thread_ring(Ring::take).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::MemoryOutput;
+    use crate::{Config, Logger};
+    use std::sync::Arc;
+
+    fn logger(recorder: FlightRecorderConfig) -> (Logger, Arc<MemoryOutput>) {
+        let config = Config::new()
+            .with_console_output(false)
+            .with_flight_recorder(recorder);
+        let logger = Logger::with_config("app", config);
+        let memory = Arc::new(MemoryOutput::new(100));
+        logger.add_output(Box::new(Arc::clone(&memory)));
+        (logger, memory)
+    }
+
+    fn messages(memory: &MemoryOutput) -> Vec<String> {
+        memory
+            .drain()
+            .iter()
+            .map(|r| match r.field("backfilled") {
+                Some(_) => format!("{}*", r.message),
+                None => r.message.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_replays_thread_records_before_error() {
+        let (logger, memory) = logger(FlightRecorderConfig::new().with_capacity(2));
+
+        logger.debug("one");
+        logger.debug("two");
+        logger.debug("three");
+        logger.info("visible");
+        let other = logger.clone();
+        std::thread::spawn(move || other.debug("other thread"))
+            .join()
+            .unwrap();
+        assert_eq!(messages(&memory), ["visible"]);
+
+        logger.error("failed");
+        assert_eq!(messages(&memory), ["two*", "three*", "failed"]);
+
+        // The buffer is emptied by the replay.
+        logger.error("failed again");
+        assert_eq!(messages(&memory), ["failed again"]);
+    }
+
+    #[test]
+    fn test_groups_by_context_key() {
+        let (logger, memory) = logger(FlightRecorderConfig::new().with_key("request_id"));
+
+        logger.debug_with("a1", &[("request_id", "a")]);
+        logger.debug_with("b1", &[("request_id", "b")]);
+        {
+            let _guard = logger.with_context("request_id", "a");
+            logger.debug("a2");
+        }
+        logger.error_with("a failed", &[("request_id", "a")]);
+
+        assert_eq!(messages(&memory), ["a1*", "a2*", "a failed"]);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_buffer() {
+        let recorder = FlightRecorder::new(
+            FlightRecorderConfig::new()
+                .with_key("request_id")
+                .with_max_buffers(2),
+        );
+        let ctx = HashMap::new();
+        let location = Location::caller();
+        for id in ["a", "b", "a", "c"] {
+            let data = [("request_id", id)];
+            recorder.capture("app", LogLevel::Debug, id, &ctx, Some(&data), location);
+        }
+
+        assert!(recorder.take(&ctx, Some(&[("request_id", "b")])).is_empty());
+        assert_eq!(recorder.take(&ctx, Some(&[("request_id", "a")])).len(), 2);
+        assert_eq!(recorder.take(&ctx, Some(&[("request_id", "c")])).len(), 1);
+    }
+
+    #[test]
+    fn test_thread_buffers_are_per_recorder() {
+        let first = FlightRecorder::new(FlightRecorderConfig::new().with_capacity(3));
+        let second = FlightRecorder::new(FlightRecorderConfig::new());
+        let ctx = HashMap::new();
+        let location = Location::caller();
+        for message in ["a", "b", "c", "d", "e"] {
+            first.capture("app", LogLevel::Debug, message, &ctx, None, location);
+        }
+        second.capture("app", LogLevel::Info, "other", &ctx, None, location);
+
+        let records = first.take(&ctx, None);
+        let messages: Vec<_> = records.iter().map(|r| r.message.as_str()).collect();
+        assert_eq!(messages, ["c", "d", "e"]);
+        assert!(first.take(&ctx, None).is_empty());
+
+        let records = second.take(&ctx, None);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, LogLevel::Info);
+    }
+}